@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackoffPolicy {
+    Linear(Duration),
+    Exponential(Duration),
+}
+
+impl BackoffPolicy {
+    // WorkManager caps backoff at 5 hours
+    pub const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60 * 60);
+
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = match *self {
+            BackoffPolicy::Linear(initial) => initial.saturating_mul(attempt.max(1)),
+            BackoffPolicy::Exponential(initial) => {
+                initial.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            }
+        };
+        delay.min(Self::MAX_BACKOFF)
+    }
+}
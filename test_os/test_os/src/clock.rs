@@ -0,0 +1,86 @@
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(any(target_os = "android", target_os = "ios"))]
+use std::{sync::Arc, thread, time::Instant};
+
+// Monotonic time source; `now` is measured from the clock's own origin
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+
+    fn elapsed_since(&self, earlier: Duration) -> Duration {
+        self.now().saturating_sub(earlier)
+    }
+}
+
+// Wall time only matters on device; host builds run everything on SimulatedClock
+#[cfg(any(target_os = "android", target_os = "ios"))]
+pub type SharedClock = Arc<dyn Clock>;
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+pub struct RealClock {
+    origin: Instant,
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+
+    pub fn shared() -> SharedClock {
+        Arc::new(Self::new())
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// Time only moves when `advance` or `sleep` is called, so waits return immediately
+#[derive(Default)]
+pub struct SimulatedClock {
+    now: Mutex<Duration>,
+}
+
+impl SimulatedClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    pub fn shared() -> Arc<SimulatedClock> {
+        Arc::new(Self::new())
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
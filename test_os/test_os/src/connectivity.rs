@@ -0,0 +1,217 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::job_scheduler::NetworkType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    None,
+    Wifi,
+    Cellular,
+}
+
+// The default network as ConnectivityManager's NetworkCapabilities or an NWPath describe it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkState {
+    pub transport: Transport,
+    pub metered: bool,
+    pub roaming: bool,
+    // Internet access confirmed, e.g. not stuck behind a captive portal
+    pub validated: bool,
+}
+
+impl NetworkState {
+    pub fn offline() -> Self {
+        Self { transport: Transport::None, metered: false, roaming: false, validated: false }
+    }
+
+    pub fn wifi() -> Self {
+        Self { transport: Transport::Wifi, metered: false, roaming: false, validated: true }
+    }
+
+    pub fn cellular() -> Self {
+        Self { transport: Transport::Cellular, metered: true, roaming: false, validated: true }
+    }
+
+    pub fn roaming(mut self) -> Self {
+        self.roaming = true;
+        self
+    }
+
+    pub fn metered(mut self) -> Self {
+        self.metered = true;
+        self
+    }
+
+    // Associated but not yet through the connectivity check
+    pub fn unvalidated(mut self) -> Self {
+        self.validated = false;
+        self
+    }
+
+    // NWPath.status == .satisfied
+    pub fn is_usable(&self) -> bool {
+        self.transport != Transport::None && self.validated
+    }
+
+    // What JobScheduler constraints see
+    pub fn network_type(&self) -> NetworkType {
+        if !self.is_usable() {
+            NetworkType::None
+        } else if self.metered || self.roaming {
+            NetworkType::Metered
+        } else {
+            NetworkType::Unmetered
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectivityEvent {
+    // A new default network, possibly not validated yet
+    Available(NetworkState),
+    // Same transport, different flags
+    CapabilitiesChanged { previous: NetworkState, current: NetworkState },
+    Lost(NetworkState),
+}
+
+pub type ObserverId = u64;
+
+// Returns false once it no longer wants events, e.g. a subscriber whose receiver was dropped
+type Observer = Arc<dyn Fn(&ConnectivityEvent) -> bool + Send + Sync>;
+
+struct MonitorState {
+    current: NetworkState,
+    observers: Vec<(ObserverId, Observer)>,
+    next_id: ObserverId,
+}
+
+// Tracks the default network and tells registered observers about every change. Cloning gives
+// another handle to the same monitor, so the platform side and the app can live on different threads
+#[derive(Clone)]
+pub struct ConnectivityMonitor {
+    shared: Arc<(Mutex<MonitorState>, Condvar)>,
+}
+
+impl ConnectivityMonitor {
+    pub fn new(initial: NetworkState) -> Self {
+        let state = MonitorState { current: initial, observers: Vec::new(), next_id: 1 };
+        Self { shared: Arc::new((Mutex::new(state), Condvar::new())) }
+    }
+
+    pub fn current(&self) -> NetworkState {
+        self.shared.0.lock().unwrap().current
+    }
+
+    // Like registerDefaultNetworkCallback, a network that is already up is reported right away
+    pub fn register<F>(&self, observer: F) -> ObserverId
+    where
+        F: Fn(&ConnectivityEvent) + Send + Sync + 'static,
+    {
+        self.attach(Arc::new(move |event: &ConnectivityEvent| {
+            observer(event);
+            true
+        }))
+    }
+
+    // Events as a channel, for code that handles them on its own thread or between steps. The
+    // observer behind it is removed on the first event after the receiver is dropped
+    pub fn subscribe(&self) -> Receiver<ConnectivityEvent> {
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        self.attach(Arc::new(move |event: &ConnectivityEvent| sender.lock().unwrap().send(*event).is_ok()));
+        receiver
+    }
+
+    fn attach(&self, observer: Observer) -> ObserverId {
+        let (id, current) = {
+            let mut state = self.shared.0.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.observers.push((id, Arc::clone(&observer)));
+            (id, state.current)
+        };
+        if current.transport != Transport::None {
+            observer(&ConnectivityEvent::Available(current));
+        }
+        id
+    }
+
+    pub fn unregister(&self, id: ObserverId) -> bool {
+        let mut state = self.shared.0.lock().unwrap();
+        let before = state.observers.len();
+        state.observers.retain(|(observer_id, _)| *observer_id != id);
+        state.observers.len() != before
+    }
+
+    pub fn observer_count(&self) -> usize {
+        self.shared.0.lock().unwrap().observers.len()
+    }
+
+    // Platform side: the default network changed. Returns the events that were delivered
+    pub fn set_state(&self, next: NetworkState) -> Vec<ConnectivityEvent> {
+        let (lock, changed) = &*self.shared;
+        let (events, observers) = {
+            let mut state = lock.lock().unwrap();
+            let previous = state.current;
+            state.current = next;
+            let observers: Vec<(ObserverId, Observer)> =
+                state.observers.iter().map(|(id, o)| (*id, Arc::clone(o))).collect();
+            (transition(previous, next), observers)
+        };
+        changed.notify_all();
+
+        // Outside the lock, so observers may query or even update the monitor
+        let mut detached = Vec::new();
+        for event in &events {
+            for (id, observer) in &observers {
+                if !detached.contains(id) && !observer(event) {
+                    detached.push(*id);
+                }
+            }
+        }
+        if !detached.is_empty() {
+            lock.lock().unwrap().observers.retain(|(id, _)| !detached.contains(id));
+        }
+        events
+    }
+
+    // Blocks until the network satisfies `condition`, instead of sleeping and polling
+    pub fn wait_until<F>(&self, timeout: Duration, condition: F) -> Option<NetworkState>
+    where
+        F: Fn(&NetworkState) -> bool,
+    {
+        let (lock, changed) = &*self.shared;
+        let deadline = Instant::now() + timeout;
+        let mut state = lock.lock().unwrap();
+        loop {
+            if condition(&state.current) {
+                return Some(state.current);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            state = changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+}
+
+fn transition(previous: NetworkState, next: NetworkState) -> Vec<ConnectivityEvent> {
+    if previous == next {
+        return Vec::new();
+    }
+    let mut events = Vec::new();
+    if previous.transport == next.transport {
+        events.push(ConnectivityEvent::CapabilitiesChanged { previous, current: next });
+        return events;
+    }
+    if previous.transport != Transport::None {
+        events.push(ConnectivityEvent::Lost(previous));
+    }
+    if next.transport != Transport::None {
+        events.push(ConnectivityEvent::Available(next));
+    }
+    events
+}
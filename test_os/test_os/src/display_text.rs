@@ -0,0 +1,119 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub const ELLIPSIS: &str = "…";
+
+const LRE: char = '\u{202A}';
+const RLE: char = '\u{202B}';
+const PDF: char = '\u{202C}';
+const LRO: char = '\u{202D}';
+const RLO: char = '\u{202E}';
+const LRI: char = '\u{2066}';
+const RLI: char = '\u{2067}';
+const FSI: char = '\u{2068}';
+const PDI: char = '\u{2069}';
+const EMOJI_PRESENTATION: char = '\u{FE0F}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Neutral,
+}
+
+// Terminal-style columns: CJK and emoji take two, combining marks and format characters none.
+// A whole cluster is at most two columns, so ZWJ families and flags count as one emoji
+pub fn grapheme_width(grapheme: &str) -> usize {
+    if grapheme.contains(EMOJI_PRESENTATION) {
+        2
+    } else {
+        grapheme.width().min(2)
+    }
+}
+
+pub fn display_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+// Cuts at a grapheme cluster boundary so the result plus the ellipsis fits `max_width` columns.
+// The ellipsis goes at the logical end, which renders on the left of RTL text, and bidi
+// embeddings or isolates left open by the cut are closed so they do not leak past it
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+    let budget = max_width.saturating_sub(display_width(ELLIPSIS));
+
+    let mut width = 0;
+    let mut end = 0;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        let w = grapheme_width(grapheme);
+        if width + w > budget {
+            break;
+        }
+        width += w;
+        end = offset + grapheme.len();
+    }
+
+    let kept = text[..end].trim_end();
+    let mut truncated = kept.to_string();
+    truncated.extend(unclosed_bidi(kept).iter().rev().map(|opener| closer(*opener)));
+    truncated.push_str(ELLIPSIS);
+    truncated
+}
+
+fn closer(opener: char) -> char {
+    if matches!(opener, LRI | RLI | FSI) { PDI } else { PDF }
+}
+
+// Openers still in effect at the end of `text`, outermost first
+fn unclosed_bidi(text: &str) -> Vec<char> {
+    let mut stack = Vec::new();
+    for c in text.chars() {
+        match c {
+            LRE | RLE | LRO | RLO | LRI | RLI | FSI => stack.push(c),
+            PDF if stack.last().is_some_and(|top| closer(*top) == PDF) => {
+                stack.pop();
+            }
+            // PDI also terminates embeddings opened inside the isolate
+            PDI => {
+                if let Some(isolate) = stack.iter().rposition(|top| closer(*top) == PDI) {
+                    stack.truncate(isolate);
+                }
+            }
+            _ => {}
+        }
+    }
+    stack
+}
+
+// Hebrew, Arabic, Syriac, Thaana, NKo and their presentation forms; Arabic digits and
+// harakat are not strong characters
+fn is_strong_rtl(c: char) -> bool {
+    let weak = matches!(c, '\u{0610}'..='\u{061A}' | '\u{064B}'..='\u{0669}' | '\u{0670}' | '\u{06D6}'..='\u{06ED}' | '\u{06F0}'..='\u{06F9}' | '\u{0591}'..='\u{05C7}');
+    !weak && matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFC}' | '\u{10800}'..='\u{10FFF}')
+}
+
+// First strong character outside isolates, as in rules P2/P3 of the bidi algorithm
+pub fn base_direction(text: &str) -> Direction {
+    let mut isolates = 0usize;
+    for c in text.chars() {
+        match c {
+            LRI | RLI | FSI => isolates += 1,
+            PDI => isolates = isolates.saturating_sub(1),
+            _ if isolates > 0 => {}
+            _ if is_strong_rtl(c) => return Direction::Rtl,
+            _ if c.is_alphabetic() => return Direction::Ltr,
+            _ => {}
+        }
+    }
+    Direction::Neutral
+}
+
+// Wraps user text for insertion into a template so its direction does not reorder the rest
+pub fn isolate(text: &str) -> String {
+    format!("{}{}{}", FSI, text, PDI)
+}
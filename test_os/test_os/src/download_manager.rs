@@ -0,0 +1,319 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::http_load::{HttpConnection, HttpError, HttpResponse, DEFAULT_TIMEOUT};
+use crate::backoff::BackoffPolicy;
+
+// Data is synced and progress recorded at least this often
+pub const CHECKPOINT_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadRequest {
+    pub server: SocketAddr,
+    pub path: String,
+    pub destination: PathBuf,
+    // Lowercase hex digest the finished file must have
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadError {
+    Http(HttpError),
+    Io(String),
+    // The partial download is discarded, the next attempt starts from zero
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::Http(error) => write!(f, "{}", error),
+            DownloadError::Io(message) => write!(f, "storage error: {}", message),
+            DownloadError::ChecksumMismatch { expected, actual } => {
+                write!(f, "SHA-256 mismatch: expected {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl From<HttpError> for DownloadError {
+    fn from(error: HttpError) -> Self {
+        DownloadError::Http(error)
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(error: io::Error) -> Self {
+        DownloadError::Io(error.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadReport {
+    pub total_bytes: u64,
+    // Already on disk from an earlier session
+    pub resumed_from: u64,
+    // Received in this session, including bytes that had to be fetched again
+    pub transferred: u64,
+    pub attempts: u32,
+    pub elapsed: Duration,
+}
+
+impl DownloadReport {
+    // Bytes per second actually moved in this session
+    pub fn throughput(&self) -> f64 {
+        self.transferred as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+// Sidecar next to the partial file, one "key value" per line. It is written only after the data
+// it describes has been synced, so the partial file can be longer than recorded but never shorter
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Progress {
+    path: String,
+    etag: Option<String>,
+    total: Option<u64>,
+    downloaded: u64,
+}
+
+impl Progress {
+    fn load(file: &Path) -> Option<Self> {
+        let text = fs::read_to_string(file).ok()?;
+        let mut progress = Progress::default();
+        for line in text.lines() {
+            let (key, value) = line.split_once(' ')?;
+            match key {
+                "path" => progress.path = value.to_string(),
+                "etag" => progress.etag = Some(value.to_string()),
+                "total" => progress.total = Some(value.parse().ok()?),
+                "downloaded" => progress.downloaded = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(progress)
+    }
+
+    fn save(&self, file: &Path) -> io::Result<()> {
+        let mut text = format!("path {}\ndownloaded {}\n", self.path, self.downloaded);
+        if let Some(etag) = &self.etag {
+            text.push_str(&format!("etag {}\n", etag));
+        }
+        if let Some(total) = self.total {
+            text.push_str(&format!("total {}\n", total));
+        }
+        let tmp = file.with_extension("progress.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, file)
+    }
+
+    fn restart(&mut self) {
+        self.etag = None;
+        self.total = None;
+        self.downloaded = 0;
+    }
+}
+
+// Appends to the partial file and checkpoints progress as bytes arrive
+struct PartWriter<'a> {
+    file: &'a mut File,
+    progress: &'a mut Progress,
+    sidecar: &'a Path,
+    unsaved: u64,
+    transferred: u64,
+}
+
+impl PartWriter<'_> {
+    fn checkpoint(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.progress.save(self.sidecar)?;
+        self.unsaved = 0;
+        Ok(())
+    }
+}
+
+impl Write for PartWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write_all(buf)?;
+        self.progress.downloaded += buf.len() as u64;
+        self.transferred += buf.len() as u64;
+        self.unsaved += buf.len() as u64;
+        if self.unsaved >= CHECKPOINT_BYTES {
+            self.checkpoint()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn is_transient(error: &DownloadError) -> bool {
+    matches!(
+        error,
+        DownloadError::Http(HttpError::Refused | HttpError::Timeout | HttpError::Reset | HttpError::Status(416 | 500..=599))
+    )
+}
+
+// "bytes start-end/total"
+fn content_range(response: &HttpResponse) -> Option<(u64, u64)> {
+    let range = response.header("content-range")?.strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+pub fn sha256_hex(file: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut reader = File::open(file)?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Resumable downloads over Range requests. All state lives next to the destination, so a new
+// manager in a restarted process picks up where the old one stopped
+pub struct DownloadManager {
+    backoff: BackoffPolicy,
+    max_attempts: u32,
+    timeout: Duration,
+}
+
+impl DownloadManager {
+    pub fn new(backoff: BackoffPolicy, max_attempts: u32) -> Self {
+        Self { backoff, max_attempts: max_attempts.max(1), timeout: DEFAULT_TIMEOUT }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn part_path(destination: &Path) -> PathBuf {
+        destination.with_extension("part")
+    }
+
+    pub fn progress_path(destination: &Path) -> PathBuf {
+        destination.with_extension("progress")
+    }
+
+    // Bytes safely on disk for an unfinished download
+    pub fn progress(request: &DownloadRequest) -> Option<u64> {
+        Progress::load(&Self::progress_path(&request.destination))
+            .filter(|p| p.path == request.path)
+            .map(|p| p.downloaded)
+    }
+
+    pub fn download(&self, request: &DownloadRequest) -> Result<DownloadReport, DownloadError> {
+        let start = Instant::now();
+        let part = Self::part_path(&request.destination);
+        let sidecar = Self::progress_path(&request.destination);
+
+        let mut file = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(&part)?;
+        let mut progress = Progress::load(&sidecar)
+            .filter(|p| p.path == request.path)
+            .filter(|p| file.metadata().is_ok_and(|m| m.len() >= p.downloaded))
+            .unwrap_or_else(|| Progress { path: request.path.clone(), ..Progress::default() });
+        // Bytes past the last checkpoint may not have reached the disk intact
+        file.set_len(progress.downloaded)?;
+
+        let mut report = DownloadReport { resumed_from: progress.downloaded, ..DownloadReport::default() };
+        let mut connection = HttpConnection::new(request.server, self.timeout, true);
+        loop {
+            report.attempts += 1;
+            let result = self.fetch(&mut connection, request, &mut file, &mut progress, &sidecar, &mut report);
+            match result {
+                Ok(()) => break,
+                Err(error) if is_transient(&error) && report.attempts < self.max_attempts => {
+                    thread::sleep(self.backoff.delay(report.attempts));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        drop(file);
+
+        let actual = sha256_hex(&part)?;
+        if actual != request.sha256.to_lowercase() {
+            let _ = fs::remove_file(&part);
+            let _ = fs::remove_file(&sidecar);
+            return Err(DownloadError::ChecksumMismatch { expected: request.sha256.clone(), actual });
+        }
+        fs::rename(&part, &request.destination)?;
+        let _ = fs::remove_file(&sidecar);
+
+        report.total_bytes = progress.downloaded;
+        report.elapsed = start.elapsed();
+        Ok(report)
+    }
+
+    fn fetch(
+        &self,
+        connection: &mut HttpConnection,
+        request: &DownloadRequest,
+        file: &mut File,
+        progress: &mut Progress,
+        sidecar: &Path,
+        report: &mut DownloadReport,
+    ) -> Result<(), DownloadError> {
+        if progress.downloaded > 0 && progress.total == Some(progress.downloaded) {
+            return Ok(());
+        }
+        let range = format!("bytes={}-", progress.downloaded);
+        let mut headers = Vec::new();
+        if progress.downloaded > 0 {
+            headers.push(("Range", range.as_str()));
+            // Only resume if the server still has the same file, otherwise it sends all of it
+            if let Some(etag) = &progress.etag {
+                headers.push(("If-Range", etag.as_str()));
+            }
+        }
+        let response = connection.send("GET", &request.path, &headers, &[])?;
+
+        match response.status {
+            206 if content_range(&response).is_some_and(|(start, _)| start == progress.downloaded) => {
+                progress.total = content_range(&response).map(|(_, total)| total);
+            }
+            200 => {
+                progress.restart();
+                progress.etag = response.header("etag").map(str::to_string);
+                progress.total = response.header("content-length").and_then(|len| len.parse().ok());
+                file.set_len(0)?;
+            }
+            416 => {
+                // Our offset is past the end: whatever is on disk is not a prefix of this file
+                progress.restart();
+                file.set_len(0)?;
+                progress.save(sidecar)?;
+                return Err(HttpError::Status(416).into());
+            }
+            206 => return Err(HttpError::Protocol("Content-Range does not continue the partial file".to_string()).into()),
+            status => return Err(HttpError::Status(status).into()),
+        }
+        file.seek(SeekFrom::Start(progress.downloaded))?;
+
+        let mut writer = PartWriter { file, progress, sidecar, unsaved: 0, transferred: 0 };
+        let result = connection.read_body(&response, &mut writer);
+        // Keep what arrived before a failure; the next attempt asks only for the rest
+        writer.checkpoint()?;
+        report.transferred += writer.transferred;
+        result?;
+
+        if progress.total.is_some_and(|total| total != progress.downloaded) {
+            return Err(HttpError::Reset.into());
+        }
+        Ok(())
+    }
+}
@@ -2,13 +2,63 @@
 #![cfg(any(target_os = "android", target_os = "ios"))]
 
 use std::time::{Duration, Instant};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write, Read, Seek, SeekFrom};
-use std::sync::{Arc, Mutex, Barrier};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, Barrier};
 use std::thread;
 use std::collections::HashMap;
 
+use serde_json::json;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod backoff;
+mod clock;
+mod connectivity;
+mod display_text;
+mod fusion;
+mod geofence;
+mod input_session;
+mod job_scheduler;
+mod lifecycle;
+mod location_trace;
+mod nmea;
+mod notifications;
+mod offline_sync;
+mod push_gateway;
+mod saved_state;
+mod sensor_timing;
+mod sensors;
+mod touch;
+
+use backoff::BackoffPolicy;
+use clock::{Clock, RealClock, SharedClock, SimulatedClock};
+use connectivity::{ConnectivityEvent, ConnectivityMonitor, NetworkState, Transport};
+use display_text::{display_width, Direction};
+use geofence::{Geofence, GeofenceEngine, GeofenceError, GeofenceEvent, GeofenceTransition};
+use fusion::{ComplementaryFilter, MotionProfile, Quaternion, SampleTiming};
+use input_session::{InputSession, LifecycleCommand, SessionEvent, SessionRecorder};
+
+use job_scheduler::{
+    DeviceState, JobResult, JobScheduler, JobSpec, JobStatus, NetworkRequirement,
+    NetworkType,
+};
+use location_trace::{LocationEvent, LocationFix, LocationTrace, PlaybackConfig, TracePoint};
+use nmea::{DeviceConfig, FixDecoder, NmeaDevice, NmeaError, Rmc, SatelliteInView, Sentence};
+use notifications::{
+    Delivery, DndMode, DndRule, Importance, Interruption, Notification, NotificationChannel, NotificationError,
+    NotificationManager,
+};
+use offline_sync::{
+    Change, ConflictResolver, FieldMerge, Fields, LastWriterWins, MockSyncServer, Outbox, ServerWins, SyncReport,
+};
+use push_gateway::{GatewayStats, PushError, PushGateway, PushMessage, PushReceiver, PushSender};
+use lifecycle::{AppLifecycle, LifecycleError, LifecycleEvent, LifecyclePlatform, LifecycleState};
+use saved_state::{BundleError, BundleValue, SavedStateBundle};
+use sensor_timing::RateTolerance;
+use sensors::{NoiseModel, SensorConfig, SensorKind, SensorSource, SimulatedSensor, STANDARD_GRAVITY};
+use touch::{Gesture, GestureRecognizer, MotionEvent, SwipeDirection};
 
 #[cfg(target_os = "android")]
 mod android {
@@ -90,6 +140,14 @@ fn test_mobile_app_lifecycle_e2e() {
     println!("=== MOBILE APP LIFECYCLE E2E TEST ===");
     
 
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut lifecycle = AppLifecycle::new(LifecyclePlatform::current());
+    {
+        let events = Arc::clone(&events);
+        lifecycle.on_event(move |event| events.lock().unwrap().push(event.clone()));
+    }
+    lifecycle.move_to_foreground().expect("App should reach foreground");
+
     let app_dir = get_mobile_app_dir();
     fs::create_dir_all(&app_dir).expect("Failed to create app directory");
     
@@ -125,16 +183,325 @@ fn test_mobile_app_lifecycle_e2e() {
    
     test_background_operations();
     
+    // background -> save state -> low-memory kill -> relaunch
+    lifecycle.move_to_background().expect("App should move to background");
+    assert!(!lifecycle.is_foreground(), "App should not be in foreground");
+    
+    let mut saved = SavedStateBundle::new();
+    saved.put_string_map("user_data", &user_data);
+    saved.put_string("config", config_data);
+    let saved_size = lifecycle.save_instance_state(&saved).expect("Failed to save instance state");
+    println!("Saved instance state: {} bytes", saved_size);
+    
+    lifecycle.kill_process().expect("System should kill a background app");
+    assert!(!lifecycle.is_process_alive(), "Process should be dead after kill");
+    
+    let restored = lifecycle.relaunch().expect("App should relaunch after kill");
+    assert!(restored, "Relaunch after process death should restore state");
+    assert!(lifecycle.was_restored(), "Lifecycle should report the restore");
+    
+    let restored_state = lifecycle
+        .restored_state()
+        .expect("Saved state should decode")
+        .expect("Saved state should survive process death");
+    assert_eq!(
+        restored_state.get_string_map("user_data"),
+        Some(user_data.clone()),
+        "user_data should survive process death"
+    );
+    assert_eq!(
+        restored_state.get_string("config"),
+        Some(config_data),
+        "Config should survive process death"
+    );
+    assert_eq!(lifecycle.launches(), 2, "App should have launched twice");
+    assert!(lifecycle.is_foreground(), "Relaunched app should be in foreground");
+    
+    {
+        let events = events.lock().unwrap();
+        assert!(
+            events.iter().any(|e| matches!(e, LifecycleEvent::ProcessKilled { .. })),
+            "Callbacks should see the process kill"
+        );
+        assert!(
+            events.contains(&LifecycleEvent::Relaunched { restored: true }),
+            "Callbacks should see the restoring relaunch"
+        );
+        println!("Lifecycle events delivered: {}", events.len());
+    }
+    
    
     test_app_update_scenario(&app_dir);
     
     
     cleanup_test_data(&app_dir);
     
+    lifecycle.finish().expect("App should finish cleanly");
+    let restored = lifecycle.relaunch().expect("App should relaunch after finish");
+    assert!(!restored, "Relaunch after finish should start fresh");
+    assert!(
+        lifecycle.restored_state().expect("Saved state should decode").is_none(),
+        "Finished app should not get its old state back"
+    );
+    
     println!("✓ Mobile app lifecycle E2E test completed");
 }
 
 
+#[test]
+fn test_app_lifecycle_transitions_e2e() {
+    println!("=== APP LIFECYCLE TRANSITIONS E2E TEST ===");
+    
+    // Android: Created -> Started -> Resumed -> Paused -> Stopped -> Destroyed
+    let mut android = AppLifecycle::new(LifecyclePlatform::Android);
+    android.move_to_foreground().expect("Android app should resume");
+    assert_eq!(android.state(), LifecycleState::Resumed);
+    
+    assert_eq!(
+        android.transition(LifecycleState::Stopped),
+        Err(LifecycleError::IllegalTransition {
+            from: LifecycleState::Resumed,
+            to: LifecycleState::Stopped,
+        }),
+        "Resumed activity must pause before stopping"
+    );
+    assert_eq!(
+        android.kill_process(),
+        Err(LifecycleError::KillInForeground(LifecycleState::Resumed)),
+        "Foreground process should not be killed"
+    );
+    
+    android.transition(LifecycleState::Paused).expect("Resumed -> Paused");
+    android.transition(LifecycleState::Stopped).expect("Paused -> Stopped");
+    android.transition(LifecycleState::Started).expect("Stopped -> Started (restart)");
+    android.move_to_background().expect("Started -> Stopped");
+    android.transition(LifecycleState::Destroyed).expect("Stopped -> Destroyed");
+    assert!(
+        android.transition(LifecycleState::Resumed).is_err(),
+        "Destroyed activity cannot resume"
+    );
+    
+    // iOS: NotRunning -> Inactive -> Active -> Inactive -> Background -> Suspended
+    let mut ios = AppLifecycle::new(LifecyclePlatform::Ios);
+    ios.move_to_foreground().expect("iOS app should become active");
+    assert_eq!(ios.state(), LifecycleState::Active);
+    assert!(
+        ios.transition(LifecycleState::Suspended).is_err(),
+        "Active app cannot be suspended directly"
+    );
+    
+    ios.move_to_background().expect("iOS app should enter background");
+    ios.suspend().expect("Background app should be suspended");
+    ios.kill_process().expect("Suspended app should be killable");
+    assert_eq!(
+        ios.transition(LifecycleState::Background),
+        Err(LifecycleError::ProcessDead),
+        "Dead process cannot transition"
+    );
+    
+    assert_eq!(ios.relaunch(), Ok(true), "iOS should restore state after kill");
+    assert_eq!(ios.state(), LifecycleState::Active);
+    assert_eq!(ios.relaunch(), Err(LifecycleError::ProcessAlive));
+    
+    let transitions = ios
+        .history()
+        .iter()
+        .filter(|e| matches!(e, LifecycleEvent::Transition { .. }))
+        .count();
+    println!("iOS transitions recorded: {}", transitions);
+    assert_eq!(transitions, 7, "Unexpected iOS transition count");
+    
+    println!("✓ App lifecycle transitions E2E test completed");
+}
+
+
+#[test]
+fn test_saved_state_bundle_e2e() {
+    println!("=== SAVED INSTANCE STATE E2E TEST ===");
+    
+    let mut bundle = SavedStateBundle::with_limit(4 * 1024);
+    bundle.put_bool("logged_in", true);
+    bundle.put_int("scroll_position", 1234);
+    bundle.put_float("zoom", 1.5);
+    bundle.put_string("draft", "Черновик сообщения");
+    bundle.put_bytes("thumbnail", &[0xAB; 256]);
+    
+    let parcel = bundle.to_parcel().expect("Small bundle should fit");
+    let decoded = SavedStateBundle::from_parcel(&parcel, bundle.limit()).expect("Parcel should decode");
+    assert_eq!(decoded, bundle, "Bundle should round-trip through a parcel");
+    println!("Parcel size: {} bytes (limit {})", parcel.len(), bundle.limit());
+    
+    assert_eq!(decoded.len(), 5);
+    assert!(!decoded.is_empty() && SavedStateBundle::new().is_empty());
+    assert!(decoded.contains_key("draft") && !decoded.contains_key("bitmap"));
+    assert_eq!(decoded.get_bool("logged_in"), Some(true));
+    assert_eq!(decoded.get_int("scroll_position"), Some(1234));
+    assert_eq!(decoded.get_float("zoom"), Some(1.5));
+    assert_eq!(decoded.get_bytes("thumbnail"), Some(&[0xAB; 256][..]));
+    assert_eq!(decoded.get("draft"), Some(&BundleValue::Str("Черновик сообщения".to_string())));
+    assert_eq!(decoded.get_int("zoom"), None, "Typed getter should not coerce other types");
+    
+    // При восстановлении действует тот же лимит, что и при сохранении
+    assert_eq!(
+        SavedStateBundle::from_parcel(&parcel, 64),
+        Err(BundleError::TransactionTooLarge { size: parcel.len(), limit: 64 }),
+        "Oversized parcel should not restore"
+    );
+    
+    bundle.put_bytes("bitmap", &vec![0u8; 8 * 1024]);
+    let size = bundle.parcel_size();
+    assert_eq!(
+        bundle.to_parcel(),
+        Err(BundleError::TransactionTooLarge { size, limit: 4 * 1024 }),
+        "Oversized bundle should be rejected"
+    );
+    
+    let mut lifecycle = AppLifecycle::new(LifecyclePlatform::Android);
+    lifecycle.move_to_foreground().expect("App should reach foreground");
+    lifecycle.move_to_background().expect("App should move to background");
+    assert_eq!(
+        lifecycle.save_instance_state(&bundle),
+        Err(LifecycleError::SavedState(BundleError::TransactionTooLarge { size, limit: 4 * 1024 })),
+        "Lifecycle should surface TransactionTooLarge"
+    );
+    
+    lifecycle.kill_process().expect("System should kill a background app");
+    assert_eq!(lifecycle.relaunch(), Ok(true));
+    assert!(
+        lifecycle.restored_state().expect("Saved state should decode").is_none(),
+        "Rejected save should not be restored"
+    );
+    
+    let truncated = &parcel[..parcel.len() - 3];
+    assert!(
+        matches!(SavedStateBundle::from_parcel(truncated, 4 * 1024), Err(BundleError::Corrupt(_))),
+        "Truncated parcel should be reported as corrupt"
+    );
+    
+    println!("✓ Saved instance state E2E test completed");
+}
+
+
+#[test]
+fn test_background_job_scheduler_e2e() {
+    println!("=== BACKGROUND JOB SCHEDULER E2E TEST ===");
+    
+    let on_battery = DeviceState {
+        charging: false,
+        network: NetworkType::Metered,
+        idle: false,
+        storage_low: false,
+    };
+    let clock = SimulatedClock::shared();
+    let mut scheduler = JobScheduler::with_clock(on_battery, clock.clone());
+    
+    let mut backup = JobSpec::new("photo_backup");
+    backup.constraints.requires_charging = true;
+    backup.constraints.network = NetworkRequirement::Unmetered;
+    let backup_id = scheduler.schedule(backup, |_| JobResult::Success);
+    
+    let mut sync = JobSpec::new("inbox_sync");
+    sync.constraints.network = NetworkRequirement::Connected;
+    let sync_id = scheduler.schedule(sync, |_| JobResult::Success);
+    
+    let mut maintenance = JobSpec::new("db_vacuum");
+    maintenance.constraints.requires_idle = true;
+    maintenance.deadline = Some(Duration::from_millis(50));
+    let maintenance_id = scheduler.schedule(maintenance, |_| JobResult::Success);
+    
+    let attempt_times = Arc::new(Mutex::new(Vec::new()));
+    let mut flaky = JobSpec::new("flaky_upload");
+    flaky.max_retries = 3;
+    flaky.backoff = BackoffPolicy::Exponential(Duration::from_millis(10));
+    let flaky_id = {
+        let attempt_times = Arc::clone(&attempt_times);
+        let clock = Arc::clone(&clock);
+        scheduler.schedule(flaky, move |_| {
+            attempt_times.lock().unwrap().push(clock.now());
+            JobResult::Retry
+        })
+    };
+    
+    let mut report = JobSpec::new("analytics_report");
+    report.initial_delay = Duration::from_secs(60);
+    let report_id = scheduler.schedule(report, |_| JobResult::Success);
+    
+    let corrupt_id = scheduler.schedule(JobSpec::new("corrupt_upload"), |_| JobResult::Failure);
+    
+    // На батарее и мобильной сети: только sync, corrupt и первая попытка flaky
+    assert_eq!(scheduler.run_pending(), 3);
+    assert_eq!(scheduler.status(sync_id), Some(JobStatus::Succeeded));
+    assert_eq!(scheduler.status(corrupt_id), Some(JobStatus::Failed));
+    assert_eq!(scheduler.attempts(corrupt_id), 1, "Permanent failure should not be retried");
+    assert_eq!(scheduler.status(backup_id), Some(JobStatus::Pending));
+    assert_eq!(scheduler.status(maintenance_id), Some(JobStatus::Pending));
+    
+    assert!(scheduler.cancel(report_id), "Pending job should be cancellable");
+    assert!(!scheduler.cancel(report_id), "Cancelled job cannot be cancelled twice");
+    assert!(!scheduler.cancel(sync_id), "Finished job cannot be cancelled");
+    
+    scheduler.set_device_state(DeviceState {
+        charging: true,
+        network: NetworkType::Unmetered,
+        ..scheduler.device_state()
+    });
+    scheduler.run_pending();
+    assert_eq!(scheduler.status(backup_id), Some(JobStatus::Succeeded));
+    assert!(!scheduler.ran_past_deadline(backup_id));
+    assert_eq!(scheduler.status(maintenance_id), Some(JobStatus::Pending), "Device is not idle yet");
+    
+    // Не idle, но у maintenance есть дедлайн, так что будильник есть всегда
+    while scheduler.pending_count() > 0 {
+        clock.sleep(scheduler.next_wakeup().expect("Pending jobs with a deadline need a wakeup"));
+        scheduler.run_pending();
+    }
+    
+    assert_eq!(scheduler.status(maintenance_id), Some(JobStatus::Succeeded));
+    assert!(scheduler.ran_past_deadline(maintenance_id), "Maintenance should run on deadline");
+    
+    assert_eq!(scheduler.status(flaky_id), Some(JobStatus::Failed));
+    assert_eq!(scheduler.attempts(flaky_id), 4, "Flaky job should run 1 + 3 retries");
+    
+    let attempt_times = attempt_times.lock().unwrap();
+    for (i, pair) in attempt_times.windows(2).enumerate() {
+        let gap = pair[1] - pair[0];
+        println!("Retry {} after {:?}", i + 1, gap);
+        assert_eq!(gap, Duration::from_millis(10 << i), "Retry {} ignored its backoff", i + 1);
+    }
+    
+    // Линейный backoff растёт на шаг за попытку и упирается в тот же потолок
+    let linear = BackoffPolicy::Linear(Duration::from_secs(30));
+    let delays: Vec<Duration> = (1..=3).map(|attempt| linear.delay(attempt)).collect();
+    assert_eq!(delays, [30, 60, 90].map(Duration::from_secs));
+    assert_eq!(linear.delay(u32::MAX), BackoffPolicy::MAX_BACKOFF);
+    
+    assert_eq!(scheduler.status(report_id), Some(JobStatus::Cancelled));
+    assert!(
+        scheduler.run_log().iter().all(|&(id, _, _)| id != report_id),
+        "Cancelled job should never run"
+    );
+    for &(id, attempt, result) in scheduler.run_log() {
+        println!("{} attempt {}: {:?}", scheduler.name(id).unwrap(), attempt, result);
+    }
+    
+    // Ждёт только idle: будильник на дедлайн, а без дедлайна будить незачем
+    let mut cleanup = JobSpec::new("cache_cleanup");
+    cleanup.constraints.requires_idle = true;
+    cleanup.deadline = Some(Duration::from_secs(5));
+    let cleanup_id = scheduler.schedule(cleanup, |_| JobResult::Success);
+    let mut index = JobSpec::new("search_index");
+    index.constraints.requires_idle = true;
+    scheduler.schedule(index, |_| JobResult::Success);
+    assert_eq!(scheduler.next_wakeup(), Some(Duration::from_secs(5)));
+    
+    assert_eq!(scheduler.cancel_all(), 2);
+    assert_eq!(scheduler.status(cleanup_id), Some(JobStatus::Cancelled));
+    assert_eq!(scheduler.next_wakeup(), None);
+    
+    println!("✓ Background job scheduler E2E test completed");
+}
+
+
 #[test]
 fn test_touch_gestures_e2e() {
     println!("=== TOUCH GESTURES E2E TEST ===");
@@ -148,14 +515,27 @@ fn test_touch_gestures_e2e() {
         ("long_press", Duration::from_millis(500)),
     ];
     
+ 
+    let recognizer = GestureRecognizer::default();
+    
     for (gesture_name, expected_max_latency) in gestures {
+        let events = simulate_gesture(gesture_name);
         let start = Instant::now();
         
         
-        simulate_gesture(gesture_name);
+        let recognized = recognizer.recognize(&events);
         
         let latency = start.elapsed();
-        println!("Gesture '{}' latency: {:?}", gesture_name, latency);
+        println!("Gesture '{}' ({} events) latency: {:?}", gesture_name, events.len(), latency);
+        
+        assert_eq!(recognized.len(), 1, "Gesture '{}' recognized as {:?}", gesture_name, recognized);
+        assert_eq!(
+            recognized[0].name(),
+            gesture_name,
+            "Gesture '{}' misclassified: {:?}",
+            gesture_name,
+            recognized[0]
+        );
         
         
         assert!(
@@ -168,34 +548,193 @@ fn test_touch_gestures_e2e() {
     }
     
    
+    let multitouch_events = simulate_multitouch(2); // 2 пальца
     let multitouch_start = Instant::now();
-    simulate_multitouch(2); // 2 пальца
+    let multitouch = recognizer.recognize(&multitouch_events);
     let multitouch_latency = multitouch_start.elapsed();
     
+    assert_eq!(multitouch, vec![Gesture::MultiTouch { fingers: 2 }]);
+    
+    // Направление свайпа и масштаб пинча
+    let swipe_left = touch::swipe_stream((900.0, 960.0), (100.0, 980.0), Duration::from_millis(150), Duration::ZERO);
+    match recognizer.recognize(&swipe_left).as_slice() {
+        [Gesture::Swipe { direction, velocity, .. }] => {
+            assert_eq!(*direction, SwipeDirection::Left);
+            assert!(*velocity > 1000.0, "Fling velocity too low: {:.0}px/s", velocity);
+        }
+        other => panic!("Expected a single swipe, got {:?}", other),
+    }
+    
+    let pinch_in = touch::pinch_stream((540.0, 960.0), 600.0, 200.0, Duration::from_millis(250), Duration::ZERO);
+    match recognizer.recognize(&pinch_in).as_slice() {
+        [Gesture::Pinch { scale }] => assert!(*scale < 0.5, "Pinch-in scale: {:.2}", scale),
+        other => panic!("Expected a single pinch, got {:?}", other),
+    }
+    
+    // Два тапа с большим интервалом не должны склеиваться в double tap
+    let mut slow_taps = touch::tap_stream(300.0, 300.0, Duration::ZERO);
+    slow_taps.extend(touch::tap_stream(300.0, 300.0, Duration::from_millis(800)));
+    let names: Vec<&str> = recognizer.recognize(&slow_taps).iter().map(|g| g.name()).collect();
+    assert_eq!(names, vec!["tap", "tap"]);
+    
     assert!(
         multitouch_latency < Duration::from_millis(250),
         "Multitouch too slow: {:?}",
         multitouch_latency
     );
     
+    let session = record_touch_gestures_session(SimulatedClock::shared());
+    let artifact = save_session_artifact("test_touch_gestures_e2e", &session);
+    println!("Touch session saved to {:?} ({} events)", artifact, session.events.len());
+    
     println!("✓ Touch gestures E2E test completed");
 }
 
-fn simulate_gesture(gesture: &str) {
+const GESTURE_SCENARIO: [&str; 5] = ["tap", "double_tap", "swipe", "pinch", "long_press"];
+
+fn record_touch_gestures_session(clock: SharedClock) -> InputSession {
+    let mut recorder = SessionRecorder::new("touch_gestures", Arc::clone(&clock));
     
-    thread::sleep(Duration::from_millis(match gesture {
-        "tap" => 10,
-        "double_tap" => 15,
-        "swipe" => 20,
-        "pinch" => 25,
-        "long_press" => 30,
-        _ => 5,
-    }));
+    for gesture_name in GESTURE_SCENARIO {
+        recorder.record_touch_stream(&simulate_gesture(gesture_name));
+        // пауза длиннее double tap timeout, чтобы жесты не склеивались
+        clock.sleep(Duration::from_millis(500));
+    }
+    recorder.record_touch_stream(&simulate_multitouch(2));
+    
+    recorder.finish()
 }
 
-fn simulate_multitouch(fingers: u8) {
+// Файл на каждый тест: тесты идут параллельно и не должны перезаписывать чужие сессии
+fn save_session_artifact(test: &str, session: &InputSession) -> PathBuf {
+    let sessions_dir = get_mobile_cache_dir().join("input_sessions");
+    fs::create_dir_all(&sessions_dir).expect("Failed to create sessions dir");
+    
+    let path = sessions_dir.join(format!("{}_{}.json", session.name, test));
+    session.save(&path).expect("Failed to save input session");
+    path
+}
+
+
+#[test]
+fn test_input_session_replay_e2e() {
+    println!("=== INPUT SESSION REPLAY E2E TEST ===");
+    
+    let recorded = record_touch_gestures_session(SimulatedClock::shared());
+    let path = save_session_artifact("test_input_session_replay_e2e", &recorded);
+    let session = InputSession::load(&path).expect("Failed to load input session");
+    assert_eq!(session, recorded, "Session should round-trip through JSON");
+    
+    let mut expected: Vec<&str> = GESTURE_SCENARIO.to_vec();
+    expected.push("multitouch");
+    let recognizer = GestureRecognizer::default();
+    
+    // Мгновенное воспроизведение на симулированных часах
+    let clock = SimulatedClock::new();
+    let mut replayed = Vec::new();
+    let stats = input_session::replay(&session, &clock, 1.0, |timed| {
+        if let SessionEvent::Touch(motion) = &timed.event {
+            replayed.push(motion.clone());
+        }
+    });
+    assert_eq!(stats.events, session.events.len());
+    assert_eq!(stats.elapsed, session.duration(), "Replay should keep the original timing");
+    assert_eq!(stats.max_lag, Duration::ZERO);
+    
+    let names: Vec<&str> = recognizer.recognize(&replayed).iter().map(|g| g.name()).collect();
+    assert_eq!(names, expected, "Replayed gestures misclassified");
+    
+    // Ускоренное воспроизведение в реальном времени
+    let speed = 20.0;
+    let mut replayed = Vec::new();
+    let stats = input_session::replay(&session, &RealClock::new(), speed, |timed| {
+        if let SessionEvent::Touch(motion) = &timed.event {
+            replayed.push(motion.clone());
+        }
+    });
+    let scaled = session.duration().div_f64(speed);
+    println!("Real-time replay at {}x: {:?} (expected {:?}, max lag {:?})", speed, stats.elapsed, scaled, stats.max_lag);
+    assert!(stats.elapsed >= scaled, "Replay finished early: {:?}", stats.elapsed);
+    assert!(
+        stats.elapsed < scaled + Duration::from_millis(200),
+        "Time-scaled replay too slow: {:?}",
+        stats.elapsed
+    );
+    let names: Vec<&str> = recognizer.recognize(&replayed).iter().map(|g| g.name()).collect();
+    assert_eq!(names, expected, "Time-scaled replay changed gesture classification");
+    
+    // Повороты, клавиши и жизненный цикл через симуляторы
+    let rotation_session = record_screen_rotation_session(SimulatedClock::shared());
+    let mut lifecycle = AppLifecycle::new(LifecyclePlatform::current());
+    let mut rotations = 0;
+    let mut keys = 0;
+    input_session::replay(&rotation_session, &SimulatedClock::new(), 1.0, |timed| match &timed.event {
+        SessionEvent::Rotation { orientation, width, height } => {
+            simulate_screen_rotation(orientation, *width, *height);
+            assert!(verify_content_layout(*width, *height), "Layout broken after replayed rotation");
+            rotations += 1;
+        }
+        SessionEvent::Key { .. } => keys += 1,
+        SessionEvent::Lifecycle(command) => {
+            let result = match command {
+                LifecycleCommand::Foreground => lifecycle.move_to_foreground(),
+                LifecycleCommand::Background => lifecycle.move_to_background(),
+                LifecycleCommand::Kill => lifecycle.kill_process(),
+                LifecycleCommand::Relaunch => lifecycle.relaunch().map(|_| ()),
+            };
+            result.unwrap_or_else(|e| panic!("Replayed {:?} failed: {}", command, e));
+        }
+        SessionEvent::Touch(_) => {}
+    });
+    
+    assert_eq!(rotations, 4, "All recorded rotations should replay");
+    assert_eq!(keys, 2, "BACK down/up should replay");
+    assert!(lifecycle.was_restored(), "Replayed kill/relaunch should restore the app");
+    
+    let corrupted = r#"{"version": 1, "name": "bad", "events": [{"t_us": 5, "type": "teleport"}]}"#;
+    let value: serde_json::Value = serde_json::from_str(corrupted).unwrap();
+    assert!(InputSession::from_json(&value).is_err(), "Unknown event types should be rejected");
     
-    thread::sleep(Duration::from_millis(20 * fingers as u64));
+    println!("✓ Input session replay E2E test completed");
+}
+
+fn record_screen_rotation_session(clock: SharedClock) -> InputSession {
+    let mut recorder = SessionRecorder::new("screen_rotation", Arc::clone(&clock));
+    
+    recorder.record_lifecycle(LifecycleCommand::Foreground);
+    for (orientation_name, (width, height)) in ROTATION_SCENARIO {
+        clock.sleep(Duration::from_millis(200));
+        recorder.record_rotation(orientation_name, width, height);
+    }
+    
+    recorder.record_key("KEYCODE_BACK", true);
+    clock.sleep(Duration::from_millis(80));
+    recorder.record_key("KEYCODE_BACK", false);
+    
+    recorder.record_lifecycle(LifecycleCommand::Background);
+    clock.sleep(Duration::from_secs(30));
+    recorder.record_lifecycle(LifecycleCommand::Kill);
+    recorder.record_lifecycle(LifecycleCommand::Relaunch);
+    
+    recorder.finish()
+}
+
+fn simulate_gesture(gesture: &str) -> Vec<MotionEvent> {
+    
+    let start = Duration::ZERO;
+    match gesture {
+        "tap" => touch::tap_stream(540.0, 960.0, start),
+        "double_tap" => touch::double_tap_stream(540.0, 960.0, start),
+        "swipe" => touch::swipe_stream((540.0, 1600.0), (540.0, 400.0), Duration::from_millis(180), start),
+        "pinch" => touch::pinch_stream((540.0, 960.0), 300.0, 700.0, Duration::from_millis(250), start),
+        "long_press" => touch::long_press_stream(540.0, 960.0, start),
+        _ => Vec::new(),
+    }
+}
+
+fn simulate_multitouch(fingers: u8) -> Vec<MotionEvent> {
+    
+    touch::multitouch_stream(fingers as u32, (300.0, 960.0), Duration::ZERO)
 }
 
 
@@ -203,34 +742,130 @@ fn simulate_multitouch(fingers: u8) {
 fn test_sensors_e2e() {
     println!("=== SENSORS E2E TEST ===");
     
+    let clock = SimulatedClock::new();
+    
     #[cfg(target_os = "android")]
     {
         use jni::JNIEnv;
         
        
-        let sensor_data = simulate_sensor_data("accelerometer", 100);
+        let sensor_data = simulate_sensor_data(&clock, "accelerometer", 100);
         assert_eq!(sensor_data.len(), 100, "Should have 100 sensor readings");
         
-        
+        // Телефон лежит на столе: вдоль оси z только гравитация
         for (i, data) in sensor_data.iter().enumerate() {
+            let magnitude = (data.x * data.x + data.y * data.y + data.z * data.z).sqrt();
             assert!(
-                data.x.abs() < 20.0 && data.y.abs() < 20.0 && data.z.abs() < 20.0,
-                "Sensor data out of bounds at index {}: {:?}",
+                (magnitude - STANDARD_GRAVITY).abs() < 0.3 && data.x.abs() < 0.3 && data.y.abs() < 0.3,
+                "Accelerometer reading off gravity at index {}: {:?}",
                 i,
                 data
             );
         }
+        
+        let timestamps: Vec<Duration> = sensor_data.iter().map(|d| Duration::from_millis(d.timestamp)).collect();
+        let report = sensor_timing::analyze("accelerometer", &timestamps, 100.0);
+        print!("{}", report);
+        if let Err(violations) = report.check(&RateTolerance::new(100.0)) {
+            panic!("Accelerometer timing: {}", violations.join("; "));
+        }
     }
     
     #[cfg(target_os = "ios")]
     {
        
-        let motion_data = simulate_core_motion_data(50);
+        let motion_data = simulate_core_motion_data(&clock, 50);
         assert!(!motion_data.is_empty(), "Should have motion data");
+        assert!(
+            motion_data.iter().all(|d| d.x.abs() < 0.05 && d.y.abs() < 0.05 && d.z.abs() < 0.05),
+            "Device at rest should not report rotation"
+        );
+    }
+    
+    for kind in [SensorKind::Accelerometer, SensorKind::Gyroscope, SensorKind::Magnetometer, SensorKind::Barometer] {
+        let mut sensor = SimulatedSensor::new(kind);
+        let batches = sensors::run_batched(&mut sensor, &clock, Duration::from_secs(2));
+        let samples: Vec<_> = batches.iter().flat_map(|b| b.samples.iter().copied()).collect();
+        let noise = sensor.config().noise;
+        
+        let expected = (2.0 * sensor.config().rate_hz) as usize;
+        assert_eq!(samples.len(), expected, "{} sample count at {}Hz", kind.name(), sensor.config().rate_hz);
+        assert!(batches.iter().all(|b| b.samples.len() == 1), "{} should stream without batching", kind.name());
+        
+        for sample in &samples {
+            let (lo, hi) = sensor_bounds(kind);
+            let value = if kind.axes() == 1 { sample.values[0] } else { sample.magnitude() };
+            assert!(
+                value >= lo && value <= hi,
+                "{} reading {:.3}{} outside [{}, {}]",
+                kind.name(),
+                value,
+                kind.unit(),
+                lo,
+                hi
+            );
+            
+            for (axis, v) in sample.values.iter().enumerate() {
+                let steps = v / noise.resolution;
+                assert!(
+                    (steps - steps.round()).abs() < 0.01,
+                    "{} axis {} not quantized to {}: {}",
+                    kind.name(),
+                    axis,
+                    noise.resolution,
+                    v
+                );
+                if axis >= kind.axes() {
+                    assert_eq!(*v, 0.0, "{} has no axis {}", kind.name(), axis);
+                }
+            }
+        }
+        
+        // Разброс должен соответствовать модели шума
+        let n = samples.len() as f32;
+        let mean = samples.iter().map(|s| s.values[0]).sum::<f32>() / n;
+        let std = (samples.iter().map(|s| (s.values[0] - mean).powi(2)).sum::<f32>() / n).sqrt();
+        println!("{}: mean={:.4}{} std={:.4} (model {:.4})", kind.name(), mean, kind.unit(), std, noise.noise_std);
+        assert!(
+            std > noise.noise_std * 0.5 && std < noise.noise_std * 1.5 + noise.resolution,
+            "{} noise std {:.4} does not match model {:.4}",
+            kind.name(),
+            std,
+            noise.noise_std
+        );
     }
     
+    // FIFO-батчинг: 200Hz с задержкой доставки до 100ms
+    let mut config = SensorConfig::for_kind(SensorKind::Accelerometer);
+    config.rate_hz = 200.0;
+    config.max_report_latency = Duration::from_millis(100);
+    let mut batched = SimulatedSensor::with_config(SensorKind::Accelerometer, config);
+    let batches = sensors::run_batched(&mut batched, &clock, Duration::from_secs(2));
+    let delivered: usize = batches.iter().map(|b| b.samples.len()).sum();
+    assert_eq!(delivered, 400, "Batching must not lose samples");
+    assert!(batches.len() <= 21, "Too many wakeups for 100ms report latency: {}", batches.len());
+    for batch in &batches {
+        assert!(
+            batch.max_latency() <= config.max_report_latency,
+            "Batch delivered {:?} after its first sample",
+            batch.max_latency()
+        );
+    }
+    
+    config.fifo_capacity = 8;
+    let mut small_fifo = SimulatedSensor::with_config(SensorKind::Accelerometer, config);
+    let batches = sensors::run_batched(&mut small_fifo, &clock, Duration::from_millis(500));
+    assert!(batches.iter().all(|b| b.samples.len() <= 8), "FIFO overflowed its capacity");
+    assert_eq!(batches.len(), 13, "Full FIFO should wake the host every 8 samples");
+    
+    // Насыщение: удар в 20g обрезается по диапазону ±8g
+    let mut shock = SimulatedSensor::accelerometer().with_signal(|_| [0.0, 0.0, 20.0 * STANDARD_GRAVITY]);
+    let reading = shock.measure(clock.now());
+    let range = NoiseModel::for_kind(SensorKind::Accelerometer).max;
+    assert!((reading.values[2] - range).abs() < 0.01, "Accelerometer should saturate at {}: {:?}", range, reading);
+    
    
-    let location = simulate_gps_fix();
+    let location = simulate_gps_fix(&clock);
     assert!(
         location.latitude >= -90.0 && location.latitude <= 90.0,
         "Invalid latitude: {}",
@@ -245,6 +880,569 @@ fn test_sensors_e2e() {
     println!("✓ Sensors E2E test completed");
 }
 
+
+#[test]
+fn test_sensor_timing_e2e() {
+    println!("=== SENSOR TIMING E2E TEST ===");
+    
+    let clock = SimulatedClock::new();
+    
+    // Каждый сенсор проверяется против своей частоты
+    for kind in [SensorKind::Accelerometer, SensorKind::Gyroscope, SensorKind::Magnetometer, SensorKind::Barometer] {
+        let mut config = SensorConfig::for_kind(kind);
+        config.max_report_latency = Duration::from_millis(200);
+        let mut sensor = SimulatedSensor::with_config(kind, config);
+        let batches = sensors::run_batched(&mut sensor, &clock, Duration::from_secs(5));
+        
+        let timestamps: Vec<Duration> = batches.iter().flat_map(|b| b.samples.iter().map(|s| s.timestamp)).collect();
+        let sync_points: Vec<(Duration, Duration)> = batches
+            .iter()
+            .filter_map(|b| b.samples.last().map(|s| (s.timestamp, b.delivered_at)))
+            .collect();
+        
+        let report = sensor_timing::analyze_with_host(kind.name(), &timestamps, &sync_points, config.rate_hz);
+        print!("{}", report);
+        
+        assert_eq!(report.samples, (5.0 * config.rate_hz) as usize, "{} sample count", kind.name());
+        assert_eq!(report.dropped, 0, "{} should not drop samples", kind.name());
+        assert_eq!(report.non_monotonic, 0, "{} timestamps must increase", kind.name());
+        // Батчинг сдвигает время доставки, но не часы сенсора
+        assert!(report.drift_ppm.unwrap().abs() < 50.0, "{} drift from batching: {:?}", kind.name(), report.drift_ppm);
+        if let Err(violations) = report.check(&RateTolerance::new(config.rate_hz)) {
+            panic!("{} timing: {}", kind.name(), violations.join("; "));
+        }
+    }
+    
+    // Пропуски и джиттер из симулятора IMU должны обнаруживаться точно
+    let timing = SampleTiming { drop_rate: 0.1, jitter: 0.2, ..SampleTiming::ideal(100.0) };
+    let imu = fusion::simulate_imu(MotionProfile::stationary(Quaternion::IDENTITY), Duration::from_secs(5), timing);
+    let timestamps: Vec<Duration> = imu.iter().map(|s| s.timestamp).collect();
+    let report = sensor_timing::analyze("imu_lossy", &timestamps, 100.0);
+    print!("{}", report);
+    
+    let span = timestamps[timestamps.len() - 1] - timestamps[0];
+    let expected_dropped = (span.as_secs_f64() * 100.0).round() as usize + 1 - timestamps.len();
+    assert_eq!(report.dropped, expected_dropped, "Drop detection miscounted");
+    assert!(report.jitter > Duration::from_micros(500), "Jitter not detected: {:?}", report.jitter);
+    let violations = report.check(&RateTolerance::new(100.0)).unwrap_err();
+    assert!(violations.iter().any(|v| v.starts_with("dropped")), "Drop limit not enforced: {:?}", violations);
+    assert!(violations.iter().any(|v| v.starts_with("jitter")), "Jitter limit not enforced: {:?}", violations);
+    
+    // Временная метка, ушедшая назад
+    let mut reordered: Vec<Duration> = (0..100).map(|i| Duration::from_millis(i * 10)).collect();
+    reordered.swap(40, 41);
+    let report = sensor_timing::analyze("reordered", &reordered, 100.0);
+    assert_eq!(report.non_monotonic, 1, "Backwards timestamp not detected");
+    assert!(report.check(&RateTolerance::new(100.0)).is_err());
+    
+    // Часы сенсора спешат на 300ppm относительно хоста
+    let host: Vec<Duration> = (0..1000).map(|i| Duration::from_millis(i * 10)).collect();
+    let sensor_clock: Vec<Duration> = host.iter().map(|t| t.mul_f64(1.0 + 300e-6)).collect();
+    let sync_points: Vec<(Duration, Duration)> = sensor_clock.iter().copied().zip(host.iter().copied()).collect();
+    let report = sensor_timing::analyze_with_host("drifting", &sensor_clock, &sync_points, 100.0);
+    let drift = report.drift_ppm.expect("Drift should be estimated");
+    assert!((drift - 300.0).abs() < 5.0, "Drift estimate off: {:.1}ppm", drift);
+    assert!(report.check(&RateTolerance::new(100.0)).is_ok());
+    assert!(report.check(&RateTolerance { max_drift_ppm: 200.0, ..RateTolerance::new(100.0) }).is_err());
+    
+    println!("✓ Sensor timing E2E test completed");
+}
+
+#[test]
+fn test_orientation_fusion_e2e() {
+    println!("=== ORIENTATION FUSION E2E TEST ===");
+    
+    // Наклонённый телефон, поворот вокруг оси экрана 90°/s, затем покой
+    let profile = MotionProfile {
+        start: Quaternion::from_euler_deg(60.0, -20.0, 30.0),
+        rate: [0.0, 0.0, 90f32.to_radians()],
+        spin_from: Duration::from_secs(3),
+        spin_until: Duration::from_secs(5),
+    };
+    let duration = Duration::from_secs(8);
+    let settled = Duration::from_secs(6);
+    let tau = Duration::from_millis(500);
+    
+    let scenarios = vec![
+        ("ideal", SampleTiming::ideal(100.0), Duration::from_secs(2), 1.0),
+        ("dropped_20pct", SampleTiming { drop_rate: 0.2, ..SampleTiming::ideal(100.0) }, Duration::from_secs(2), 1.5),
+        ("jitter_30pct", SampleTiming { jitter: 0.3, ..SampleTiming::ideal(100.0) }, Duration::from_secs(2), 1.5),
+        (
+            "dropped_and_jitter",
+            SampleTiming { drop_rate: 0.3, jitter: 0.4, ..SampleTiming::ideal(100.0) },
+            Duration::from_millis(2500),
+            2.0,
+        ),
+        ("low_rate_25hz", SampleTiming::ideal(25.0), Duration::from_millis(2500), 2.0),
+    ];
+    
+    for (name, timing, max_convergence, max_steady_error) in scenarios {
+        let samples = fusion::simulate_imu(profile, duration, timing);
+        // Фильтр стартует с единичной ориентации, ошибка ~70°
+        let mut filter = ComplementaryFilter::with_initial(tau, Quaternion::IDENTITY);
+        let report = fusion::run_fusion(&mut filter, &profile, &samples);
+        
+        let converged = report.converged_at(3.0);
+        let steady = report.rms_error_after(settled);
+        println!(
+            "{}: {} samples, converged at {:?}, steady-state RMS {:.2}°, max after settle {:.2}°",
+            name,
+            samples.len(),
+            converged,
+            steady,
+            report.max_error_after(settled)
+        );
+        
+        let converged = converged.unwrap_or_else(|| panic!("{}: filter never converged", name));
+        assert!(converged <= max_convergence, "{}: convergence too slow: {:?}", name, converged);
+        assert!(steady < max_steady_error, "{}: steady-state error {:.2}° too high", name, steady);
+    }
+    
+    // Без начальной оценки фильтр берёт первую пару accel/mag
+    let still = MotionProfile::stationary(Quaternion::from_euler_deg(-135.0, 45.0, 0.0));
+    let samples = fusion::simulate_imu(still, Duration::from_secs(1), SampleTiming::ideal(100.0));
+    let mut filter = ComplementaryFilter::new(tau);
+    let report = fusion::run_fusion(&mut filter, &still, &samples);
+    let worst = report.max_error_after(Duration::ZERO);
+    assert!(worst < 3.0, "Initial accel/mag fix is off by {:.2}°", worst);
+    
+    println!("✓ Orientation fusion E2E test completed");
+}
+
+#[test]
+fn test_location_trace_playback_e2e() {
+    println!("=== LOCATION TRACE PLAYBACK E2E TEST ===");
+    
+    let walk = LocationTrace::from_gpx("embarcadero_walk", EMBARCADERO_WALK_GPX).expect("Failed to parse GPX");
+    let walking_speed = walk.length_m() / walk.duration().as_secs_f64();
+    println!("GPX '{}': {} points, {:.0}m in {:?} ({:.2} m/s)", walk.name, walk.points.len(), walk.length_m(), walk.duration(), walking_speed);
+    assert!(walk.points.len() > 100, "GPX trace too short");
+    assert!((walking_speed - 1.4).abs() < 0.1, "Unexpected walking speed {:.2} m/s", walking_speed);
+    
+    // Интерполяция между соседними точками
+    let (a, b) = (walk.points[10], walk.points[11]);
+    let mid = walk.position_at((a.time + b.time) / 2).unwrap();
+    assert!((mid.latitude - (a.latitude + b.latitude) / 2.0).abs() < 1e-9);
+    assert!((mid.longitude - (a.longitude + b.longitude) / 2.0).abs() < 1e-9);
+    assert!(walk.position_at(walk.duration() + Duration::from_secs(1)).is_none());
+    
+    let drive = LocationTrace::from_nmea("market_street_drive", MARKET_STREET_DRIVE_NMEA).expect("Failed to parse NMEA");
+    let (drive_speed, bearing) = drive.velocity_at(Duration::from_secs(60));
+    println!("NMEA '{}': {} points, {:?}, {:.1} m/s heading {:.0}°", drive.name, drive.points.len(), drive.duration(), drive_speed, bearing);
+    assert!((drive_speed - 11.0).abs() < 0.5, "Unexpected driving speed {:.1} m/s", drive_speed);
+    assert!(bearing > 180.0 && bearing < 270.0, "Market St runs south-west, got {:.0}°", bearing);
+    assert!(drive.points.iter().all(|p| p.altitude.is_some()), "GGA altitude should be merged into RMC fixes");
+    
+    let corrupted = MARKET_STREET_DRIVE_NMEA.replacen("3747.6400", "3747.6401", 1);
+    assert!(LocationTrace::from_nmea("corrupted", &corrupted).is_err(), "Bad checksum should be rejected");
+    
+    // Две точки с одинаковым временем: скорость 0, а не inf/NaN
+    let at = chrono::NaiveDate::from_ymd_opt(2024, 5, 18).unwrap().and_hms_opt(17, 5, 12).unwrap();
+    let duplicate = LocationTrace::from_points("duplicate", vec![(at, 37.79, -122.39, None), (at, 37.7901, -122.3901, None)])
+        .expect("Equal timestamps are allowed");
+    assert_eq!(duplicate.velocity_at(Duration::ZERO).0, 0.0, "Zero-length segment should report no speed");
+    
+    // Прогулка в 10 раз быстрее: холодный старт и туннель без сигнала
+    let clock = SimulatedClock::new();
+    let config = PlaybackConfig {
+        speed: 10.0,
+        time_to_first_fix: Duration::from_secs(30),
+        outages: vec![(Duration::from_secs(60), Duration::from_secs(20))],
+        ..PlaybackConfig::default()
+    };
+    let start = clock.now();
+    let mut fixes = Vec::new();
+    let mut lost = Vec::new();
+    let stats = location_trace::play(&walk, &config, &clock, |event| {
+        match event {
+            LocationEvent::Fix(fix) => fixes.push(*fix),
+            LocationEvent::FixLost { at } => lost.push(*at - start),
+        }
+        true
+    });
+    println!("Playback: {} fixes, {} losses, TTFF {:?}", stats.fixes, stats.lost, stats.time_to_first_fix);
+    
+    assert_eq!(stats.time_to_first_fix, Some(config.time_to_first_fix));
+    assert_eq!(lost, vec![Duration::from_secs(60)], "Fix loss should be reported once at the outage");
+    assert!(
+        fixes.iter().all(|f| f.timestamp - start < Duration::from_secs(60) || f.timestamp - start >= Duration::from_secs(80)),
+        "Fixes delivered during the outage"
+    );
+    let expected_fixes = (walk.duration().as_secs() / 10 - 30 + 1 - 20) as usize;
+    assert_eq!(fixes.len(), expected_fixes, "Unexpected fix count at 10x");
+    
+    for fix in &fixes {
+        let truth = walk.position_at(fix.trace_time).unwrap();
+        let error = location_trace::distance_m(fix.latitude, fix.longitude, truth.latitude, truth.longitude);
+        assert!(error < 4.0 * fix.accuracy as f64, "Fix {:.1}m off with accuracy {:.1}m", error, fix.accuracy);
+        assert!(fix.accuracy >= 3.0 && fix.accuracy < 20.0, "Accuracy out of range: {:.1}m", fix.accuracy);
+    }
+    let mean_speed = fixes.iter().map(|f| f.speed).sum::<f32>() / fixes.len() as f32;
+    assert!((mean_speed - 1.4).abs() < 0.1, "Reported walking speed {:.2} m/s", mean_speed);
+    
+    // Свежий фикс после потери сигнала хуже установившегося
+    let settled = fixes.iter().rfind(|f| f.timestamp - start < Duration::from_secs(60)).unwrap();
+    let reacquired = fixes.iter().find(|f| f.timestamp - start >= Duration::from_secs(80)).unwrap();
+    assert!(
+        reacquired.accuracy > settled.accuracy,
+        "Reacquired fix ({:.1}m) should be less accurate than a settled one ({:.1}m)",
+        reacquired.accuracy,
+        settled.accuracy
+    );
+    let accuracies: Vec<f32> = fixes.iter().map(|f| f.accuracy).collect();
+    let spread = accuracies.iter().cloned().fold(0.0, f32::max) - accuracies.iter().cloned().fold(f32::MAX, f32::min);
+    assert!(spread > 1.0, "Accuracy should vary between fixes");
+    
+    // Поездка в реальном времени с ускорением 500x
+    let config = PlaybackConfig {
+        speed: 500.0,
+        fix_interval: Duration::from_millis(50),
+        time_to_first_fix: Duration::ZERO,
+        ..PlaybackConfig::default()
+    };
+    let wall = Instant::now();
+    let stats = location_trace::play(&drive, &config, &RealClock::new(), |_| true);
+    let elapsed = wall.elapsed();
+    let scaled = drive.duration().div_f64(config.speed);
+    println!("Real-time drive at {}x: {} fixes in {:?} (trace {:?})", config.speed, stats.fixes, elapsed, scaled);
+    assert_eq!(stats.fixes, (scaled.as_millis() / 50) as usize + 1);
+    assert!(elapsed + Duration::from_millis(50) >= scaled, "Playback ran ahead of the clock: {:?}", elapsed);
+    assert!(elapsed < scaled + Duration::from_millis(300), "Playback too slow: {:?}", elapsed);
+    
+    println!("✓ Location trace playback E2E test completed");
+}
+
+#[test]
+fn test_geofencing_e2e() {
+    println!("=== GEOFENCING E2E TEST ===");
+    
+    // Записанная прогулка: от Ferry Building до Pier 39
+    let walk = LocationTrace::from_gpx("embarcadero_walk", EMBARCADERO_WALK_GPX).expect("Failed to parse GPX");
+    let mut engine = GeofenceEngine::default();
+    for fence in [
+        Geofence::new("ferry_building", 37.7955, -122.3934, 120.0),
+        Geofence::new("pier_15", 37.8017, -122.3977, 250.0),
+        Geofence::new("coit_tower", 37.8024, -122.4058, 150.0),
+        Geofence::new("pier_39", 37.8087, -122.4098, 150.0),
+    ] {
+        engine.add(fence).expect("Failed to register geofence");
+    }
+    
+    let config = PlaybackConfig { time_to_first_fix: Duration::ZERO, ..PlaybackConfig::default() };
+    let events = run_geofences(&mut engine, &walk, &config);
+    for event in &events {
+        println!("{:>8.1?} {:<15} {:?} ({:.0}m from center)", event.at, event.fence_id, event.transition, event.distance_m);
+    }
+    
+    use GeofenceTransition::{Dwell, Enter, Exit};
+    let expected = vec![
+        ("ferry_building", vec![Enter, Dwell, Exit]),
+        ("pier_15", vec![Enter, Dwell, Exit]),
+        ("coit_tower", vec![]),
+        ("pier_39", vec![Enter, Dwell]),
+    ];
+    for (fence_id, transitions) in expected {
+        let actual: Vec<GeofenceTransition> =
+            events.iter().filter(|e| e.fence_id == fence_id).map(|e| e.transition).collect();
+        assert_eq!(actual, transitions, "Unexpected transitions for '{}'", fence_id);
+    }
+    assert!(events.windows(2).all(|w| w[0].at <= w[1].at), "Events out of order");
+    assert_eq!(engine.is_inside("pier_39"), Some(true));
+    
+    // Медленный выход через границу: шум не должен давать дребезг
+    let center = (37.7694, -122.4862);
+    let radius = 150.0;
+    let outward = generated_trace("slow_exit", center, |t| (0.0, t * 0.3), Duration::from_secs(1000));
+    let mut engine = GeofenceEngine::default();
+    engine.add(Geofence::new("park", center.0, center.1, radius)).unwrap();
+    
+    let mut naive_crossings = 0;
+    let mut naive_inside = None;
+    let mut events = Vec::new();
+    location_trace::play(&outward, &config, &SimulatedClock::new(), |event| {
+        if let LocationEvent::Fix(fix) = event {
+            let inside = location_trace::distance_m(center.0, center.1, fix.latitude, fix.longitude) < radius;
+            if naive_inside.is_some_and(|was| was != inside) {
+                naive_crossings += 1;
+            }
+            naive_inside = Some(inside);
+            events.extend(engine.process(fix));
+        }
+        true
+    });
+    let transitions: Vec<GeofenceTransition> = events.iter().map(|e| e.transition).collect();
+    println!("Slow exit: {} raw boundary crossings, events {:?}", naive_crossings, transitions);
+    assert!(naive_crossings > 1, "Trace should be noisy enough to flap without hysteresis");
+    assert_eq!(transitions, vec![Enter, Dwell, Exit], "Hysteresis should suppress boundary flapping");
+    
+    // Зашёл и стоит: один DWELL, без повторов
+    let loiter = generated_trace("loiter", center, |t| (0.0, (200.0 - t * 1.5).max(20.0)), Duration::from_secs(600));
+    let mut engine = GeofenceEngine::default();
+    engine
+        .add(Geofence::new("park", center.0, center.1, radius).with_dwell_delay(Duration::from_secs(120)))
+        .unwrap();
+    let events = run_geofences(&mut engine, &loiter, &config);
+    let transitions: Vec<GeofenceTransition> = events.iter().map(|e| e.transition).collect();
+    assert_eq!(transitions, vec![Enter, Dwell]);
+    assert_eq!(events[1].at - events[0].at, Duration::from_secs(120), "DWELL should fire after the dwell delay");
+    
+    // Фикс с плохой точностью игнорируется
+    let vague = LocationFix {
+        latitude: center.0,
+        longitude: center.1,
+        altitude: None,
+        accuracy: 500.0,
+        speed: 0.0,
+        bearing: 0.0,
+        timestamp: Duration::from_secs(1),
+        trace_time: Duration::from_secs(1),
+    };
+    let mut engine = GeofenceEngine::default();
+    engine.add(Geofence::new("park", center.0, center.1, radius)).unwrap();
+    assert!(engine.process(&vague).is_empty(), "500m accuracy fix should not trigger a 150m fence");
+    assert_eq!(engine.is_inside("park"), Some(false));
+    
+    // Лимит и валидация
+    let mut engine = GeofenceEngine::default();
+    assert!(engine.is_empty());
+    for i in 0..geofence::MAX_GEOFENCES {
+        engine.add(Geofence::new(&format!("fence_{}", i), 37.0, -122.0, 100.0)).unwrap();
+    }
+    assert_eq!(
+        engine.add(Geofence::new("one_too_many", 37.0, -122.0, 100.0)),
+        Err(GeofenceError::TooManyFences { limit: geofence::MAX_GEOFENCES })
+    );
+    engine.remove("fence_0").unwrap();
+    assert_eq!(engine.len(), geofence::MAX_GEOFENCES - 1);
+    assert!(engine.add(Geofence::new("one_too_many", 37.0, -122.0, 100.0)).is_ok());
+    assert_eq!(
+        engine.add(Geofence::new("fence_1", 37.0, -122.0, 100.0)),
+        Err(GeofenceError::DuplicateId("fence_1".to_string()))
+    );
+    assert!(matches!(engine.add(Geofence::new("bad", 37.0, -122.0, 0.0)), Err(GeofenceError::InvalidRadius(_))));
+    assert!(matches!(engine.remove("missing"), Err(GeofenceError::UnknownId(_))));
+    
+    println!("✓ Geofencing E2E test completed");
+}
+
+fn run_geofences(engine: &mut GeofenceEngine, trace: &LocationTrace, config: &PlaybackConfig) -> Vec<GeofenceEvent> {
+    let mut events = Vec::new();
+    location_trace::play(trace, config, &SimulatedClock::new(), |event| {
+        if let LocationEvent::Fix(fix) = event {
+            events.extend(engine.process(fix));
+        }
+        true
+    });
+    events
+}
+
+// Трасса по смещению (север, восток) в метрах от центра как функции времени в секундах
+fn generated_trace<F>(name: &str, center: (f64, f64), offset: F, duration: Duration) -> LocationTrace
+where
+    F: Fn(f64) -> (f64, f64),
+{
+    let points = (0..=duration.as_secs())
+        .map(|t| {
+            let (north, east) = offset(t as f64);
+            let (latitude, longitude) = location_trace::offset_m(center.0, center.1, north, east);
+            TracePoint { time: Duration::from_secs(t), latitude, longitude, altitude: None }
+        })
+        .collect();
+    LocationTrace { name: name.to_string(), points }
+}
+
+#[test]
+fn test_nmea_device_e2e() {
+    println!("=== NMEA DEVICE E2E TEST ===");
+    
+    // Эталонные предложения из описания NMEA 0183
+    let reference = [
+        "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47",
+        "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A",
+        "$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39",
+        "$GPGSV,2,1,08,01,40,083,46,02,17,308,41,12,07,344,39,14,22,228,45*75",
+    ];
+    let parsed: Vec<Sentence> = reference.iter().map(|line| nmea::parse(line).expect("Failed to parse reference sentence")).collect();
+    
+    let Sentence::Gga(gga) = &parsed[0] else { panic!("Expected GGA, got {:?}", parsed[0]) };
+    assert!((gga.latitude.unwrap() - 48.1173).abs() < 1e-9);
+    assert!((gga.longitude.unwrap() - 11.516_666_667).abs() < 1e-9);
+    assert_eq!((gga.quality, gga.satellites, gga.hdop, gga.altitude), (1, 8, Some(0.9), Some(545.4)));
+    let Sentence::Rmc(rmc) = &parsed[1] else { panic!("Expected RMC, got {:?}", parsed[1]) };
+    assert!(rmc.valid);
+    assert_eq!((rmc.speed_knots, rmc.course), (Some(22.4), Some(84.4)));
+    assert_eq!(rmc.date, chrono::NaiveDate::from_ymd_opt(1994, 3, 23).unwrap());
+    let Sentence::Gsa(gsa) = &parsed[2] else { panic!("Expected GSA, got {:?}", parsed[2]) };
+    assert_eq!((gsa.fix_type, gsa.prns.as_slice()), (3, [4, 5, 9, 12, 24].as_slice()));
+    assert_eq!((gsa.pdop, gsa.hdop, gsa.vdop), (Some(2.5), Some(1.3), Some(2.1)));
+    let Sentence::Gsv(gsv) = &parsed[3] else { panic!("Expected GSV, got {:?}", parsed[3]) };
+    assert_eq!((gsv.messages, gsv.number, gsv.in_view, gsv.satellites.len()), (2, 1, 8, 4));
+    assert_eq!(gsv.satellites[2], SatelliteInView { prn: 12, elevation: Some(7), azimuth: Some(344), snr: Some(39) });
+    
+    // Генератор и парсер согласованы
+    for sentence in &parsed {
+        let line = sentence.to_sentence("GP");
+        assert_eq!(&nmea::parse(&line).expect("Generated sentence does not parse"), sentence, "Round trip changed {}", line);
+    }
+    
+    // Ошибки
+    let with_checksum = |body: &str| format!("${}*{:02X}", body, nmea::checksum(body));
+    assert!(matches!(nmea::parse(&reference[0].replace("4807.038", "4807.039")), Err(NmeaError::ChecksumMismatch { .. })));
+    assert_eq!(nmea::parse(reference[0].trim_end_matches("*47")), Err(NmeaError::MissingChecksum));
+    assert_eq!(nmea::parse(&reference[0][1..]), Err(NmeaError::NotASentence));
+    assert_eq!(
+        nmea::parse(&with_checksum("GPVTG,054.7,T,034.4,M,005.5,N,010.2,K")),
+        Err(NmeaError::Unsupported("GPVTG".to_string()))
+    );
+    assert_eq!(
+        nmea::parse(&with_checksum("GPGGA,123519,4807.038,N,01131.000,E,X,08,0.9,545.4,M,46.9,M,,")),
+        Err(NmeaError::BadField { sentence: "GGA", field: "quality" })
+    );
+    assert_eq!(
+        nmea::parse(&with_checksum("GPGSV,1,1")),
+        Err(NmeaError::BadField { sentence: "GSV", field: "satellites in view" })
+    );
+    for line in MARKET_STREET_DRIVE_NMEA.lines() {
+        nmea::parse(line).unwrap_or_else(|e| panic!("Capture line '{}': {}", line, e));
+    }
+    
+    // Псевдо-устройство на 10Hz в реальном времени
+    let drive = LocationTrace::from_nmea("market_street_drive", MARKET_STREET_DRIVE_NMEA).expect("Failed to parse NMEA");
+    let start = chrono::NaiveDate::from_ymd_opt(2024, 5, 18).unwrap().and_hms_opt(17, 5, 12).unwrap();
+    let config = DeviceConfig {
+        rate_hz: 10,
+        talker: "GN".to_string(),
+        start,
+        satellites: 12,
+        playback: PlaybackConfig { time_to_first_fix: Duration::from_millis(100), ..PlaybackConfig::default() },
+    };
+    let sky = nmea::simulated_sky(config.satellites, config.playback.seed);
+    let tracked = sky.iter().filter(|sat| sat.snr.is_some()).count();
+    
+    let clock = RealClock::shared();
+    let mut device = NmeaDevice::spawn(drive.clone(), config.clone(), clock.clone()).expect("Failed to start NMEA device");
+    let mut decoder = FixDecoder::new();
+    let mut fixes = Vec::new();
+    let mut arrivals = Vec::new();
+    for line in device.by_ref() {
+        let line = line.expect("Failed to read NMEA device");
+        assert!(line.starts_with("$GN"), "Unexpected talker: {}", line);
+        assert!(line.len() + 2 <= nmea::MAX_SENTENCE_LEN, "Sentence too long: {}", line);
+        let sentence = nmea::parse(&line).unwrap_or_else(|e| panic!("Device sent '{}': {}", line, e));
+        if let Some(fix) = decoder.feed(&sentence) {
+            arrivals.push(clock.now());
+            fixes.push(fix);
+            if fixes.len() == 30 {
+                break;
+            }
+        }
+    }
+    let written = device.close().expect("NMEA writer failed");
+    println!("10Hz device: {} sentences written, {} fixes read", written, fixes.len());
+    
+    assert_eq!(fixes[0].time, start + chrono::Duration::milliseconds(100), "First fix should follow TTFF");
+    assert!(
+        fixes.windows(2).all(|w| w[1].time - w[0].time == chrono::Duration::milliseconds(100)),
+        "Fix times should advance by 100ms"
+    );
+    for fix in &fixes {
+        assert_eq!((fix.satellites_used, fix.satellites_in_view), (tracked, sky.len()));
+        let trace_time = (fix.time - start).to_std().unwrap();
+        let truth = drive.position_at(trace_time).unwrap();
+        let error = location_trace::distance_m(fix.latitude, fix.longitude, truth.latitude, truth.longitude);
+        // HDOP на UERE 5m, плюс округление координат в предложении
+        let accuracy = fix.hdop.expect("Fix without HDOP") as f64 * 5.0;
+        assert!(error < 4.0 * accuracy + 1.0, "Fix {:.1}m off with accuracy {:.1}m", error, accuracy);
+        assert!(fix.altitude.is_some(), "GGA altitude missing");
+    }
+    let mean_speed = fixes.iter().map(|f| f.speed).sum::<f64>() / fixes.len() as f64;
+    assert!((mean_speed - 11.0).abs() < 0.5, "Reported driving speed {:.1} m/s", mean_speed);
+    
+    let report = sensor_timing::analyze("nmea_10hz", &arrivals, 10.0);
+    print!("{}", report);
+    if let Err(violations) = report.check(&RateTolerance::new(10.0)) {
+        panic!("NMEA output timing out of tolerance: {:?}", violations);
+    }
+    
+    // 1Hz на симулированных часах: вся поездка с потерей сигнала
+    let config = DeviceConfig {
+        rate_hz: 1,
+        playback: PlaybackConfig {
+            time_to_first_fix: Duration::from_secs(5),
+            outages: vec![(Duration::from_secs(60), Duration::from_secs(10))],
+            ..PlaybackConfig::default()
+        },
+        ..config
+    };
+    let mut device = NmeaDevice::spawn(drive.clone(), config.clone(), SimulatedClock::shared()).expect("Failed to start NMEA device");
+    let sentences: Vec<Sentence> = device
+        .by_ref()
+        .map(|line| nmea::parse(&line.expect("Failed to read NMEA device")).expect("Device emitted a bad sentence"))
+        .collect();
+    let written = device.close().expect("NMEA writer failed");
+    assert_eq!(written, sentences.len(), "Reader should receive every sentence");
+    
+    let mut decoder = FixDecoder::new();
+    let fixes: Vec<_> = sentences.iter().filter_map(|s| decoder.feed(s)).collect();
+    let no_fix: Vec<&Rmc> = sentences.iter().filter_map(|s| if let Sentence::Rmc(rmc) = s { Some(rmc) } else { None }).filter(|rmc| !rmc.valid).collect();
+    println!("1Hz device: {} sentences, {} fixes, {} no-fix epochs", sentences.len(), fixes.len(), no_fix.len());
+    
+    let expected_fixes = drive.duration().as_secs() as usize - 5 + 1 - 10;
+    assert_eq!(fixes.len(), expected_fixes, "Unexpected fix count at 1Hz");
+    assert_eq!(no_fix.len(), 1, "Signal loss should be reported once");
+    assert_eq!(start.date().and_time(no_fix[0].time), start + chrono::Duration::seconds(60));
+    assert!(
+        sentences.iter().any(|s| matches!(s, Sentence::Gga(gga) if gga.quality == 0 && gga.latitude.is_none())),
+        "No-fix epoch should carry an empty GGA"
+    );
+    
+    let too_fast = DeviceConfig { rate_hz: 20, ..config };
+    assert!(NmeaDevice::spawn(drive, too_fast, SimulatedClock::shared()).is_err(), "20Hz is outside the NMEA device range");
+    
+    println!("✓ NMEA device E2E test completed");
+}
+
+#[test]
+fn test_virtual_clock_e2e() {
+    println!("=== VIRTUAL CLOCK E2E TEST ===");
+    
+    let wall_start = Instant::now();
+    let clock = SimulatedClock::new();
+    
+    let start_battery = simulate_battery_level(&clock);
+    
+    let samples = simulate_sensor_data(&clock, "accelerometer", 500);
+    for (i, pair) in samples.windows(2).enumerate() {
+        assert_eq!(
+            pair[1].timestamp - pair[0].timestamp,
+            10,
+            "Sample {} not spaced at 100Hz on the simulated clock",
+            i + 1
+        );
+    }
+    
+    let fix = simulate_gps_fix(&clock);
+    assert_eq!(fix.timestamp, 5100, "GPS fix should land 100ms after the sensor burst");
+    
+    clock.advance(Duration::from_secs(3600));
+    let drain = start_battery - simulate_battery_level(&clock);
+    assert!(
+        (drain - BATTERY_DRAIN_PERCENT_PER_HOUR).abs() < 0.1,
+        "Unexpected battery drain over one simulated hour: {:.2}%",
+        drain
+    );
+    
+    let wall_time = wall_start.elapsed();
+    println!("Simulated {:?} in {:?}", clock.now(), wall_time);
+    assert!(
+        wall_time < Duration::from_millis(500),
+        "Simulated clock should not wait in real time: {:?}",
+        wall_time
+    );
+    
+    println!("✓ Virtual clock E2E test completed");
+}
+
 #[derive(Debug, Clone)]
 struct SensorData {
     x: f32,
@@ -261,42 +1459,71 @@ struct Location {
     timestamp: u64,
 }
 
-fn simulate_sensor_data(sensor_type: &str, samples: usize) -> Vec<SensorData> {
+// Допустимый диапазон для неподвижного устройства: модуль вектора, для барометра давление
+fn sensor_bounds(kind: SensorKind) -> (f32, f32) {
+    match kind {
+        SensorKind::Accelerometer => (STANDARD_GRAVITY - 0.3, STANDARD_GRAVITY + 0.3),
+        SensorKind::Gyroscope => (0.0, 0.05),
+        SensorKind::Magnetometer => (25.0, 65.0),
+        SensorKind::Barometer => (950.0, 1050.0),
+    }
+}
+
+fn simulate_sensor_data(clock: &dyn Clock, sensor_type: &str, samples: usize) -> Vec<SensorData> {
+    let kind = SensorKind::parse(sensor_type)
+        .unwrap_or_else(|| panic!("Unknown sensor type '{}'", sensor_type));
+    let mut sensor = SimulatedSensor::new(kind);
+    let period = sensor.config().period();
     let mut data = Vec::with_capacity(samples);
-    let start_time = Instant::now();
     
-    for i in 0..samples {
-       
+    for _ in 0..samples {
+        let sample = sensor.measure(clock.now());
         data.push(SensorData {
-            x: (i as f32 * 0.1).sin(),
-            y: (i as f32 * 0.2).cos(),
-            z: (i as f32 * 0.3).sin() * (i as f32 * 0.4).cos(),
-            timestamp: start_time.elapsed().as_millis() as u64,
+            x: sample.values[0],
+            y: sample.values[1],
+            z: sample.values[2],
+            timestamp: sample.timestamp.as_millis() as u64,
         });
         
-      
-        thread::sleep(Duration::from_micros(10000)); 
+        clock.sleep(period);
     }
     
     data
 }
 
-fn simulate_gps_fix() -> Location {
-    
-    thread::sleep(Duration::from_millis(100)); 
+const EMBARCADERO_WALK_GPX: &str = include_str!("../captures/location/embarcadero_walk.gpx");
+const MARKET_STREET_DRIVE_NMEA: &str = include_str!("../captures/location/market_street_drive.nmea");
+
+fn simulate_gps_fix(clock: &dyn Clock) -> Location {
+    
+    let trace = LocationTrace::from_gpx("embarcadero_walk", EMBARCADERO_WALK_GPX).expect("Bundled GPX trace is invalid");
+    let config = PlaybackConfig {
+        time_to_first_fix: Duration::from_millis(100), // горячий старт
+        ..PlaybackConfig::default()
+    };
+    
+    let mut first_fix = None;
+    location_trace::play(&trace, &config, clock, |event| match event {
+        LocationEvent::Fix(fix) => {
+            first_fix = Some(*fix);
+            false
+        }
+        LocationEvent::FixLost { .. } => true,
+    });
+    let fix = first_fix.expect("Trace playback produced no fix");
     
     Location {
-        latitude: 37.7749,  
-        longitude: -122.4194,
-        accuracy: 10.0, 
-        timestamp: Instant::now().elapsed().as_millis() as u64,
+        latitude: fix.latitude,
+        longitude: fix.longitude,
+        accuracy: fix.accuracy,
+        timestamp: fix.timestamp.as_millis() as u64,
     }
 }
 
 #[cfg(target_os = "ios")]
-fn simulate_core_motion_data(samples: usize) -> Vec<SensorData> {
+fn simulate_core_motion_data(clock: &dyn Clock, samples: usize) -> Vec<SensorData> {
    
-    simulate_sensor_data("core_motion", samples)
+    simulate_sensor_data(clock, "gyroscope", samples)
 }
 
 
@@ -306,7 +1533,8 @@ fn test_power_efficiency_e2e() {
     
     let test_duration = Duration::from_secs(10);
     let start_time = Instant::now();
-    let start_battery_level = simulate_battery_level();
+    let clock = RealClock::new();
+    let start_battery_level = simulate_battery_level(&clock);
     
    
     let modes = vec![
@@ -344,7 +1572,7 @@ fn test_power_efficiency_e2e() {
     }
     
     let avg_cpu_usage = total_cpu_usage / mode_count as f32;
-    let end_battery_level = simulate_battery_level();
+    let end_battery_level = simulate_battery_level(&clock);
     let battery_drain = start_battery_level - end_battery_level;
     
     println!("Average CPU usage: {:.1}%", avg_cpu_usage);
@@ -362,9 +1590,12 @@ fn test_power_efficiency_e2e() {
     println!("✓ Power efficiency E2E test completed");
 }
 
-fn simulate_battery_level() -> f32 {
+const BATTERY_DRAIN_PERCENT_PER_HOUR: f32 = 10.0;
+
+fn simulate_battery_level(clock: &dyn Clock) -> f32 {
     
-    85.0 
+    let hours = clock.now().as_secs_f32() / 3600.0;
+    (85.0 - hours * BATTERY_DRAIN_PERCENT_PER_HOUR).max(0.0)
 }
 
 fn simulate_workload(mode: &str, duration: Duration) -> f32 {
@@ -423,82 +1654,409 @@ fn simulate_workload(mode: &str, duration: Duration) -> f32 {
 fn test_notifications_e2e() {
     println!("=== NOTIFICATIONS E2E TEST ===");
     
-    #[cfg(target_os = "android")]
-    {
-        
-        create_notification_channel("test_channel", "Test Channel", "Test notifications");
-    }
-    
-    #[cfg(target_os = "ios")]
-    {
-        
-        request_notification_permission();
-    }
+    let clock = SimulatedClock::shared();
+    let mut manager = NotificationManager::new(clock.clone());
     
+    // Без разрешения и без канала ничего не доставляется
+    let welcome = Notification::new("general", "Добро пожаловать!", "Спасибо за установку приложения");
+    assert_eq!(manager.notify("welcome", welcome.clone()), Err(NotificationError::PermissionDenied));
+    manager.set_permission(true);
+    assert_eq!(
+        manager.notify("welcome", welcome.clone()),
+        Err(NotificationError::UnknownChannel("general".to_string()))
+    );
     
-    let notifications = vec![
-        ("welcome", "Добро пожаловать!", "Спасибо за установку приложения"),
-        ("update", "Доступно обновление", "Обновите приложение до версии 2.0"),
-        ("reminder", "Напоминание", "Не забудьте выполнить задачу"),
-        ("alert", "Внимание!", "Обнаружена подозрительная активность"),
+    manager.create_channel(NotificationChannel::new("general", "General", "Reminders and news", Importance::Default));
+    manager.create_channel(NotificationChannel::new("updates", "Updates", "New versions", Importance::Low));
+    manager.create_channel(NotificationChannel::new("security", "Security", "Account alerts", Importance::High).bypassing_dnd());
+    manager.create_channel(NotificationChannel::new("promo", "Promotions", "Offers", Importance::None));
+    
+    // Приложение может только понизить важность канала
+    manager.create_channel(NotificationChannel::new("general", "General", "Reminders, news and tips", Importance::High));
+    let general = manager.channel("general").unwrap();
+    assert_eq!((general.importance, general.description.as_str()), (Importance::Default, "Reminders, news and tips"));
+    manager.create_channel(NotificationChannel::new("updates", "Updates", "New versions", Importance::Min));
+    assert_eq!(manager.channel("updates").unwrap().importance, Importance::Min);
+    
+    let posts = vec![
+        ("welcome", "general", "Добро пожаловать!", "Спасибо за установку приложения", Interruption::Sound),
+        ("update", "updates", "Доступно обновление", "Обновите приложение до версии 2.0", Interruption::Silent),
+        ("reminder", "general", "Напоминание", "Не забудьте выполнить задачу", Interruption::Sound),
+        ("alert", "security", "Внимание!", "Обнаружена подозрительная активность", Interruption::HeadsUp),
     ];
     
-    let mut delivery_times = Vec::new();
-    
-    for (id, title, body) in notifications {
-        let send_time = Instant::now();
-        
-        
-        let notification_id = send_notification(id, title, body);
-        
-        
-        thread::sleep(Duration::from_millis(50));
-        
-        let delivery_time = send_time.elapsed();
-        delivery_times.push(delivery_time);
-        
-        println!("Notification '{}' delivered in {:?}", title, delivery_time);
-        
-        
-        assert!(notification_id > 0, "Notification should have valid ID");
-        
-   
-        simulate_notification_tap(notification_id);
+    let mut ids = HashMap::new();
+    for (key, channel, title, body, interruption) in posts {
+        let delivery = manager.notify(key, Notification::new(channel, title, body)).expect("Failed to post notification");
+        println!("Notification '{}' posted as #{} ({:?})", title, delivery.id, delivery.interruption);
+        assert!(!delivery.updated);
+        assert_eq!(delivery.interruption, interruption, "Unexpected interruption for '{}'", key);
+        ids.insert(key, delivery.id);
     }
+    let unique: std::collections::HashSet<u32> = ids.values().copied().collect();
+    assert_eq!(unique.len(), 4, "Notification ids must be unique");
+    assert_eq!(manager.active().len(), 4);
+    assert_eq!(
+        manager.notify("promo", Notification::new("promo", "Скидка 50%", "Только сегодня")),
+        Err(NotificationError::ChannelBlocked("promo".to_string()))
+    );
     
-    
-    let avg_delivery_time: Duration = delivery_times.iter().sum::<Duration>() / delivery_times.len() as u32;
-    assert!(
-        avg_delivery_time < Duration::from_millis(100),
-        "Notifications too slow: average {:?}",
-        avg_delivery_time
+    // Обновление на месте: тот же id, без повторного звука при alert_once
+    let reminder = Notification::new("general", "Напоминание", "Задача просрочена");
+    let delivery = manager.notify("reminder", reminder.clone()).unwrap();
+    assert_eq!(delivery, Delivery { id: ids["reminder"], updated: true, interruption: Interruption::Sound });
+    let delivery = manager.notify("reminder", reminder.alert_once()).unwrap();
+    assert_eq!(delivery.interruption, Interruption::Silent);
+    let posted = manager.get(ids["reminder"]).unwrap();
+    assert_eq!((posted.updates, posted.notification.body.as_str()), (2, "Задача просрочена"));
+    assert_eq!(manager.active().len(), 4, "Update must not add a notification");
+    
+    // Нажатие убирает уведомление с auto_cancel
+    let tapped = manager.tap(ids["welcome"]).expect("Failed to tap notification");
+    assert_eq!(tapped.key, "welcome");
+    assert_eq!(manager.tap(ids["welcome"]), Err(NotificationError::UnknownId(ids["welcome"])));
+    assert_eq!(manager.active().len(), 3);
+    
+    // Группа с общей сводкой
+    let mut chat = Vec::new();
+    for (i, text) in ["Привет!", "Как дела?", "Ты где?"].iter().enumerate() {
+        clock.advance(Duration::from_secs(1));
+        let message = Notification::new("general", &format!("Анна: {}", text), text).in_group("chat_anna");
+        chat.push(manager.notify(&format!("chat_{}", i), message).unwrap().id);
+    }
+    let summary = manager.group_summary("chat_anna").expect("Group should have a summary");
+    assert_eq!(summary.children, chat.iter().rev().copied().collect::<Vec<_>>());
+    assert_eq!(summary.lines[0], "Анна: Ты где?", "Summary should list the newest message first");
+    manager.cancel(chat[0]).unwrap();
+    manager.cancel(chat[1]).unwrap();
+    assert!(manager.group_summary("chat_anna").is_none(), "A single child needs no summary");
+    
+    // Не беспокоить: только приоритетные каналы
+    manager.set_dnd(DndMode::PriorityOnly);
+    assert_eq!(manager.notify("news", Notification::new("general", "Новости", "Дайджест")).unwrap().interruption, Interruption::SuppressedByDnd);
+    assert_eq!(manager.notify("login", Notification::new("security", "Вход", "Новое устройство")).unwrap().interruption, Interruption::HeadsUp);
+    manager.set_dnd(DndMode::TotalSilence);
+    assert_eq!(manager.notify("login_2", Notification::new("security", "Вход", "Ещё одно устройство")).unwrap().interruption, Interruption::SuppressedByDnd);
+    manager.set_dnd(DndMode::Off);
+    
+    // Ночное правило по расписанию
+    let now = clock.now();
+    manager.add_dnd_rule(DndRule {
+        name: "sleeping".to_string(),
+        start: now + Duration::from_secs(3600),
+        end: now + Duration::from_secs(9 * 3600),
+        mode: DndMode::TotalSilence,
+    });
+    let evening = manager.notify("evening", Notification::new("general", "Вечер", "Итоги дня")).unwrap();
+    assert_eq!(evening.interruption, Interruption::Sound);
+    clock.advance(Duration::from_secs(2 * 3600));
+    assert_eq!(manager.dnd_mode(), DndMode::TotalSilence);
+    let night = manager.notify("night", Notification::new("general", "Ночь", "Резервная копия готова")).unwrap();
+    assert_eq!(night.interruption, Interruption::SuppressedByDnd);
+    clock.advance(Duration::from_secs(8 * 3600));
+    assert_eq!(manager.dnd_mode(), DndMode::Off);
+    
+    // Частые обновления прогресса отбрасываются
+    manager.notify("download", Notification::new("updates", "Загрузка", "0%")).unwrap();
+    let mut shed = 0;
+    for percent in (10..=100).step_by(10) {
+        match manager.notify("download", Notification::new("updates", "Загрузка", &format!("{}%", percent))) {
+            Ok(_) => {}
+            Err(NotificationError::RateLimited) => shed += 1,
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
+    assert_eq!(shed, 10 - notifications::MAX_UPDATES_PER_SECOND, "Updates above the rate limit should be shed");
+    clock.advance(Duration::from_secs(1));
+    assert!(manager.notify("download", Notification::new("updates", "Загрузка", "Готово")).unwrap().updated);
+    
+    // Сокращение по графемам и ширине: кириллица, CJK, арабский, ZWJ-эмодзи
+    for (language, direction, text) in notification_corpus() {
+        assert_eq!(display_text::base_direction(text), direction, "Base direction of '{}'", language);
+        for width in [1, 5, 12, 17, 24, 40] {
+            let truncated = display_text::truncate(text, width);
+            assert!(display_width(&truncated) <= width, "'{}' is wider than {} columns", truncated, width);
+            assert_cluster_prefix(text, &truncated);
+        }
+        let delivery = manager.notify(&format!("i18n_{}", language), Notification::new("general", text, text)).unwrap();
+        let (title, body) = manager.get(delivery.id).unwrap().notification.collapsed();
+        assert_cluster_prefix(text, &title);
+        assert_cluster_prefix(text, &body);
+        println!("{:<12} {:>3} cols -> {}", language, display_width(text), title);
+    }
+    assert_eq!(display_width("👨‍👩‍👧‍👦"), 2, "ZWJ family is one emoji");
+    assert_eq!((display_width("🇷🇺"), display_width("❤️"), display_width("Анто\u{301}н")), (2, 2, 5));
+    assert_eq!(display_text::truncate("Семья 👨‍👩‍👧‍👦 в сборе", 8), "Семья…", "Emoji that does not fit is dropped whole");
+    assert_eq!(display_text::truncate("Семья 👨‍👩‍👧‍👦 в сборе", 9), "Семья 👨‍👩‍👧‍👦…");
+    assert_eq!(display_text::truncate("您的订单已发货", 9), "您的订单…");
+    let mixed = display_text::truncate("Анна: \u{2067}مرحبا بالجميع\u{2069} ok", 12);
+    assert_eq!(mixed.matches('\u{2067}').count(), mixed.matches('\u{2069}').count(), "Cut inside an isolate must close it");
+    assert_eq!(display_text::base_direction(&format!("Re: {}", display_text::isolate("שלום"))), Direction::Ltr);
+    assert_eq!(display_text::base_direction("\u{FEFF}Hello"), Direction::Ltr, "Byte order mark is not a strong RTL character");
+    
+    // Лимит активных уведомлений и отмена всех
+    let mut posted = manager.active().len();
+    while posted < notifications::MAX_ACTIVE_NOTIFICATIONS {
+        manager.notify(&format!("bulk_{}", posted), Notification::new("updates", "Пакет", "...")).unwrap();
+        posted += 1;
+    }
+    assert_eq!(
+        manager.notify("one_too_many", Notification::new("updates", "Пакет", "...")),
+        Err(NotificationError::TooManyNotifications { limit: notifications::MAX_ACTIVE_NOTIFICATIONS })
     );
+    let security_count = manager.active().iter().filter(|p| p.notification.channel_id == "security").count();
+    assert_eq!(manager.delete_channel("security"), Ok(security_count));
+    assert!(manager.notify("alert", Notification::new("security", "Внимание!", "...")).is_err());
+    assert_eq!(manager.cancel_all(), notifications::MAX_ACTIVE_NOTIFICATIONS - security_count);
+    assert!(manager.active().is_empty());
     
     println!("✓ Notifications E2E test completed");
 }
 
-fn send_notification(id: &str, title: &str, body: &str) -> u32 {
-   
-    println!("Sending notification: {} - {}", title, body);
-    id.len() as u32 
-}
+const NOTIFICATION_CORPUS: &str = include_str!("../captures/i18n/notification_corpus.tsv");
 
-fn simulate_notification_tap(notification_id: u32) {
-  
-    println!("Tapped notification with ID: {}", notification_id);
-    thread::sleep(Duration::from_millis(10));
+fn notification_corpus() -> Vec<(&'static str, Direction, &'static str)> {
+    NOTIFICATION_CORPUS
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut columns = line.split('\t');
+            let (language, direction, text) = (columns.next().unwrap(), columns.next().unwrap(), columns.next().unwrap());
+            let direction = match direction {
+                "ltr" => Direction::Ltr,
+                "rtl" => Direction::Rtl,
+                _ => Direction::Neutral,
+            };
+            (language, direction, text)
+        })
+        .collect()
 }
 
-#[cfg(target_os = "android")]
-fn create_notification_channel(id: &str, name: &str, description: &str) {
-    println!("Creating Android notification channel: {} - {}", name, description);
+// Сокращённая строка — префикс исходной по границам графем, плюс закрытие bidi и многоточие
+fn assert_cluster_prefix(original: &str, truncated: &str) {
+    if truncated == original {
+        return;
+    }
+    let bidi_closers = ['\u{202C}', '\u{2069}'];
+    let kept = truncated
+        .strip_suffix(display_text::ELLIPSIS)
+        .unwrap_or_else(|| panic!("Truncated '{}' should end with an ellipsis", truncated))
+        .trim_end_matches(bidi_closers);
+    let mut prefix = String::new();
+    let on_boundary = kept.is_empty()
+        || original.graphemes(true).any(|g| {
+            prefix.push_str(g);
+            prefix.trim_end().trim_end_matches(bidi_closers) == kept
+        });
+    assert!(on_boundary, "'{}' breaks a grapheme cluster of '{}'", truncated, original);
 }
 
-#[cfg(target_os = "ios")]
-fn request_notification_permission() {
-    println!("Requesting iOS notification permission");
+#[test]
+fn test_push_delivery_e2e() {
+    println!("=== PUSH DELIVERY E2E TEST ===");
+    
+    let gateway = PushGateway::start().expect("Failed to start push gateway");
+    let receiver = PushReceiver::register(gateway.addr(), "com.example.app").expect("Failed to register device");
+    let token = receiver.token().to_string();
+    let sender = PushSender::new(gateway.addr(), BackoffPolicy::Exponential(Duration::from_millis(20)), 3);
+    println!("Gateway on {}, device token {}", gateway.addr(), token);
+    
+    // Устройство онлайн: входящие пуши сразу становятся уведомлениями
+    let (arrivals_tx, arrivals) = mpsc::channel();
+    let online = Arc::new(AtomicBool::new(true));
+    let device = {
+        let online = Arc::clone(&online);
+        thread::spawn(move || {
+            let mut manager = NotificationManager::new(RealClock::shared());
+            manager.set_permission(true);
+            manager.create_channel(NotificationChannel::new("push", "Push", "Server messages", Importance::Default));
+            while online.load(Ordering::SeqCst) {
+                for message in receiver.poll(Duration::from_millis(200)).expect("Poll failed") {
+                    let key = message.collapse_key.clone().unwrap_or_else(|| message.message_id.to_string());
+                    manager
+                        .notify(&key, Notification::new("push", &message.title, &message.body))
+                        .expect("Failed to post push");
+                    arrivals_tx.send((message.message_id, Instant::now())).unwrap();
+                }
+            }
+            (receiver, manager.active().len())
+        })
+    };
+    
+    let mut sent_at = HashMap::new();
+    for i in 0..20 {
+        let message = PushMessage::new(&token, &format!("Сообщение {}", i), "Новое сообщение в чате");
+        let send_time = Instant::now();
+        let receipt = sender.send(&message).expect("Failed to send push");
+        assert_eq!(receipt.attempts, 1, "Healthy gateway should accept on the first attempt");
+        sent_at.insert(receipt.message_id, send_time);
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut latencies = Vec::new();
+    while latencies.len() < sent_at.len() {
+        let (id, arrived) = arrivals.recv_timeout(Duration::from_secs(2)).expect("Push was not delivered");
+        latencies.push(arrived - sent_at[&id]);
+    }
+    latencies.sort();
+    let p50 = latencies[latencies.len() / 2];
+    let p95 = latencies[latencies.len() * 95 / 100];
+    println!("Push latency over loopback: p50 {:?}, p95 {:?}, max {:?}", p50, p95, latencies.last().unwrap());
+    assert!(p95 < Duration::from_millis(50), "Push delivery too slow: p95 {:?}", p95);
+    
+    online.store(false, Ordering::SeqCst);
+    let (receiver, shown) = device.join().expect("Device thread panicked");
+    assert_eq!(shown, 20, "Every push should be shown as a notification");
+    
+    // Устройство офлайн: TTL и collapse key
+    sender.send(&PushMessage::new(&token, "Звонок", "Входящий вызов").with_ttl(Duration::ZERO)).unwrap();
+    sender.send(&PushMessage::new(&token, "Код", "Код подтверждения 1234").with_ttl(Duration::from_millis(100))).unwrap();
+    sender.send(&PushMessage::new(&token, "Письмо", "Новое письмо").with_ttl(Duration::from_secs(60))).unwrap();
+    for score in ["1:0", "1:1", "2:1"] {
+        sender.send(&PushMessage::new(&token, "Счёт матча", score).with_collapse_key("score")).unwrap();
+    }
+    thread::sleep(Duration::from_millis(200));
+    let queued: Vec<String> = receiver.poll(Duration::ZERO).unwrap().into_iter().map(|m| m.body).collect();
+    assert_eq!(queued, vec!["Новое письмо", "2:1"], "Expired and collapsed messages should not be delivered");
+    
+    // TTL 0 доходит только до подключённого устройства
+    let delivered = thread::scope(|scope| {
+        let poll = scope.spawn(|| receiver.poll(Duration::from_secs(2)));
+        thread::sleep(Duration::from_millis(100));
+        sender.send(&PushMessage::new(&token, "Звонок", "Входящий вызов").with_ttl(Duration::ZERO)).unwrap();
+        poll.join().unwrap().unwrap()
+    });
+    assert_eq!(delivered.len(), 1, "TTL 0 push should reach a connected device");
+    
+    // Некорректные запросы
+    assert_eq!(sender.send(&PushMessage::new("bogus", "Тест", "...")), Err(PushError::Unregistered));
+    assert!(matches!(sender.send(&PushMessage::new(&token, "", "...")), Err(PushError::InvalidArgument(_))));
+    let oversized = "x".repeat(push_gateway::MAX_PAYLOAD_BYTES + 1);
+    assert!(matches!(sender.send(&PushMessage::new(&token, "Тест", &oversized)), Err(PushError::InvalidArgument(_))));
+    
+    // Повторы при 503: экспоненциальная задержка и Retry-After
+    gateway.fail_next(2, None);
+    let start = Instant::now();
+    let receipt = sender.send(&PushMessage::new(&token, "Повтор", "...")).expect("Retries should succeed");
+    assert_eq!(receipt.attempts, 3);
+    assert!(start.elapsed() >= Duration::from_millis(60), "Retries should back off: {:?}", start.elapsed());
+    
+    gateway.fail_next(3, None);
+    assert_eq!(
+        sender.send(&PushMessage::new(&token, "Повтор", "...")),
+        Err(PushError::Unavailable { retry_after: None }),
+        "Sender should give up after max attempts"
+    );
+    
+    gateway.fail_next(1, Some(Duration::from_secs(1)));
+    let start = Instant::now();
+    let receipt = sender.send(&PushMessage::new(&token, "Повтор", "...")).unwrap();
+    assert_eq!(receipt.attempts, 2);
+    assert!(start.elapsed() >= Duration::from_secs(1), "Retry-After should be honoured: {:?}", start.elapsed());
+    
+    receiver.unregister().expect("Failed to unregister");
+    assert_eq!(sender.send(&PushMessage::new(&token, "Тест", "...")), Err(PushError::Unregistered));
+    
+    let stats = gateway.stats();
+    println!("Gateway stats: {:?}", stats);
+    assert_eq!(
+        stats,
+        GatewayStats { accepted: 29, delivered: 23, expired: 2, collapsed: 2, rejected: 4 },
+        "Unexpected gateway accounting"
+    );
+    
+    println!("✓ Push delivery E2E test completed");
 }
 
+#[test]
+fn test_connectivity_observer_e2e() {
+    println!("=== CONNECTIVITY OBSERVER E2E TEST ===");
+    
+    let connectivity = ConnectivityMonitor::new(NetworkState::offline());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let observer = {
+        let (seen, monitor) = (Arc::clone(&seen), connectivity.clone());
+        // Обработчик может обращаться к монитору: вызов идёт без блокировки
+        connectivity.register(move |event| seen.lock().unwrap().push((*event, monitor.current())))
+    };
+    assert!(seen.lock().unwrap().is_empty(), "Nothing to report while offline");
+    
+    let captive = NetworkState::wifi().unvalidated();
+    let roaming = NetworkState::cellular().roaming();
+    let steps = [
+        (captive, vec![ConnectivityEvent::Available(captive)]),
+        (NetworkState::wifi(), vec![ConnectivityEvent::CapabilitiesChanged { previous: captive, current: NetworkState::wifi() }]),
+        (NetworkState::wifi(), vec![]),
+        (
+            NetworkState::cellular(),
+            vec![ConnectivityEvent::Lost(NetworkState::wifi()), ConnectivityEvent::Available(NetworkState::cellular())],
+        ),
+        (roaming, vec![ConnectivityEvent::CapabilitiesChanged { previous: NetworkState::cellular(), current: roaming }]),
+        (NetworkState::offline(), vec![ConnectivityEvent::Lost(roaming)]),
+    ];
+    for (state, expected) in steps {
+        let before = seen.lock().unwrap().len();
+        assert_eq!(connectivity.set_state(state), expected, "Unexpected events for {:?}", state);
+        let delivered: Vec<_> = seen.lock().unwrap()[before..].to_vec();
+        assert_eq!(delivered.iter().map(|(event, _)| *event).collect::<Vec<_>>(), expected);
+        assert!(delivered.iter().all(|(_, current)| *current == state), "Observer saw a stale state");
+        println!("{:?} -> {:?} ({} events)", state.transport, state.network_type(), expected.len());
+    }
+    
+    // Новый наблюдатель сразу узнаёт о текущей сети
+    connectivity.set_state(NetworkState::wifi());
+    let late = connectivity.subscribe();
+    assert_eq!(late.try_recv(), Ok(ConnectivityEvent::Available(NetworkState::wifi())));
+    
+    assert!(connectivity.unregister(observer));
+    assert!(!connectivity.unregister(observer), "Observer cannot be removed twice");
+    let total = seen.lock().unwrap().len();
+    connectivity.set_state(NetworkState::cellular());
+    assert_eq!(seen.lock().unwrap().len(), total, "Unregistered observer should not be called");
+    assert_eq!(late.try_recv(), Ok(ConnectivityEvent::Lost(NetworkState::wifi())));
+    
+    // Подписка без receiver снимается на первом же событии
+    drop(late);
+    assert_eq!(connectivity.observer_count(), 1);
+    connectivity.set_state(NetworkState::wifi());
+    assert_eq!(connectivity.observer_count(), 0, "Dropped subscriber should be removed");
+    
+    // Ограничения JobScheduler берутся из текущей сети
+    let mut scheduler = JobScheduler::new(DeviceState { network: NetworkType::None, ..DeviceState::default() });
+    let mut backup = JobSpec::new("photo_backup");
+    backup.constraints.network = NetworkRequirement::Unmetered;
+    let backup_id = scheduler.schedule(backup, |_| JobResult::Success);
+    for (state, runs) in [(roaming, false), (NetworkState::wifi().metered(), false), (captive, false), (NetworkState::wifi(), true)] {
+        connectivity.set_state(state);
+        scheduler.set_device_state(DeviceState { network: connectivity.current().network_type(), ..DeviceState::default() });
+        scheduler.run_pending();
+        assert_eq!(scheduler.status(backup_id) == Some(JobStatus::Succeeded), runs, "Backup on {:?}", state);
+    }
+    
+    // Ожидание сети вместо sleep: поток платформы переключает сеть позже
+    connectivity.set_state(NetworkState::offline());
+    let start = Instant::now();
+    let platform = {
+        let connectivity = connectivity.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            connectivity.set_state(captive);
+            thread::sleep(Duration::from_millis(50));
+            connectivity.set_state(NetworkState::wifi());
+        })
+    };
+    let network = connectivity.wait_until(Duration::from_secs(2), NetworkState::is_usable);
+    let waited = start.elapsed();
+    platform.join().expect("Platform thread panicked");
+    println!("Validated network after {:?}", waited);
+    assert_eq!(network, Some(NetworkState::wifi()), "Captive portal should not count as connected");
+    assert!(waited >= Duration::from_millis(100), "Returned before validation: {:?}", waited);
+    
+    let start = Instant::now();
+    assert_eq!(connectivity.wait_until(Duration::from_millis(100), |n| n.transport == Transport::Cellular), None);
+    assert!(start.elapsed() >= Duration::from_millis(100), "wait_until should honour its timeout");
+    
+    println!("✓ Connectivity observer E2E test completed");
+}
 
 #[test]
 fn test_offline_functionality_e2e() {
@@ -517,90 +2075,173 @@ fn test_offline_functionality_e2e() {
     let cache_file = cache_dir.join("offline_cache.json");
     fs::write(&cache_file, cache_data).expect("Failed to write cache");
     
-   
-    simulate_network_loss();
-    
     
     assert!(cache_file.exists(), "Cache file should exist");
     
     let loaded_data = fs::read_to_string(&cache_file).expect("Failed to read cache");
     assert!(!loaded_data.is_empty(), "Cache should not be empty");
     
-    
-    let operations = perform_offline_operations(&cache_dir);
-    assert!(operations > 0, "Should perform some offline operations");
-    
-   
-    simulate_network_recovery();
-    
-    
-    let synced = sync_offline_data(&cache_dir);
-    assert!(synced, "Should sync data after reconnection");
+    // Один и тот же офлайн-сценарий с каждой стратегией разрешения конфликтов
+    let resolvers: [&dyn ConflictResolver; 3] = [&LastWriterWins, &ServerWins, &FieldMerge];
+    for resolver in resolvers {
+        let (server, outbox, report) = run_offline_scenario(&cache_dir, resolver);
+        println!("[{}] final sync: {:?}", resolver.name(), report);
+        
+        assert_eq!(report.remaining, 0, "Outbox should drain after reconnection");
+        assert_eq!((report.duplicates, report.conflicts, report.rejected), (1, 3, 1));
+        assert_eq!(outbox.dead_letters().len(), 1, "Invalid operation should be dead-lettered");
+        
+        // Ничего не потеряно и не записано дважды
+        let unique: std::collections::HashSet<&String> = server.writes.iter().collect();
+        assert_eq!(unique.len(), server.writes.len(), "An operation was applied twice");
+        assert_eq!(server.records("notes"), 5, "Unexpected number of notes on the server");
+        let note = |id: &str| server.record("notes", id).unwrap_or_else(|| panic!("{} is missing", id));
+        assert_eq!(note("note_4").version, 1, "Offline-created note should be written once");
+        assert_eq!(note("note_1").fields["title"], "План на неделю");
+        assert_eq!(note("note_1").fields["body"], "Пн: спорт, Ср: бассейн", "Second offline edit of a note was lost");
+        
+        let note_2 = &note("note_2").fields["title"];
+        let note_3 = &note("note_3").fields;
+        let note_5 = note("note_5");
+        match resolver.name() {
+            "last-writer-wins" => {
+                assert_eq!(report.kept_server, 0);
+                assert_eq!(note_2, "Идеи (черновик)");
+                assert_eq!((&note_3["body"], &note_3["tags"]), (&json!("Созвон в 10:00"), &json!(["work"])));
+                assert!(note_5.deleted, "Later local delete should win");
+            }
+            "server-wins" => {
+                assert_eq!(report.kept_server, 3);
+                assert_eq!(note_2, "Идеи (с планшета)");
+                assert_eq!((&note_3["body"], &note_3["tags"]), (&json!("Перенесли на 11:00"), &json!([])));
+                assert!(!note_5.deleted);
+            }
+            _ => {
+                assert_eq!(report.kept_server, 1);
+                assert_eq!(note_2, "Идеи (черновик)", "Both edited the title; the later local edit should win");
+                assert_eq!((&note_3["body"], &note_3["tags"]), (&json!("Перенесли на 11:00"), &json!(["work"])));
+                assert!(!note_5.deleted, "Remote edit should beat a local delete");
+            }
+        }
+    }
     
     println!("✓ Offline functionality E2E test completed");
 }
 
-fn simulate_network_loss() {
-    println!("Simulating network loss...");
-    thread::sleep(Duration::from_millis(100));
+fn json_fields(value: serde_json::Value) -> Fields {
+    value.as_object().expect("Expected a JSON object").clone()
 }
 
-fn simulate_network_recovery() {
-    println!("Simulating network recovery...");
-    thread::sleep(Duration::from_millis(100));
-}
-
-fn perform_offline_operations(cache_dir: &PathBuf) -> usize {
-   
-    let mut operations = 0;
-    
-    
-    for i in 0..5 {
-        let offline_item = cache_dir.join(format!("offline_item_{}.json", i));
-        let data = format!("{{\"id\": {}, \"data\": \"offline_{}\"}}", i, i);
-        fs::write(offline_item, data).expect("Failed to write offline item");
-        operations += 1;
+// Офлайн-правки, перезапуск приложения, правки с другого устройства и нестабильная сеть
+fn run_offline_scenario(cache_dir: &Path, resolver: &dyn ConflictResolver) -> (MockSyncServer, Outbox, SyncReport) {
+    let at = Duration::from_secs;
+    let outbox_path = cache_dir.join(format!("outbox_{}.jsonl", resolver.name()));
+    let _ = fs::remove_file(&outbox_path);
+    
+    let mut server = MockSyncServer::new();
+    let mut cached = HashMap::new();
+    for (id, title, body) in [
+        ("note_1", "Неделя", "Пусто"),
+        ("note_2", "Идеи", "Приложение для заметок"),
+        ("note_3", "Работа", "Созвон в 10:00"),
+        ("note_5", "Старое", "Можно удалить"),
+    ] {
+        let record = server.remote_write("notes", id, json_fields(json!({"title": title, "body": body, "tags": []})), at(100));
+        cached.insert(id, record.clone());
     }
     
-    operations
+    let connectivity = ConnectivityMonitor::new(NetworkState::wifi());
+    let events = connectivity.subscribe();
+    assert_eq!(events.try_recv(), Ok(ConnectivityEvent::Available(NetworkState::wifi())));
+    
+    // Сеть пропала: изменения копятся в outbox
+    server.fail_after(0);
+    assert_eq!(connectivity.set_state(NetworkState::offline()), vec![ConnectivityEvent::Lost(NetworkState::wifi())]);
+    let mut outbox = Outbox::open(&outbox_path).expect("Failed to open outbox");
+    let edits = [
+        ("note_4", Change::Upsert(json_fields(json!({"title": "Покупки", "body": "Молоко, хлеб", "tags": []}))), 1000),
+        ("note_1", Change::Upsert(json_fields(json!({"title": "План на неделю"}))), 1100),
+        ("note_1", Change::Upsert(json_fields(json!({"body": "Пн: спорт, Ср: бассейн"}))), 1200),
+        ("note_2", Change::Upsert(json_fields(json!({"title": "Идеи (черновик)"}))), 3000),
+        ("note_3", Change::Upsert(json_fields(json!({"tags": ["work"]}))), 3100),
+        ("note_5", Change::Delete, 3200),
+        ("", Change::Upsert(json_fields(json!({"title": "Без id"}))), 3300),
+    ];
+    for (id, change, time) in edits {
+        outbox.enqueue("notes", id, change, cached.get(id), Duration::from_millis(time)).expect("Failed to enqueue");
+    }
+    let offline = outbox.sync(&mut server, resolver).unwrap();
+    assert_eq!((offline.applied, offline.remaining), (0, 7), "Nothing should sync while offline");
+    
+    // Перезапуск приложения: очередь читается с диска в том же порядке
+    drop((outbox, events));
+    let mut outbox = Outbox::open(&outbox_path).expect("Failed to reopen outbox");
+    let events = connectivity.subscribe();
+    assert!(events.try_recv().is_err(), "No network should be reported while offline");
+    assert_eq!(outbox.pending().len(), 7, "Outbox should survive a restart");
+    assert!(outbox.pending().windows(2).all(|w| w[0].seq < w[1].seq), "Outbox lost its order");
+    
+    // Тем временем другое устройство правит те же заметки
+    server.remote_write("notes", "note_2", json_fields(json!({"title": "Идеи (с планшета)"})), Duration::from_millis(2000));
+    server.remote_write("notes", "note_3", json_fields(json!({"body": "Перенесли на 11:00"})), Duration::from_millis(2000));
+    server.remote_write("notes", "note_5", json_fields(json!({"body": "Ещё нужна"})), Duration::from_millis(2000));
+    
+    // Сначала роуминг, затем Wi-Fi за captive portal: синхронизации нет
+    connectivity.set_state(NetworkState::cellular().roaming());
+    assert!(sync_on_events(&events, &mut outbox, &mut server, resolver).is_empty(), "Should not sync while roaming");
+    assert_eq!(connectivity.observer_count(), 1, "Subscription from before the restart leaked");
+    connectivity.set_state(NetworkState::wifi().unvalidated());
+    assert!(sync_on_events(&events, &mut outbox, &mut server, resolver).is_empty(), "Should wait for validation");
+    
+    // Связь подтверждена, но рвётся после двух записей
+    server.recover();
+    server.fail_after(2);
+    connectivity.set_state(NetworkState::wifi());
+    let flaky = sync_on_events(&events, &mut outbox, &mut server, resolver);
+    assert_eq!(flaky.len(), 1, "Validated network should trigger exactly one sync");
+    assert_eq!((flaky[0].applied, flaky[0].remaining), (2, 5), "Sync should stop at the first transient failure");
+    
+    // Запись прошла, но ответ потерян: повтор не должен задублировать её
+    server.recover();
+    server.drop_next_ack();
+    let lost_ack = outbox.sync(&mut server, resolver).unwrap();
+    assert_eq!((lost_ack.applied, lost_ack.remaining), (0, 5));
+    
+    let report = outbox.sync(&mut server, resolver).unwrap();
+    (server, outbox, report)
 }
 
-fn sync_offline_data(cache_dir: &PathBuf) -> bool {
-    
-    println!("Syncing offline data...");
-    
-    
-    let mut synced_count = 0;
-    
-    for entry in fs::read_dir(cache_dir).unwrap().filter_map(Result::ok) {
-        if entry.file_name().to_string_lossy().starts_with("offline_item_") {
-           
-            println!("Syncing file: {:?}", entry.file_name());
-            synced_count += 1;
-            
-            fs::remove_file(entry.path()).ok();
+// Приложение синхронизирует outbox по событиям сети, а не по таймеру
+fn sync_on_events(
+    events: &mpsc::Receiver<ConnectivityEvent>,
+    outbox: &mut Outbox,
+    server: &mut MockSyncServer,
+    resolver: &dyn ConflictResolver,
+) -> Vec<SyncReport> {
+    let mut reports = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        let network = match event {
+            ConnectivityEvent::Available(state) | ConnectivityEvent::CapabilitiesChanged { current: state, .. } => state,
+            ConnectivityEvent::Lost(_) => continue,
+        };
+        if network.is_usable() && !network.roaming {
+            reports.push(outbox.sync(server, resolver).unwrap());
         }
     }
-    
-    synced_count > 0
+    reports
 }
 
-
 #[test]
 fn test_screen_rotation_e2e() {
     println!("=== SCREEN ROTATION E2E TEST ===");
     
-    let orientations = vec![
-        ("portrait", (1080, 1920)),
-        ("landscape", (1920, 1080)),
-        ("portrait_upside_down", (1080, 1920)),
-        ("landscape_left", (1920, 1080)),
-    ];
+    let mut recorder = SessionRecorder::new("screen_rotation_live", RealClock::shared());
     
-    for (orientation_name, (width, height)) in orientations {
+    for (orientation_name, (width, height)) in ROTATION_SCENARIO {
         let rotation_start = Instant::now();
         
         
+        recorder.record_rotation(orientation_name, width, height);
         simulate_screen_rotation(orientation_name, width, height);
         
         let rotation_time = rotation_start.elapsed();
@@ -621,9 +2262,19 @@ fn test_screen_rotation_e2e() {
         thread::sleep(Duration::from_millis(50));
     }
     
+    let artifact = save_session_artifact("test_screen_rotation_e2e", &recorder.finish());
+    println!("Rotation session saved to {:?}", artifact);
+    
     println!("✓ Screen rotation E2E test completed");
 }
 
+const ROTATION_SCENARIO: [(&str, (u32, u32)); 4] = [
+    ("portrait", (1080, 1920)),
+    ("landscape", (1920, 1080)),
+    ("portrait_upside_down", (1080, 1920)),
+    ("landscape_left", (1920, 1080)),
+];
+
 fn simulate_screen_rotation(orientation: &str, width: u32, height: u32) {
     println!("Rotating to {} ({}x{})", orientation, width, height);
     
@@ -687,17 +2338,19 @@ fn test_background_operations() {
     let background_result = Arc::new(Mutex::new(0));
     let background_result_clone = Arc::clone(&background_result);
     
-    let handle = thread::spawn(move || {
-        thread::sleep(Duration::from_secs(2));
+    let mut scheduler = JobScheduler::with_clock(DeviceState::default(), SimulatedClock::shared());
+    let mut spec = JobSpec::new("background_operation");
+    spec.constraints.network = NetworkRequirement::Connected;
+    spec.constraints.requires_storage_not_low = true;
+    
+    let job = scheduler.schedule(spec, move |_| {
         let mut result = background_result_clone.lock().unwrap();
         *result = 42;
+        JobResult::Success
     });
     
-  
-    thread::sleep(Duration::from_secs(1));
-    
-   
-    handle.join().unwrap();
+    assert_eq!(scheduler.run_pending(), 1, "Background job should run");
+    assert_eq!(scheduler.status(job), Some(JobStatus::Succeeded));
     
     let result = *background_result.lock().unwrap();
     assert_eq!(result, 42, "Background operation should complete");
@@ -775,10 +2428,22 @@ mod tests {
         println!("Starting all mobile E2E tests...");
         
         test_mobile_app_lifecycle_e2e();
+        test_app_lifecycle_transitions_e2e();
+        test_saved_state_bundle_e2e();
+        test_background_job_scheduler_e2e();
         test_touch_gestures_e2e();
+        test_input_session_replay_e2e();
         test_sensors_e2e();
+        test_sensor_timing_e2e();
+        test_location_trace_playback_e2e();
+        test_geofencing_e2e();
+        test_nmea_device_e2e();
+        test_orientation_fusion_e2e();
+        test_virtual_clock_e2e();
         test_power_efficiency_e2e();
         test_notifications_e2e();
+        test_push_delivery_e2e();
+        test_connectivity_observer_e2e();
         test_offline_functionality_e2e();
         test_screen_rotation_e2e();
         
@@ -0,0 +1,298 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sensors::{SensorConfig, SensorKind, SensorSource, SimulatedSensor, STANDARD_GRAVITY};
+
+// Android sensor convention: body frame x right, y up, z out of the screen; world frame is ENU
+const EARTH_FIELD: [f32; 3] = [0.0, 22.9, -42.1];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+    pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> Self {
+        let norm = dot(axis, axis).sqrt();
+        if norm < 1e-9 {
+            return Self::IDENTITY;
+        }
+        let (s, c) = (angle / 2.0).sin_cos();
+        Self { w: c, x: axis[0] / norm * s, y: axis[1] / norm * s, z: axis[2] / norm * s }
+    }
+
+    // Intrinsic Z-X-Y (yaw, then pitch about x, then roll about y), as Android reports orientation
+    pub fn from_euler_deg(yaw: f32, pitch: f32, roll: f32) -> Self {
+        Self::from_axis_angle([0.0, 0.0, 1.0], yaw.to_radians())
+            .mul(&Self::from_axis_angle([1.0, 0.0, 0.0], pitch.to_radians()))
+            .mul(&Self::from_axis_angle([0.0, 1.0, 0.0], roll.to_radians()))
+    }
+
+    // Rows of `m` are the world east/north/up axes expressed in the body frame
+    pub fn from_rotation_matrix(m: [[f32; 3]; 3]) -> Self {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self { w: 0.25 * s, x: (m[2][1] - m[1][2]) / s, y: (m[0][2] - m[2][0]) / s, z: (m[1][0] - m[0][1]) / s }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Self { w: (m[2][1] - m[1][2]) / s, x: 0.25 * s, y: (m[0][1] + m[1][0]) / s, z: (m[0][2] + m[2][0]) / s }
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Self { w: (m[0][2] - m[2][0]) / s, x: (m[0][1] + m[1][0]) / s, y: 0.25 * s, z: (m[1][2] + m[2][1]) / s }
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Self { w: (m[1][0] - m[0][1]) / s, x: (m[0][2] + m[2][0]) / s, y: (m[1][2] + m[2][1]) / s, z: 0.25 * s }
+        };
+        q.normalize()
+    }
+
+    pub fn mul(&self, o: &Self) -> Self {
+        Self {
+            w: self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+            x: self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            y: self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            z: self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+        }
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    pub fn normalize(&self) -> Self {
+        let n = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        Self { w: self.w / n, x: self.x / n, y: self.y / n, z: self.z / n }
+    }
+
+    // Body -> world
+    pub fn rotate(&self, v: [f32; 3]) -> [f32; 3] {
+        let p = Self { w: 0.0, x: v[0], y: v[1], z: v[2] };
+        let r = self.mul(&p).mul(&self.conjugate());
+        [r.x, r.y, r.z]
+    }
+
+    // Advances by a body-frame angular rate held for `dt` seconds
+    pub fn integrate(&self, rate: [f32; 3], dt: f32) -> Self {
+        let speed = dot(rate, rate).sqrt();
+        self.mul(&Self::from_axis_angle(rate, speed * dt)).normalize()
+    }
+
+    pub fn angle_to_deg(&self, o: &Self) -> f32 {
+        let d = (self.w * o.w + self.x * o.x + self.y * o.y + self.z * o.z).abs().min(1.0);
+        (2.0 * d.acos()).to_degrees()
+    }
+
+    // Normalized lerp along the short arc; fine for the small corrections a filter makes
+    pub fn nlerp(&self, o: &Self, t: f32) -> Self {
+        let d = self.w * o.w + self.x * o.x + self.y * o.y + self.z * o.z;
+        let o = if d < 0.0 { Self { w: -o.w, x: -o.x, y: -o.y, z: -o.z } } else { *o };
+        Self {
+            w: self.w + (o.w - self.w) * t,
+            x: self.x + (o.x - self.x) * t,
+            y: self.y + (o.y - self.y) * t,
+            z: self.z + (o.z - self.z) * t,
+        }
+        .normalize()
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn unit(v: [f32; 3]) -> Option<[f32; 3]> {
+    let n = dot(v, v).sqrt();
+    (n > 1e-6).then(|| [v[0] / n, v[1] / n, v[2] / n])
+}
+
+// Gravity + magnetic north, the same construction as SensorManager.getRotationMatrix
+pub fn orientation_from_gravity_and_field(accel: [f32; 3], mag: [f32; 3]) -> Option<Quaternion> {
+    let up = unit(accel)?;
+    let east = unit(cross(mag, up))?;
+    let north = cross(up, east);
+    Some(Quaternion::from_rotation_matrix([east, north, up]))
+}
+
+// Ground truth: holds `start`, spins at a constant body rate during [spin_from, spin_until), then holds again
+#[derive(Debug, Clone, Copy)]
+pub struct MotionProfile {
+    pub start: Quaternion,
+    pub rate: [f32; 3],
+    pub spin_from: Duration,
+    pub spin_until: Duration,
+}
+
+impl MotionProfile {
+    pub fn stationary(orientation: Quaternion) -> Self {
+        Self { start: orientation, rate: [0.0; 3], spin_from: Duration::ZERO, spin_until: Duration::ZERO }
+    }
+
+    pub fn orientation(&self, t: Duration) -> Quaternion {
+        let spun = t.clamp(self.spin_from, self.spin_until.max(self.spin_from)) - self.spin_from;
+        self.start.integrate(self.rate, spun.as_secs_f32())
+    }
+
+    pub fn angular_rate(&self, t: Duration) -> [f32; 3] {
+        if t >= self.spin_from && t < self.spin_until { self.rate } else { [0.0; 3] }
+    }
+}
+
+// Complementary filter: gyro integration pulled towards the accel/mag orientation with time constant `tau`
+pub struct ComplementaryFilter {
+    tau: f32,
+    orientation: Option<Quaternion>,
+    last_rate: [f32; 3],
+}
+
+impl ComplementaryFilter {
+    pub fn new(tau: Duration) -> Self {
+        Self { tau: tau.as_secs_f32(), orientation: None, last_rate: [0.0; 3] }
+    }
+
+    // Starts from a known (possibly wrong) estimate instead of the first accel/mag fix
+    pub fn with_initial(tau: Duration, orientation: Quaternion) -> Self {
+        Self { tau: tau.as_secs_f32(), orientation: Some(orientation), last_rate: [0.0; 3] }
+    }
+
+    // `dt` is the gap since the previous sample; the rate measured then is held across it
+    pub fn update(&mut self, gyro: [f32; 3], accel: [f32; 3], mag: [f32; 3], dt: f32) -> Option<Quaternion> {
+        let measured = orientation_from_gravity_and_field(accel, mag);
+        let rate = std::mem::replace(&mut self.last_rate, gyro);
+        let next = match (self.orientation, measured) {
+            (None, measured) => measured,
+            (Some(q), None) => Some(q.integrate(rate, dt)),
+            (Some(q), Some(m)) => {
+                let k = dt / (self.tau + dt);
+                Some(q.integrate(rate, dt).nlerp(&m, k))
+            }
+        };
+        self.orientation = next;
+        next
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleTiming {
+    pub rate_hz: f64,
+    pub drop_rate: f64,
+    // Fraction of the period each timestamp may be early or late
+    pub jitter: f64,
+    pub seed: u64,
+}
+
+impl SampleTiming {
+    pub fn ideal(rate_hz: f64) -> Self {
+        Self { rate_hz, drop_rate: 0.0, jitter: 0.0, seed: 7 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuSample {
+    pub timestamp: Duration,
+    pub accel: [f32; 3],
+    pub gyro: [f32; 3],
+    pub mag: [f32; 3],
+}
+
+// Accelerometer, gyroscope and magnetometer sampled together from `profile`
+pub fn simulate_imu(profile: MotionProfile, duration: Duration, timing: SampleTiming) -> Vec<ImuSample> {
+    let profile = Arc::new(profile);
+    let sensor = |kind: SensorKind| {
+        let mut config = SensorConfig::for_kind(kind);
+        config.rate_hz = timing.rate_hz;
+        config.seed ^= timing.seed;
+        if kind == SensorKind::Magnetometer {
+            // Hard-iron offset is removed by calibration before fusion
+            config.noise.bias = [0.0; 3];
+        }
+        let profile = Arc::clone(&profile);
+        SimulatedSensor::with_config(kind, config).with_signal(move |t| {
+            let to_body = profile.orientation(t).conjugate();
+            match kind {
+                SensorKind::Accelerometer => to_body.rotate([0.0, 0.0, STANDARD_GRAVITY]),
+                SensorKind::Gyroscope => profile.angular_rate(t),
+                _ => to_body.rotate(EARTH_FIELD),
+            }
+        })
+    };
+    let mut accel = sensor(SensorKind::Accelerometer);
+    let mut gyro = sensor(SensorKind::Gyroscope);
+    let mut mag = sensor(SensorKind::Magnetometer);
+
+    let mut rng = StdRng::seed_from_u64(timing.seed);
+    let period = 1.0 / timing.rate_hz;
+    let count = (duration.as_secs_f64() * timing.rate_hz) as usize;
+    let mut samples = Vec::with_capacity(count);
+
+    for k in 0..count {
+        if timing.drop_rate > 0.0 && rng.gen_range(0.0..1.0) < timing.drop_rate {
+            continue;
+        }
+        let offset = if timing.jitter > 0.0 { rng.gen_range(-timing.jitter..timing.jitter) } else { 0.0 };
+        let t = Duration::from_secs_f64(((k as f64 + offset) * period).max(0.0));
+        samples.push(ImuSample {
+            timestamp: t,
+            accel: accel.measure(t).values,
+            gyro: gyro.measure(t).values,
+            mag: mag.measure(t).values,
+        });
+    }
+    samples
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusionReport {
+    pub errors: Vec<(Duration, f32)>,
+}
+
+impl FusionReport {
+    // Time after which the error never again exceeds `threshold_deg`
+    pub fn converged_at(&self, threshold_deg: f32) -> Option<Duration> {
+        match self.errors.iter().rposition(|(_, e)| *e > threshold_deg) {
+            None => self.errors.first().map(|(t, _)| *t),
+            Some(i) => self.errors.get(i + 1).map(|(t, _)| *t),
+        }
+    }
+
+    pub fn max_error_after(&self, from: Duration) -> f32 {
+        self.errors.iter().filter(|(t, _)| *t >= from).map(|(_, e)| *e).fold(0.0, f32::max)
+    }
+
+    pub fn rms_error_after(&self, from: Duration) -> f32 {
+        let tail: Vec<f32> = self.errors.iter().filter(|(t, _)| *t >= from).map(|(_, e)| *e).collect();
+        if tail.is_empty() {
+            return 0.0;
+        }
+        (tail.iter().map(|e| e * e).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+}
+
+// dt comes from sample timestamps, so dropped or jittered samples are integrated over their real gap
+pub fn run_fusion(filter: &mut ComplementaryFilter, profile: &MotionProfile, samples: &[ImuSample]) -> FusionReport {
+    let mut errors = Vec::with_capacity(samples.len());
+    let mut last: Option<Duration> = None;
+
+    for sample in samples {
+        let dt = last.map_or(0.0, |l| sample.timestamp.saturating_sub(l).as_secs_f32());
+        last = Some(sample.timestamp);
+
+        if let Some(estimate) = filter.update(sample.gyro, sample.accel, sample.mag, dt) {
+            errors.push((sample.timestamp, estimate.angle_to_deg(&profile.orientation(sample.timestamp))));
+        }
+    }
+
+    FusionReport { errors }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::location_trace::{distance_m, LocationFix};
+
+// Android allows 100 active geofences per app
+pub const MAX_GEOFENCES: usize = 100;
+// Fixes worse than this say nothing useful about any fence
+pub const MAX_USABLE_ACCURACY: f32 = 100.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geofence {
+    pub id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_m: f64,
+    pub dwell_delay: Duration,
+}
+
+impl Geofence {
+    pub fn new(id: &str, latitude: f64, longitude: f64, radius_m: f64) -> Self {
+        Self {
+            id: id.to_string(),
+            latitude,
+            longitude,
+            radius_m,
+            dwell_delay: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_dwell_delay(mut self, delay: Duration) -> Self {
+        self.dwell_delay = delay;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeofenceTransition {
+    Enter,
+    Exit,
+    Dwell,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeofenceEvent {
+    pub fence_id: String,
+    pub transition: GeofenceTransition,
+    pub at: Duration,
+    pub distance_m: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeofenceError {
+    TooManyFences { limit: usize },
+    DuplicateId(String),
+    InvalidRadius(f64),
+    UnknownId(String),
+}
+
+impl fmt::Display for GeofenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeofenceError::TooManyFences { limit } => write!(f, "geofence limit of {} reached", limit),
+            GeofenceError::DuplicateId(id) => write!(f, "geofence '{}' already registered", id),
+            GeofenceError::InvalidRadius(r) => write!(f, "invalid geofence radius {}m", r),
+            GeofenceError::UnknownId(id) => write!(f, "no geofence '{}'", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Presence {
+    Unknown,
+    Outside,
+    Inside { since: Duration, dwell_sent: bool },
+}
+
+struct FenceState {
+    fence: Geofence,
+    presence: Presence,
+}
+
+pub struct GeofenceEngine {
+    fences: Vec<FenceState>,
+    limit: usize,
+}
+
+impl Default for GeofenceEngine {
+    fn default() -> Self {
+        Self::with_limit(MAX_GEOFENCES)
+    }
+}
+
+impl GeofenceEngine {
+    pub fn with_limit(limit: usize) -> Self {
+        Self { fences: Vec::new(), limit }
+    }
+
+    pub fn len(&self) -> usize {
+        self.fences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fences.is_empty()
+    }
+
+    pub fn add(&mut self, fence: Geofence) -> Result<(), GeofenceError> {
+        if !(fence.radius_m > 0.0 && fence.radius_m.is_finite()) {
+            return Err(GeofenceError::InvalidRadius(fence.radius_m));
+        }
+        if self.fences.iter().any(|s| s.fence.id == fence.id) {
+            return Err(GeofenceError::DuplicateId(fence.id));
+        }
+        if self.fences.len() >= self.limit {
+            return Err(GeofenceError::TooManyFences { limit: self.limit });
+        }
+        self.fences.push(FenceState { fence, presence: Presence::Unknown });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<Geofence, GeofenceError> {
+        let index = self
+            .fences
+            .iter()
+            .position(|s| s.fence.id == id)
+            .ok_or_else(|| GeofenceError::UnknownId(id.to_string()))?;
+        Ok(self.fences.remove(index).fence)
+    }
+
+    pub fn is_inside(&self, id: &str) -> Option<bool> {
+        self.fences
+            .iter()
+            .find(|s| s.fence.id == id)
+            .map(|s| matches!(s.presence, Presence::Inside { .. }))
+    }
+
+    // Hysteresis scales with the fix accuracy: entering needs the fix an accuracy radius inside
+    // the circle (at most half the fence radius), leaving needs it an accuracy radius outside.
+    // The first usable fix inside a fence reports ENTER, like INITIAL_TRIGGER_ENTER.
+    pub fn process(&mut self, fix: &LocationFix) -> Vec<GeofenceEvent> {
+        let mut events = Vec::new();
+        if fix.accuracy > MAX_USABLE_ACCURACY {
+            return events;
+        }
+        let accuracy = fix.accuracy as f64;
+
+        for state in &mut self.fences {
+            let fence = &state.fence;
+            let distance = distance_m(fence.latitude, fence.longitude, fix.latitude, fix.longitude);
+            let enter_margin = accuracy.min(fence.radius_m / 2.0);
+            let event = |transition| GeofenceEvent {
+                fence_id: fence.id.clone(),
+                transition,
+                at: fix.timestamp,
+                distance_m: distance,
+            };
+
+            match state.presence {
+                Presence::Unknown | Presence::Outside if distance < fence.radius_m - enter_margin => {
+                    events.push(event(GeofenceTransition::Enter));
+                    state.presence = Presence::Inside { since: fix.timestamp, dwell_sent: false };
+                }
+                Presence::Unknown if distance > fence.radius_m + accuracy => {
+                    state.presence = Presence::Outside;
+                }
+                Presence::Inside { .. } if distance > fence.radius_m + accuracy => {
+                    events.push(event(GeofenceTransition::Exit));
+                    state.presence = Presence::Outside;
+                }
+                Presence::Inside { since, dwell_sent: false } if fix.timestamp.saturating_sub(since) >= fence.dwell_delay => {
+                    events.push(event(GeofenceTransition::Dwell));
+                    state.presence = Presence::Inside { since, dwell_sent: true };
+                }
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
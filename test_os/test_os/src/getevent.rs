@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::touch::{MotionEvent, PointerCoords, TouchAction};
+
+// Raw axis ranges come from `adb shell getevent -p`; identity mapping when they match the display
+#[derive(Debug, Clone, Copy)]
+pub struct GeteventConfig {
+    pub raw_max_x: u32,
+    pub raw_max_y: u32,
+    pub display_width: u32,
+    pub display_height: u32,
+    pub pressure_max: u32,
+}
+
+impl Default for GeteventConfig {
+    fn default() -> Self {
+        Self {
+            raw_max_x: 1079,
+            raw_max_y: 2399,
+            display_width: 1080,
+            display_height: 2400,
+            pressure_max: 255,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawInputEvent {
+    pub time: Duration,
+    pub device: Option<String>,
+    pub ev_type: String,
+    pub code: String,
+    pub value: i64,
+}
+
+// `[   12345.678901] /dev/input/event2: EV_ABS       ABS_MT_POSITION_X    0000021c`
+// The device column is absent when getevent was pointed at a single device
+pub fn parse_line(line: &str) -> Result<Option<RawInputEvent>, String> {
+    let line = line.trim();
+    if !line.starts_with('[') {
+        return Ok(None);
+    }
+
+    let close = line.find(']').ok_or("unterminated timestamp")?;
+    let time = parse_timestamp(line[1..close].trim())?;
+
+    let mut rest = line[close + 1..].trim_start();
+    let mut device = None;
+    if rest.starts_with('/') {
+        let colon = rest.find(':').ok_or("device path without ':'")?;
+        device = Some(rest[..colon].to_string());
+        rest = &rest[colon + 1..];
+    }
+
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    if fields.len() != 3 {
+        return Err(format!("expected type, code and value, got '{}'", rest));
+    }
+
+    Ok(Some(RawInputEvent {
+        time,
+        device,
+        ev_type: fields[0].to_string(),
+        code: fields[1].to_string(),
+        value: parse_value(fields[2])?,
+    }))
+}
+
+// Kernel timestamps are seconds.microseconds; keep them exact instead of going through f64
+fn parse_timestamp(stamp: &str) -> Result<Duration, String> {
+    let bad = || format!("bad timestamp '{}'", stamp);
+    let (secs, micros) = stamp.split_once('.').unwrap_or((stamp, "0"));
+    let secs: u64 = secs.parse().map_err(|_| bad())?;
+    let micros: u32 = format!("{:0<6}", micros).get(..6).ok_or_else(bad)?.parse().map_err(|_| bad())?;
+    Ok(Duration::from_secs(secs) + Duration::from_micros(micros as u64))
+}
+
+fn parse_value(value: &str) -> Result<i64, String> {
+    match value {
+        "DOWN" => Ok(1),
+        "UP" => Ok(0),
+        // EV_KEY autorepeat while a key is held
+        "REPEAT" => Ok(2),
+        hex => u32::from_str_radix(hex, 16)
+            // ABS values are signed 32-bit; ffffffff is tracking id -1
+            .map(|raw| raw as i32 as i64)
+            .map_err(|_| format!("bad value '{}'", hex)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    tracking_id: Option<i64>,
+    x: i64,
+    y: i64,
+    pressure: Option<i64>,
+}
+
+// Multitouch protocol B (ABS_MT_SLOT) decoder; slot numbers become pointer ids
+pub struct GeteventParser {
+    config: GeteventConfig,
+    device: Option<String>,
+    slots: BTreeMap<u32, Slot>,
+    current_slot: u32,
+    active: BTreeMap<u32, PointerCoords>,
+    first_time: Option<Duration>,
+    events: Vec<MotionEvent>,
+}
+
+impl GeteventParser {
+    pub fn new(config: GeteventConfig) -> Self {
+        Self {
+            config,
+            device: None,
+            slots: BTreeMap::new(),
+            current_slot: 0,
+            active: BTreeMap::new(),
+            first_time: None,
+            events: Vec::new(),
+        }
+    }
+
+    // Only the first multitouch device in the capture is decoded; keys and other devices are skipped
+    pub fn feed(&mut self, raw: &RawInputEvent) {
+        if let Some(device) = &raw.device {
+            match &self.device {
+                Some(wanted) if wanted != device => return,
+                None if raw.code.starts_with("ABS_MT_") => self.device = Some(device.clone()),
+                None => return,
+                _ => {}
+            }
+        }
+
+        let slot = self.slots.entry(self.current_slot).or_default();
+        match (raw.ev_type.as_str(), raw.code.as_str()) {
+            ("EV_ABS", "ABS_MT_SLOT") => self.current_slot = raw.value.max(0) as u32,
+            ("EV_ABS", "ABS_MT_TRACKING_ID") => {
+                slot.tracking_id = if raw.value < 0 { None } else { Some(raw.value) };
+            }
+            ("EV_ABS", "ABS_MT_POSITION_X") => slot.x = raw.value,
+            ("EV_ABS", "ABS_MT_POSITION_Y") => slot.y = raw.value,
+            ("EV_ABS", "ABS_MT_PRESSURE") => slot.pressure = Some(raw.value),
+            ("EV_SYN", "SYN_REPORT") => self.sync(raw.time),
+            _ => {}
+        }
+    }
+
+    fn to_pointer(&self, id: u32, slot: &Slot) -> PointerCoords {
+        let c = &self.config;
+        let scale = |raw: i64, raw_max: u32, size: u32| {
+            raw as f32 * (size.saturating_sub(1)) as f32 / raw_max.max(1) as f32
+        };
+        PointerCoords {
+            id,
+            x: scale(slot.x, c.raw_max_x, c.display_width),
+            y: scale(slot.y, c.raw_max_y, c.display_height),
+            pressure: slot
+                .pressure
+                .map_or(1.0, |p| (p as f32 / c.pressure_max.max(1) as f32).min(1.0)),
+        }
+    }
+
+    // One SYN_REPORT frame can put fingers down, move others and lift some at once
+    fn sync(&mut self, time: Duration) {
+        let base = *self.first_time.get_or_insert(time);
+        let time = time.saturating_sub(base);
+
+        let now: BTreeMap<u32, PointerCoords> = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| slot.tracking_id.is_some())
+            .map(|(&id, slot)| (id, self.to_pointer(id, slot)))
+            .collect();
+
+        let lifted: Vec<u32> = self.active.keys().filter(|id| !now.contains_key(id)).copied().collect();
+        let landed: Vec<u32> = now.keys().filter(|id| !self.active.contains_key(id)).copied().collect();
+
+        let moved = now
+            .iter()
+            .any(|(id, p)| self.active.get(id).is_some_and(|old| old.x != p.x || old.y != p.y));
+        if moved {
+            let pointers: Vec<PointerCoords> =
+                self.active.keys().filter_map(|id| now.get(id)).copied().collect();
+            self.emit(TouchAction::Move, pointers[0].id, pointers, time);
+        }
+
+        for id in landed {
+            let action = if self.active.is_empty() { TouchAction::Down } else { TouchAction::PointerDown };
+            self.active.insert(id, now[&id]);
+            let pointers = self.active.values().copied().collect();
+            self.emit(action, id, pointers, time);
+        }
+
+        for id in lifted {
+            let pointers: Vec<PointerCoords> = self.active.values().copied().collect();
+            let action = if pointers.len() == 1 { TouchAction::Up } else { TouchAction::PointerUp };
+            self.emit(action, id, pointers, time);
+            self.active.remove(&id);
+        }
+
+        for (id, p) in now {
+            self.active.insert(id, p);
+        }
+    }
+
+    fn emit(&mut self, action: TouchAction, action_pointer: u32, pointers: Vec<PointerCoords>, time: Duration) {
+        self.events.push(MotionEvent { action, action_pointer, pointers, time });
+    }
+
+    pub fn finish(self) -> Vec<MotionEvent> {
+        self.events
+    }
+}
+
+pub fn parse_getevent(log: &str, config: GeteventConfig) -> Result<Vec<MotionEvent>, String> {
+    let mut parser = GeteventParser::new(config);
+    for (i, line) in log.lines().enumerate() {
+        if let Some(raw) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))? {
+            parser.feed(&raw);
+        }
+    }
+    Ok(parser.finish())
+}
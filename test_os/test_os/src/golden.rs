@@ -0,0 +1,278 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Set to 1 to overwrite the goldens with whatever the test renders
+pub const REGENERATE_ENV: &str = "UPDATE_GOLDENS";
+
+// Largest YIQ delta, between black and white
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    // RGBA8, row major
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+        assert_eq!(rgba.len(), (width * height * 4) as usize, "RGBA buffer does not match {}x{}", width, height);
+        Self { width, height, rgba }
+    }
+
+    pub fn decode(png: &[u8]) -> Result<Self, GoldenError> {
+        let mut decoder = png::Decoder::new(png);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(GoldenError::Png(format!("{:?} bit PNGs are not supported", info.bit_depth)));
+        }
+        let rgba = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => return Err(GoldenError::Png("palette was not expanded".to_string())),
+        };
+        Ok(Self::new(info.width, info.height, rgba))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GoldenError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgba)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    // Perceptual distance in 0..1 a single pixel may be off by before it counts as different
+    pub pixel: f32,
+    // Share of pixels allowed to differ
+    pub differing: f64,
+}
+
+impl Tolerance {
+    pub fn new(pixel: f32, differing: f64) -> Self {
+        Self { pixel, differing }
+    }
+
+    pub fn exact() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    pub pixels: usize,
+    pub differing: usize,
+    pub max_distance: f32,
+    pub mean_distance: f32,
+}
+
+impl DiffReport {
+    pub fn differing_fraction(&self) -> f64 {
+        self.differing as f64 / self.pixels.max(1) as f64
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.differing_fraction() <= tolerance.differing
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} pixels differ ({:.3}%), max distance {:.3}, mean {:.4}",
+            self.differing,
+            self.pixels,
+            self.differing_fraction() * 100.0,
+            self.max_distance,
+            self.mean_distance
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoldenError {
+    // The rendered image was saved to `actual` so it can be reviewed and copied in
+    Missing { name: String, actual: PathBuf },
+    SizeMismatch { expected: (u32, u32), actual: (u32, u32) },
+    Mismatch { report: DiffReport, diff: PathBuf },
+    Io(String),
+    Png(String),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Missing { name, actual } => write!(
+                f,
+                "no golden named {}, rendered image saved to {} (set {}=1 to create it)",
+                name,
+                actual.display(),
+                REGENERATE_ENV
+            ),
+            GoldenError::SizeMismatch { expected, actual } => {
+                write!(f, "size mismatch: golden is {}x{}, got {}x{}", expected.0, expected.1, actual.0, actual.1)
+            }
+            GoldenError::Mismatch { report, diff } => write!(f, "{}, diff saved to {}", report, diff.display()),
+            GoldenError::Io(message) => write!(f, "I/O error: {}", message),
+            GoldenError::Png(message) => write!(f, "PNG error: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(error: io::Error) -> Self {
+        GoldenError::Io(error.to_string())
+    }
+}
+
+impl From<png::DecodingError> for GoldenError {
+    fn from(error: png::DecodingError) -> Self {
+        match error {
+            png::DecodingError::IoError(error) => error.into(),
+            error => GoldenError::Png(error.to_string()),
+        }
+    }
+}
+
+impl From<png::EncodingError> for GoldenError {
+    fn from(error: png::EncodingError) -> Self {
+        GoldenError::Png(error.to_string())
+    }
+}
+
+// Composited over white, so a difference only in a fully transparent pixel is invisible
+fn yiq(pixel: &[u8]) -> (f32, f32, f32) {
+    let alpha = pixel[3] as f32 / 255.0;
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| 255.0 + (c as f32 - 255.0) * alpha);
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+        r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+    )
+}
+
+// Perceptual colour difference in 0..1, weighting brightness over hue as the eye does
+pub fn pixel_distance(a: &[u8], b: &[u8]) -> f32 {
+    if a == b {
+        return 0.0;
+    }
+    let (ya, ia, qa) = yiq(a);
+    let (yb, ib, qb) = yiq(b);
+    let (y, i, q) = (ya - yb, ia - ib, qa - qb);
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_YIQ_DELTA).sqrt().min(1.0)
+}
+
+pub fn compare(expected: &Image, actual: &Image, tolerance: &Tolerance) -> DiffReport {
+    assert_eq!((expected.width, expected.height), (actual.width, actual.height), "images differ in size");
+    let mut report = DiffReport { pixels: (expected.width * expected.height) as usize, differing: 0, max_distance: 0.0, mean_distance: 0.0 };
+    let mut total = 0.0;
+    for (a, b) in expected.rgba.chunks_exact(4).zip(actual.rgba.chunks_exact(4)) {
+        let distance = pixel_distance(a, b);
+        if distance > tolerance.pixel {
+            report.differing += 1;
+        }
+        report.max_distance = report.max_distance.max(distance);
+        total += distance as f64;
+    }
+    report.mean_distance = (total / report.pixels.max(1) as f64) as f32;
+    report
+}
+
+// Faded copy of the golden with pixels over the tolerance in red and smaller changes in yellow
+pub fn diff_image(expected: &Image, actual: &Image, tolerance: &Tolerance) -> Image {
+    let rgba = expected
+        .rgba
+        .chunks_exact(4)
+        .zip(actual.rgba.chunks_exact(4))
+        .flat_map(|(a, b)| {
+            let distance = pixel_distance(a, b);
+            if distance > tolerance.pixel {
+                [255, 0, 0, 255]
+            } else if distance > 0.0 {
+                [255, 200, 0, 255]
+            } else {
+                let faded = (255.0 - (255.0 - yiq(a).0) * 0.1) as u8;
+                [faded, faded, faded, 255]
+            }
+        })
+        .collect();
+    Image::new(expected.width, expected.height, rgba)
+}
+
+// (name, PNG bytes) pairs, usually from include_bytes! since the source tree is not on the device
+pub type EmbeddedGoldens = &'static [(&'static str, &'static [u8])];
+
+// Goldens are compiled in; regenerated goldens, rendered images and diffs of failed checks are
+// written to `output` to be pulled off the device
+pub struct Goldens {
+    embedded: EmbeddedGoldens,
+    output: PathBuf,
+    regenerate: bool,
+}
+
+impl Goldens {
+    pub fn new(embedded: EmbeddedGoldens, output: PathBuf) -> Self {
+        let regenerate = std::env::var(REGENERATE_ENV).is_ok_and(|value| value == "1" || value == "true");
+        Self { embedded, output, regenerate }
+    }
+
+    pub fn regenerating(mut self, regenerate: bool) -> Self {
+        self.regenerate = regenerate;
+        self
+    }
+
+    pub fn is_regenerating(&self) -> bool {
+        self.regenerate
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    pub fn check(&self, name: &str, actual: &Image, tolerance: &Tolerance) -> Result<DiffReport, GoldenError> {
+        if self.regenerate {
+            actual.save(&self.output.join(format!("{}.png", name)))?;
+            return Ok(compare(actual, actual, tolerance));
+        }
+
+        let actual_path = self.output.join(format!("{}.actual.png", name));
+        let Some(&(_, png)) = self.embedded.iter().find(|(golden, _)| *golden == name) else {
+            actual.save(&actual_path)?;
+            return Err(GoldenError::Missing { name: name.to_string(), actual: actual_path });
+        };
+        let expected = Image::decode(png)?;
+        if (expected.width, expected.height) != (actual.width, actual.height) {
+            actual.save(&actual_path)?;
+            return Err(GoldenError::SizeMismatch {
+                expected: (expected.width, expected.height),
+                actual: (actual.width, actual.height),
+            });
+        }
+
+        let report = compare(&expected, actual, tolerance);
+        if !report.passes(tolerance) {
+            let diff = self.output.join(format!("{}.diff.png", name));
+            actual.save(&actual_path)?;
+            diff_image(&expected, actual, tolerance).save(&diff)?;
+            return Err(GoldenError::Mismatch { report, diff });
+        }
+        Ok(report)
+    }
+}
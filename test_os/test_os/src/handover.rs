@@ -0,0 +1,217 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::http_load::{Endpoint, HttpConnection, HttpError, HttpResponse, HttpServer};
+use crate::network_emulator::{NetworkEmulator, NetworkProfile};
+
+const STREAM_BYTES: usize = 64 * 1024;
+const STREAM_CHUNKS: usize = 16;
+const UPLOAD_BYTES: usize = 64 * 1024;
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowKind {
+    // Small requests on one pooled connection
+    KeepAlive,
+    // Long chunked response
+    Streaming,
+    Upload,
+}
+
+impl FlowKind {
+    pub const ALL: [FlowKind; 3] = [FlowKind::KeepAlive, FlowKind::Streaming, FlowKind::Upload];
+
+    fn send(self, connection: &mut HttpConnection) -> Result<HttpResponse, HttpError> {
+        match self {
+            FlowKind::KeepAlive => connection.request("GET", "/ping", &[]),
+            FlowKind::Streaming => connection.request("GET", "/stream", &[]),
+            FlowKind::Upload => connection.request("POST", "/upload", &vec![0x55; UPLOAD_BYTES]),
+        }
+    }
+
+    // A 200 is not enough: a stream cut short or a partial upload is a failure too
+    fn check(self, response: HttpResponse) -> Result<(), HttpError> {
+        if !(200..300).contains(&response.status) {
+            return Err(HttpError::Status(response.status));
+        }
+        match self {
+            FlowKind::KeepAlive => Ok(()),
+            FlowKind::Streaming if response.body.len() == STREAM_BYTES => Ok(()),
+            FlowKind::Streaming => Err(HttpError::Protocol(format!("stream ended after {} bytes", response.body.len()))),
+            FlowKind::Upload if response.header("x-received-bytes") == Some(UPLOAD_BYTES.to_string().as_str()) => Ok(()),
+            FlowKind::Upload => Err(HttpError::Protocol("server got a partial upload".to_string())),
+        }
+    }
+
+    fn pause(self) -> Duration {
+        match self {
+            FlowKind::KeepAlive => Duration::from_millis(20),
+            FlowKind::Streaming | FlowKind::Upload => Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Completed,
+    // Surfaced an error the app can act on right away
+    FailedCleanly(HttpError),
+    // No progress and no error until the client's own timeout gave up
+    Hung,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Completed => write!(f, "completed"),
+            Outcome::FailedCleanly(error) => write!(f, "failed cleanly ({})", error),
+            Outcome::Hung => write!(f, "hung until timeout"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowReport {
+    pub flow: FlowKind,
+    // First request to finish after the handover: in flight when it hit, or sent on a pooled
+    // connection that had died
+    pub affected: Option<Outcome>,
+    // From the handover until a request succeeded again
+    pub recovery: Option<Duration>,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for FlowReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: ", self.flow)?;
+        match &self.affected {
+            Some(outcome) => write!(f, "{}", outcome)?,
+            None => write!(f, "nothing in flight")?,
+        }
+        match self.recovery {
+            Some(recovery) => write!(f, ", recovered in {:?}", recovery)?,
+            None => write!(f, ", never recovered")?,
+        }
+        write!(f, ", {} ok / {} failed", self.completed, self.failed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandoverConfig {
+    pub from: NetworkProfile,
+    pub to: NetworkProfile,
+    // Black-hole old connections instead of resetting them
+    pub silent: bool,
+    // Radio switch time before the new address is usable
+    pub switch_delay: Duration,
+    pub request_timeout: Duration,
+    pub before: Duration,
+    pub after: Duration,
+}
+
+impl HandoverConfig {
+    pub fn new(from: NetworkProfile, to: NetworkProfile) -> Self {
+        Self {
+            from,
+            to,
+            silent: false,
+            switch_delay: Duration::from_millis(200),
+            request_timeout: Duration::from_secs(1),
+            before: Duration::from_millis(500),
+            after: Duration::from_secs(3),
+        }
+    }
+
+    pub fn silent(mut self) -> Self {
+        self.silent = true;
+        self
+    }
+}
+
+struct Attempt {
+    started: Instant,
+    finished: Instant,
+    result: Result<(), HttpError>,
+}
+
+// Behaves like a typical app: pooled connections are kept until they fail, and only new
+// connections go through the address the current network provides
+fn run_flow(flow: FlowKind, route: &Mutex<SocketAddr>, timeout: Duration, stop: &AtomicBool) -> Vec<Attempt> {
+    let mut attempts = Vec::new();
+    let mut connection = HttpConnection::new(*route.lock().unwrap(), timeout, true);
+    while !stop.load(Ordering::SeqCst) {
+        let started = Instant::now();
+        let result = flow.send(&mut connection).and_then(|response| flow.check(response));
+        let failed = result.is_err();
+        attempts.push(Attempt { started, finished: Instant::now(), result });
+        if failed {
+            connection = HttpConnection::new(*route.lock().unwrap(), timeout, true);
+            thread::sleep(RETRY_DELAY);
+        } else {
+            thread::sleep(flow.pause());
+        }
+    }
+    attempts
+}
+
+fn report(flow: FlowKind, attempts: &[Attempt], handover_at: Instant) -> FlowReport {
+    let affected = attempts.iter().find(|a| a.finished > handover_at).map(|a| match &a.result {
+        Ok(()) => Outcome::Completed,
+        Err(HttpError::Timeout) => Outcome::Hung,
+        Err(error) => Outcome::FailedCleanly(error.clone()),
+    });
+    let recovery = attempts
+        .iter()
+        .find(|a| a.started >= handover_at && a.result.is_ok())
+        .map(|a| a.finished - handover_at);
+    FlowReport {
+        flow,
+        affected,
+        recovery,
+        completed: attempts.iter().filter(|a| a.result.is_ok()).count(),
+        failed: attempts.iter().filter(|a| a.result.is_err()).count(),
+    }
+}
+
+// Runs all flows against a local server over `from`, then hands over to `to`: old connections
+// are dropped and the server becomes reachable through a different local port
+pub fn run_handover(config: &HandoverConfig) -> io::Result<Vec<FlowReport>> {
+    let server = HttpServer::start(vec![
+        Endpoint::new("/ping").with_payload(256),
+        Endpoint::new("/stream").with_payload(STREAM_BYTES).streaming(STREAM_CHUNKS, Duration::from_millis(40)),
+        Endpoint::new("/upload").with_payload(16),
+    ])?;
+    let mut emulator = NetworkEmulator::new(config.from.clone(), 46);
+    let proxy = emulator.proxy_tcp(server.addr())?;
+    let route = Arc::new(Mutex::new(proxy));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let flows: Vec<_> = FlowKind::ALL
+        .iter()
+        .map(|&flow| {
+            let (route, stop, timeout) = (Arc::clone(&route), Arc::clone(&stop), config.request_timeout);
+            thread::spawn(move || run_flow(flow, &route, timeout, &stop))
+        })
+        .collect();
+
+    thread::sleep(config.before);
+    let handover_at = Instant::now();
+    let new_proxy = emulator.migrate(proxy, config.silent)?;
+    thread::sleep(config.switch_delay);
+    emulator.set_profile(config.to.clone());
+    *route.lock().unwrap() = new_proxy;
+    thread::sleep(config.after);
+
+    stop.store(true, Ordering::SeqCst);
+    Ok(FlowKind::ALL
+        .iter()
+        .zip(flows)
+        .map(|(&flow, handle)| report(flow, &handle.join().unwrap_or_default(), handover_at))
+        .collect())
+}
@@ -0,0 +1,630 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub path: String,
+    // Server think time before the response starts
+    pub latency: Duration,
+    pub status: u16,
+    pub payload_bytes: usize,
+    // Every nth request to this endpoint gets the given status instead
+    pub fail_every: Option<(usize, u16)>,
+    // Chunked transfer of the payload in this many pieces with a pause after each
+    pub streaming: Option<(usize, Duration)>,
+}
+
+impl Endpoint {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            latency: Duration::ZERO,
+            status: 200,
+            payload_bytes: 0,
+            fail_every: None,
+            streaming: None,
+        }
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_payload(mut self, bytes: usize) -> Self {
+        self.payload_bytes = bytes;
+        self
+    }
+
+    pub fn failing_every(mut self, nth: usize, status: u16) -> Self {
+        self.fail_every = Some((nth.max(1), status));
+        self
+    }
+
+    pub fn streaming(mut self, chunks: usize, interval: Duration) -> Self {
+        self.streaming = Some((chunks.max(1), interval));
+        self
+    }
+}
+
+struct ServerState {
+    endpoints: Vec<Endpoint>,
+    hits: Vec<AtomicUsize>,
+    // Body bytes the next full response gets before its connection is closed
+    cut_next: Mutex<Option<usize>>,
+}
+
+// HTTP/1.1 server on 127.0.0.1 with keep-alive; any method on a configured path gets its canned
+// response, with single byte ranges honoured on plain ones. Request bodies are read and
+// discarded, their size comes back in X-Received-Bytes
+pub struct HttpServer {
+    addr: SocketAddr,
+    state: Arc<ServerState>,
+    shutdown: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub fn start(endpoints: Vec<Endpoint>) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(ServerState {
+            hits: endpoints.iter().map(|_| AtomicUsize::new(0)).collect(),
+            endpoints,
+            cut_next: Mutex::new(None),
+        });
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = Arc::clone(&state);
+                    thread::spawn(move || {
+                        let _ = serve(stream, &state);
+                    });
+                }
+            })
+        };
+
+        Ok(Self { addr, state, shutdown, acceptor: Some(acceptor) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // The next 200 response stops after `bytes` of its body and the connection is dropped,
+    // a deterministic way to interrupt a transfer at a known point
+    pub fn cut_next_response(&self, bytes: usize) {
+        *self.state.cut_next.lock().unwrap() = Some(bytes);
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the blocking accept
+        let _ = TcpStream::connect(self.addr);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+struct Request {
+    path: String,
+    close: bool,
+    received: u64,
+    range: Option<String>,
+    if_range: Option<String>,
+}
+
+// None once the client has closed the connection between requests
+fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>> {
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let _method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let http10 = parts.next() == Some("HTTP/1.0");
+
+    let mut content_length: u64 = 0;
+    let mut close = http10;
+    let (mut range, mut if_range) = (None, None);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(0);
+        } else if name.eq_ignore_ascii_case("connection") {
+            close = value.eq_ignore_ascii_case("close");
+        } else if name.eq_ignore_ascii_case("range") {
+            range = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("if-range") {
+            if_range = Some(value.to_string());
+        }
+    }
+    let received = io::copy(&mut reader.by_ref().take(content_length), &mut io::sink())?;
+    if received < content_length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let path = target.split_once('?').map_or(target, |(path, _)| path).to_string();
+    Ok(Some(Request { path, close, received, range, if_range }))
+}
+
+fn serve(stream: TcpStream, state: &ServerState) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(request) = read_request(&mut reader)? {
+        let mut etag = None;
+        let (mut status, mut payload, streaming) = match state.endpoints.iter().position(|e| e.path == request.path) {
+            Some(index) => {
+                let endpoint = &state.endpoints[index];
+                let hit = state.hits[index].fetch_add(1, Ordering::SeqCst) + 1;
+                thread::sleep(endpoint.latency);
+                match endpoint.fail_every {
+                    Some((nth, status)) if hit.is_multiple_of(nth) => (status, Vec::new(), None),
+                    _ => {
+                        etag = Some(format!("\"{}-{}\"", index, endpoint.payload_bytes));
+                        (endpoint.status, payload(endpoint.payload_bytes), endpoint.streaming)
+                    }
+                }
+            }
+            None => (404, Vec::new(), None),
+        };
+
+        let mut headers = Vec::new();
+        if let Some(etag) = &etag {
+            headers.push(format!("ETag: {}", etag));
+            headers.push("Accept-Ranges: bytes".to_string());
+        }
+        // A stale If-Range means the client's partial copy is of something else: send it all
+        let range = request.range.filter(|_| status == 200 && streaming.is_none());
+        if let Some(range) = range.filter(|_| request.if_range.is_none() || request.if_range == etag) {
+            let total = payload.len();
+            match byte_range(&range, total) {
+                Some((start, end)) => {
+                    headers.push(format!("Content-Range: bytes {}-{}/{}", start, end, total));
+                    payload = payload[start..=end].to_vec();
+                    status = 206;
+                }
+                None => {
+                    headers.push(format!("Content-Range: bytes */{}", total));
+                    payload.clear();
+                    status = 416;
+                }
+            }
+        }
+
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/octet-stream\r\nX-Received-Bytes: {}\r\nConnection: {}\r\n",
+            status,
+            reason(status),
+            request.received,
+            if request.close { "close" } else { "keep-alive" }
+        )?;
+        for header in headers {
+            write!(writer, "{}\r\n", header)?;
+        }
+        match streaming {
+            Some((chunks, interval)) => {
+                write!(writer, "Transfer-Encoding: chunked\r\n\r\n")?;
+                for chunk in payload.chunks(payload.len().div_ceil(chunks).max(1)) {
+                    write!(writer, "{:x}\r\n", chunk.len())?;
+                    writer.write_all(chunk)?;
+                    write!(writer, "\r\n")?;
+                    writer.flush()?;
+                    thread::sleep(interval);
+                }
+                write!(writer, "0\r\n\r\n")?;
+            }
+            None => {
+                write!(writer, "Content-Length: {}\r\n\r\n", payload.len())?;
+                let cut = if status == 200 { state.cut_next.lock().unwrap().take() } else { None };
+                if let Some(bytes) = cut {
+                    writer.write_all(&payload[..bytes.min(payload.len())])?;
+                    writer.flush()?;
+                    return Ok(());
+                }
+                writer.write_all(&payload)?;
+            }
+        }
+        writer.flush()?;
+        if request.close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Printable and position-dependent, so truncated or shifted bodies do not compare equal
+pub fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| b'a' + (i % 26) as u8).collect()
+}
+
+// "bytes=start-end", "bytes=start-" or "bytes=-suffix" as an inclusive range, None if unsatisfiable
+fn byte_range(spec: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = spec.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let last = len.checked_sub(1)?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (len.saturating_sub(suffix.parse().ok()?), last),
+        (start, "") => (start.parse().ok()?, last),
+        (start, end) => (start.parse().ok()?, end.parse::<usize>().ok()?.min(last)),
+    };
+    (start <= end).then_some((start, end))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HttpError {
+    Refused,
+    Timeout,
+    // Closed or reset by the peer mid-exchange
+    Reset,
+    Protocol(String),
+    // A complete response outside 2xx
+    Status(u16),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Refused => write!(f, "connection refused"),
+            HttpError::Timeout => write!(f, "timed out"),
+            HttpError::Reset => write!(f, "connection reset"),
+            HttpError::Protocol(message) => write!(f, "protocol error: {}", message),
+            HttpError::Status(status) => write!(f, "HTTP {}", status),
+        }
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::ConnectionRefused => HttpError::Refused,
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => HttpError::Timeout,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => HttpError::Reset,
+            _ => HttpError::Protocol(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+// One client connection, reused across requests while the server keeps it alive
+pub struct HttpConnection {
+    addr: SocketAddr,
+    timeout: Duration,
+    keep_alive: bool,
+    stream: Option<BufReader<TcpStream>>,
+}
+
+impl HttpConnection {
+    pub fn new(addr: SocketAddr, timeout: Duration, keep_alive: bool) -> Self {
+        Self { addr, timeout, keep_alive, stream: None }
+    }
+
+    pub fn request(&mut self, method: &str, path: &str, body: &[u8]) -> Result<HttpResponse, HttpError> {
+        let mut response = self.send(method, path, &[], body)?;
+        let mut response_body = Vec::new();
+        self.read_body(&response, &mut response_body)?;
+        response.body = response_body;
+        Ok(response)
+    }
+
+    // Sends a request and reads the status line and headers; the body must then be consumed
+    // with `read_body` before the connection can be reused
+    pub fn send(
+        &mut self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<HttpResponse, HttpError> {
+        let result = self.send_head(method, path, headers, body);
+        // Whatever failed, the stream position is unknown; start over on the next request
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    // Streams the body into `sink` as it arrives, so whatever came in before a failure is kept
+    pub fn read_body(&mut self, response: &HttpResponse, sink: &mut dyn Write) -> Result<(), HttpError> {
+        let Some(reader) = self.stream.as_mut() else {
+            return Err(HttpError::Reset);
+        };
+        let result = if response.header("transfer-encoding").is_some_and(|te| te.eq_ignore_ascii_case("chunked")) {
+            read_chunked(reader, sink)
+        } else {
+            match response.header("content-length").and_then(|len| len.parse().ok()) {
+                Some(content_length) => copy_exact(reader, content_length, sink),
+                None => Err(HttpError::Protocol("missing Content-Length".to_string())),
+            }
+        };
+        if result.is_err()
+            || !self.keep_alive
+            || response.header("connection").is_some_and(|c| c.eq_ignore_ascii_case("close"))
+        {
+            self.stream = None;
+        }
+        result
+    }
+
+    fn send_head(
+        &mut self,
+        method: &str,
+        path: &str,
+        request_headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<HttpResponse, HttpError> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect_timeout(&self.addr, self.timeout)?;
+            stream.set_read_timeout(Some(self.timeout))?;
+            stream.set_write_timeout(Some(self.timeout))?;
+            stream.set_nodelay(true)?;
+            self.stream = Some(BufReader::new(stream));
+        }
+        let reader = self.stream.as_mut().unwrap();
+
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n",
+            method,
+            path,
+            self.addr,
+            body.len()
+        );
+        for (name, value) in request_headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !self.keep_alive {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        let stream = reader.get_mut();
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+
+        let mut status_line = String::new();
+        if reader.read_line(&mut status_line)? == 0 {
+            return Err(HttpError::Reset);
+        }
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| HttpError::Protocol(format!("bad status line {:?}", status_line.trim())))?;
+
+        let mut headers = Vec::new();
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                return Err(HttpError::Reset);
+            }
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        Ok(HttpResponse { status, headers, body: Vec::new() })
+    }
+}
+
+fn copy_exact(reader: &mut BufReader<TcpStream>, len: u64, sink: &mut dyn Write) -> Result<(), HttpError> {
+    if io::copy(&mut reader.by_ref().take(len), sink)? < len {
+        return Err(HttpError::Reset);
+    }
+    Ok(())
+}
+
+fn read_chunked(reader: &mut BufReader<TcpStream>, sink: &mut dyn Write) -> Result<(), HttpError> {
+    loop {
+        let mut size_line = String::new();
+        if reader.read_line(&mut size_line)? == 0 {
+            return Err(HttpError::Reset);
+        }
+        let size = size_line
+            .trim()
+            .split(';')
+            .next()
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| HttpError::Protocol(format!("bad chunk size {:?}", size_line.trim())))?;
+        copy_exact(reader, size, sink)?;
+        let mut terminator = [0; 2];
+        reader.read_exact(&mut terminator)?;
+        if size == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadConfig {
+    pub requests: usize,
+    pub concurrency: usize,
+    // Requests cycle through these in order
+    pub paths: Vec<String>,
+    pub timeout: Duration,
+    pub keep_alive: bool,
+}
+
+impl LoadConfig {
+    pub fn new(requests: usize, concurrency: usize, paths: &[&str]) -> Self {
+        Self {
+            requests,
+            concurrency: concurrency.max(1),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            timeout: DEFAULT_TIMEOUT,
+            keep_alive: true,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    pub requests: usize,
+    pub succeeded: usize,
+    pub bytes_received: u64,
+    pub elapsed: Duration,
+    // Successful requests only, sorted
+    pub latencies: Vec<Duration>,
+    pub errors: BTreeMap<HttpError, usize>,
+}
+
+impl LoadReport {
+    pub fn success_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.succeeded as f64 / self.requests as f64
+    }
+
+    // Nearest-rank percentile, `p` in 0..=100
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = ((p / 100.0) * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn transport_errors(&self) -> usize {
+        self.errors.iter().filter(|(e, _)| !matches!(e, HttpError::Status(_))).map(|(_, n)| n).sum()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} ok ({:.1}%), p50 {:?}, p95 {:?}, p99 {:?}, {:.0} req/s",
+            self.succeeded,
+            self.requests,
+            self.success_rate() * 100.0,
+            self.percentile(50.0),
+            self.percentile(95.0),
+            self.percentile(99.0),
+            self.requests_per_second()
+        )?;
+        for (error, count) in &self.errors {
+            write!(f, ", {}: {}", error, count)?;
+        }
+        Ok(())
+    }
+}
+
+// `concurrency` workers, each with its own connection, pull request numbers from a shared counter
+pub fn run_load(addr: SocketAddr, config: &LoadConfig) -> LoadReport {
+    let next = Arc::new(AtomicUsize::new(0));
+    let report = Arc::new(Mutex::new(LoadReport::default()));
+    let start = Instant::now();
+
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| {
+            let (next, report, config) = (Arc::clone(&next), Arc::clone(&report), config.clone());
+            thread::spawn(move || {
+                let mut connection = HttpConnection::new(addr, config.timeout, config.keep_alive);
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= config.requests {
+                        break;
+                    }
+                    let path = config.paths.get(index % config.paths.len().max(1)).map_or("/", String::as_str);
+                    let sent = Instant::now();
+                    let result = connection.request("GET", path, &[]);
+                    let latency = sent.elapsed();
+
+                    let mut report = report.lock().unwrap();
+                    report.requests += 1;
+                    match result {
+                        Ok(response) if (200..300).contains(&response.status) => {
+                            report.succeeded += 1;
+                            report.bytes_received += response.body.len() as u64;
+                            report.latencies.push(latency);
+                        }
+                        Ok(response) => *report.errors.entry(HttpError::Status(response.status)).or_insert(0) += 1,
+                        Err(error) => *report.errors.entry(error).or_insert(0) += 1,
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    let mut report = report.lock().unwrap().clone();
+    report.elapsed = start.elapsed();
+    report.latencies.sort();
+    report
+}
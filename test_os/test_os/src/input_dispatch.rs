@@ -0,0 +1,174 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::touch::MotionEvent;
+
+#[derive(Debug, Clone)]
+pub struct QueuedInput {
+    pub seq: u64,
+    pub injected_at: Instant,
+    pub event: MotionEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyReport {
+    pub count: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyReport {
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self {
+                count: 0,
+                mean: Duration::ZERO,
+                p50: Duration::ZERO,
+                p95: Duration::ZERO,
+                p99: Duration::ZERO,
+                max: Duration::ZERO,
+            };
+        }
+
+        samples.sort();
+        let count = samples.len();
+        Self {
+            count,
+            mean: samples.iter().sum::<Duration>() / count as u32,
+            p50: percentile(&samples, 50.0),
+            p95: percentile(&samples, 95.0),
+            p99: percentile(&samples, 99.0),
+            max: samples[count - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DispatchReport {
+    pub latency: LatencyReport,
+    pub out_of_order: u64,
+}
+
+// Nearest-rank percentile over an already sorted slice
+pub fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Clone)]
+pub struct InputInjector {
+    sender: Sender<QueuedInput>,
+    next_seq: Arc<Mutex<u64>>,
+}
+
+impl InputInjector {
+    pub fn inject(&self, event: MotionEvent) -> bool {
+        // Sequence numbers are assigned under the lock so the queue order matches them
+        let mut next = self.next_seq.lock().unwrap();
+        *next += 1;
+        self.sender
+            .send(QueuedInput {
+                seq: *next,
+                injected_at: Instant::now(),
+                event,
+            })
+            .is_ok()
+    }
+}
+
+// Single consumer "UI thread" that drains the queue in order and times each handler call
+pub struct InputDispatcher {
+    injector: Option<InputInjector>,
+    ui_thread: Option<JoinHandle<()>>,
+    latencies: Arc<Mutex<Vec<Duration>>>,
+    out_of_order: Arc<Mutex<u64>>,
+}
+
+impl InputDispatcher {
+    pub fn start<H>(mut handler: H) -> Self
+    where
+        H: FnMut(&MotionEvent) + Send + 'static,
+    {
+        let (sender, receiver): (Sender<QueuedInput>, Receiver<QueuedInput>) = mpsc::channel();
+        let latencies = Arc::new(Mutex::new(Vec::new()));
+        let out_of_order = Arc::new(Mutex::new(0));
+
+        let ui_thread = {
+            let latencies = Arc::clone(&latencies);
+            let out_of_order = Arc::clone(&out_of_order);
+            thread::Builder::new()
+                .name("ui_thread".to_string())
+                .spawn(move || {
+                    let mut last_seq = 0;
+                    for input in receiver {
+                        let latency = input.injected_at.elapsed();
+                        handler(&input.event);
+                        latencies.lock().unwrap().push(latency);
+
+                        if input.seq <= last_seq {
+                            *out_of_order.lock().unwrap() += 1;
+                        }
+                        last_seq = input.seq;
+                    }
+                })
+                .expect("Failed to spawn UI thread")
+        };
+
+        Self {
+            injector: Some(InputInjector {
+                sender,
+                next_seq: Arc::new(Mutex::new(0)),
+            }),
+            ui_thread: Some(ui_thread),
+            latencies,
+            out_of_order,
+        }
+    }
+
+    pub fn injector(&self) -> InputInjector {
+        self.injector.clone().expect("Dispatcher already finished")
+    }
+
+    // Producer thread that replays `events` with the spacing of their stream timestamps
+    pub fn spawn_producer(&self, events: Vec<MotionEvent>) -> JoinHandle<usize> {
+        let injector = self.injector();
+        thread::spawn(move || {
+            let start = Instant::now();
+            let base = events.first().map_or(Duration::ZERO, |e| e.time);
+            let mut injected = 0;
+
+            for event in events {
+                let due = event.time.saturating_sub(base);
+                let elapsed = start.elapsed();
+                if due > elapsed {
+                    thread::sleep(due - elapsed);
+                }
+                if injector.inject(event) {
+                    injected += 1;
+                }
+            }
+            injected
+        })
+    }
+
+    // Join producers first: closes the queue, drains it and reports injection-to-handler latency
+    pub fn finish(mut self) -> DispatchReport {
+        self.injector.take();
+        if let Some(ui_thread) = self.ui_thread.take() {
+            ui_thread.join().expect("UI thread panicked");
+        }
+        let samples = std::mem::take(&mut *self.latencies.lock().unwrap());
+        DispatchReport {
+            latency: LatencyReport::from_samples(samples),
+            out_of_order: *self.out_of_order.lock().unwrap(),
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::clock::{Clock, SharedClock};
+use crate::touch::{MotionEvent, PointerCoords, TouchAction};
+
+pub const SESSION_FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleCommand {
+    Foreground,
+    Background,
+    Kill,
+    Relaunch,
+}
+
+impl LifecycleCommand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleCommand::Foreground => "foreground",
+            LifecycleCommand::Background => "background",
+            LifecycleCommand::Kill => "kill",
+            LifecycleCommand::Relaunch => "relaunch",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "foreground" => Some(LifecycleCommand::Foreground),
+            "background" => Some(LifecycleCommand::Background),
+            "kill" => Some(LifecycleCommand::Kill),
+            "relaunch" => Some(LifecycleCommand::Relaunch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    Touch(MotionEvent),
+    Key { code: String, down: bool },
+    Rotation { orientation: String, width: u32, height: u32 },
+    Lifecycle(LifecycleCommand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent {
+    pub at: Duration,
+    pub event: SessionEvent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputSession {
+    pub name: String,
+    pub events: Vec<TimedEvent>,
+}
+
+impl InputSession {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |e| e.at)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let events: Vec<Value> = self.events.iter().map(event_to_json).collect();
+        json!({
+            "version": SESSION_FORMAT_VERSION,
+            "name": self.name,
+            "events": events,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let version = value["version"].as_u64().ok_or("missing session version")?;
+        if version != SESSION_FORMAT_VERSION {
+            return Err(format!("unsupported session version {}", version));
+        }
+
+        let name = value["name"].as_str().ok_or("missing session name")?;
+        let raw_events = value["events"].as_array().ok_or("missing events array")?;
+
+        let mut events = Vec::with_capacity(raw_events.len());
+        for (i, raw) in raw_events.iter().enumerate() {
+            let event = event_from_json(raw).map_err(|e| format!("event {}: {}", i, e))?;
+            if events.last().is_some_and(|prev: &TimedEvent| event.at < prev.at) {
+                return Err(format!("event {}: timestamps go backwards", i));
+            }
+            events.push(event);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            events,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.to_json()).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let value: Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+        Self::from_json(&value)
+    }
+}
+
+fn action_name(action: TouchAction) -> &'static str {
+    match action {
+        TouchAction::Down => "down",
+        TouchAction::Move => "move",
+        TouchAction::Up => "up",
+        TouchAction::PointerDown => "pointer_down",
+        TouchAction::PointerUp => "pointer_up",
+        TouchAction::Cancel => "cancel",
+    }
+}
+
+fn parse_action(name: &str) -> Option<TouchAction> {
+    match name {
+        "down" => Some(TouchAction::Down),
+        "move" => Some(TouchAction::Move),
+        "up" => Some(TouchAction::Up),
+        "pointer_down" => Some(TouchAction::PointerDown),
+        "pointer_up" => Some(TouchAction::PointerUp),
+        "cancel" => Some(TouchAction::Cancel),
+        _ => None,
+    }
+}
+
+fn event_to_json(event: &TimedEvent) -> Value {
+    let t_us = event.at.as_micros() as u64;
+    match &event.event {
+        SessionEvent::Touch(motion) => {
+            let pointers: Vec<Value> = motion
+                .pointers
+                .iter()
+                .map(|p| json!({ "id": p.id, "x": p.x, "y": p.y, "pressure": p.pressure }))
+                .collect();
+            json!({
+                "t_us": t_us,
+                "type": "touch",
+                "action": action_name(motion.action),
+                "action_pointer": motion.action_pointer,
+                "pointers": pointers,
+            })
+        }
+        SessionEvent::Key { code, down } => json!({
+            "t_us": t_us,
+            "type": "key",
+            "code": code,
+            "down": down,
+        }),
+        SessionEvent::Rotation { orientation, width, height } => json!({
+            "t_us": t_us,
+            "type": "rotation",
+            "orientation": orientation,
+            "width": width,
+            "height": height,
+        }),
+        SessionEvent::Lifecycle(command) => json!({
+            "t_us": t_us,
+            "type": "lifecycle",
+            "command": command.as_str(),
+        }),
+    }
+}
+
+fn event_from_json(value: &Value) -> Result<TimedEvent, String> {
+    let at = Duration::from_micros(value["t_us"].as_u64().ok_or("missing t_us")?);
+    let str_field = |name: &str| {
+        value[name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or(format!("missing {}", name))
+    };
+    let u32_field = |name: &str| {
+        value[name]
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or(format!("missing {}", name))
+    };
+
+    let event = match value["type"].as_str() {
+        Some("touch") => {
+            let action = parse_action(&str_field("action")?).ok_or("unknown touch action")?;
+            let raw_pointers = value["pointers"].as_array().ok_or("missing pointers")?;
+            let mut pointers = Vec::with_capacity(raw_pointers.len());
+            for p in raw_pointers {
+                pointers.push(PointerCoords {
+                    id: p["id"].as_u64().ok_or("pointer without id")? as u32,
+                    x: p["x"].as_f64().ok_or("pointer without x")? as f32,
+                    y: p["y"].as_f64().ok_or("pointer without y")? as f32,
+                    pressure: p["pressure"].as_f64().unwrap_or(1.0) as f32,
+                });
+            }
+            SessionEvent::Touch(MotionEvent {
+                action,
+                action_pointer: u32_field("action_pointer")?,
+                pointers,
+                time: at,
+            })
+        }
+        Some("key") => SessionEvent::Key {
+            code: str_field("code")?,
+            down: value["down"].as_bool().ok_or("missing down")?,
+        },
+        Some("rotation") => SessionEvent::Rotation {
+            orientation: str_field("orientation")?,
+            width: u32_field("width")?,
+            height: u32_field("height")?,
+        },
+        Some("lifecycle") => SessionEvent::Lifecycle(
+            LifecycleCommand::parse(&str_field("command")?).ok_or("unknown lifecycle command")?,
+        ),
+        Some(other) => return Err(format!("unknown event type '{}'", other)),
+        None => return Err("missing event type".to_string()),
+    };
+
+    Ok(TimedEvent { at, event })
+}
+
+// Sessions store microseconds, like kernel input timestamps
+fn to_micros(duration: Duration) -> Duration {
+    Duration::from_micros(duration.as_micros() as u64)
+}
+
+// Stamps events with the clock; touch streams keep their own relative timing
+pub struct SessionRecorder {
+    session: InputSession,
+    clock: SharedClock,
+    origin: Duration,
+}
+
+impl SessionRecorder {
+    pub fn new(name: &str, clock: SharedClock) -> Self {
+        let origin = clock.now();
+        Self {
+            session: InputSession::new(name),
+            clock,
+            origin,
+        }
+    }
+
+    fn now(&self) -> Duration {
+        self.clock.elapsed_since(self.origin)
+    }
+
+    fn push(&mut self, at: Duration, event: SessionEvent) {
+        let at = to_micros(at);
+        let event = match event {
+            SessionEvent::Touch(mut motion) => {
+                motion.time = at;
+                SessionEvent::Touch(motion)
+            }
+            other => other,
+        };
+        self.session.events.push(TimedEvent { at, event });
+    }
+
+    pub fn record_touch_stream(&mut self, events: &[MotionEvent]) {
+        let Some(first) = events.first() else {
+            return;
+        };
+        let base = self.now();
+        let first_time = first.time;
+
+        for motion in events {
+            let at = base + motion.time.saturating_sub(first_time);
+            self.push(at, SessionEvent::Touch(motion.clone()));
+        }
+
+        // Performing the gesture takes as long as the stream lasts
+        let span = events[events.len() - 1].time.saturating_sub(first_time);
+        self.clock.sleep(span);
+    }
+
+    pub fn record_key(&mut self, code: &str, down: bool) {
+        let at = self.now();
+        self.push(at, SessionEvent::Key { code: code.to_string(), down });
+    }
+
+    pub fn record_rotation(&mut self, orientation: &str, width: u32, height: u32) {
+        let at = self.now();
+        self.push(
+            at,
+            SessionEvent::Rotation { orientation: orientation.to_string(), width, height },
+        );
+    }
+
+    pub fn record_lifecycle(&mut self, command: LifecycleCommand) {
+        let at = self.now();
+        self.push(at, SessionEvent::Lifecycle(command));
+    }
+
+    pub fn finish(self) -> InputSession {
+        self.session
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStats {
+    pub events: usize,
+    pub elapsed: Duration,
+    pub max_lag: Duration,
+}
+
+// Replays a session on `clock`; speed 1.0 keeps the original timing, 2.0 plays twice as fast
+pub fn replay<F>(session: &InputSession, clock: &dyn Clock, speed: f64, mut sink: F) -> PlaybackStats
+where
+    F: FnMut(&TimedEvent),
+{
+    assert!(speed > 0.0, "Playback speed must be positive");
+
+    let start = clock.now();
+    let mut max_lag = Duration::ZERO;
+
+    for event in &session.events {
+        let due = Duration::from_secs_f64(event.at.as_secs_f64() / speed);
+        let elapsed = clock.elapsed_since(start);
+        if due > elapsed {
+            clock.sleep(due - elapsed);
+        }
+
+        max_lag = max_lag.max(clock.elapsed_since(start).saturating_sub(due));
+        sink(event);
+    }
+
+    PlaybackStats {
+        events: session.events.len(),
+        elapsed: clock.elapsed_since(start),
+        max_lag,
+    }
+}
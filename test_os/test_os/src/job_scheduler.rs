@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use crate::backoff::BackoffPolicy;
+use crate::clock::{RealClock, SharedClock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkType {
+    None,
+    Metered,
+    Unmetered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkRequirement {
+    NotRequired,
+    Connected,
+    Unmetered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceState {
+    pub charging: bool,
+    pub network: NetworkType,
+    pub idle: bool,
+    pub storage_low: bool,
+}
+
+impl Default for DeviceState {
+    fn default() -> Self {
+        Self {
+            charging: false,
+            network: NetworkType::Metered,
+            idle: false,
+            storage_low: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobConstraints {
+    pub requires_charging: bool,
+    pub network: NetworkRequirement,
+    pub requires_idle: bool,
+    pub requires_storage_not_low: bool,
+}
+
+impl Default for JobConstraints {
+    fn default() -> Self {
+        Self {
+            requires_charging: false,
+            network: NetworkRequirement::NotRequired,
+            requires_idle: false,
+            requires_storage_not_low: false,
+        }
+    }
+}
+
+impl JobConstraints {
+    pub fn satisfied_by(&self, device: &DeviceState) -> bool {
+        let network_ok = match self.network {
+            NetworkRequirement::NotRequired => true,
+            NetworkRequirement::Connected => device.network != NetworkType::None,
+            NetworkRequirement::Unmetered => device.network == NetworkType::Unmetered,
+        };
+
+        network_ok
+            && (!self.requires_charging || device.charging)
+            && (!self.requires_idle || device.idle)
+            && (!self.requires_storage_not_low || !device.storage_low)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JobSpec {
+    pub name: String,
+    pub constraints: JobConstraints,
+    pub initial_delay: Duration,
+    // Once passed, the job runs even if its constraints are not met
+    pub deadline: Option<Duration>,
+    pub max_retries: u32,
+    pub backoff: BackoffPolicy,
+}
+
+impl JobSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            constraints: JobConstraints::default(),
+            initial_delay: Duration::ZERO,
+            deadline: None,
+            max_retries: 3,
+            backoff: BackoffPolicy::Exponential(Duration::from_secs(30)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobResult {
+    Success,
+    Retry,
+    Failure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+pub type JobId = u64;
+
+type JobWork = Box<dyn FnMut(u32) -> JobResult + Send>;
+
+struct ScheduledJob {
+    id: JobId,
+    spec: JobSpec,
+    work: JobWork,
+    status: JobStatus,
+    attempts: u32,
+    scheduled_at: Duration,
+    earliest_run: Duration,
+    ran_past_deadline: bool,
+}
+
+impl ScheduledJob {
+    fn is_due(&self, now: Duration, device: &DeviceState) -> bool {
+        if self.status != JobStatus::Pending || now < self.earliest_run {
+            return false;
+        }
+        self.spec.constraints.satisfied_by(device) || self.deadline_passed(now)
+    }
+
+    // When the job may next run under `device`: after its delay if the constraints hold, else at
+    // its deadline. None while it waits on constraints with no deadline to force it
+    fn next_run(&self, device: &DeviceState) -> Option<Duration> {
+        if self.spec.constraints.satisfied_by(device) {
+            return Some(self.earliest_run);
+        }
+        self.spec.deadline.map(|deadline| self.earliest_run.max(self.scheduled_at + deadline))
+    }
+
+    fn deadline_passed(&self, now: Duration) -> bool {
+        match self.spec.deadline {
+            Some(deadline) => now.saturating_sub(self.scheduled_at) >= deadline,
+            None => false,
+        }
+    }
+}
+
+pub struct JobScheduler {
+    jobs: Vec<ScheduledJob>,
+    device: DeviceState,
+    clock: SharedClock,
+    next_id: JobId,
+    run_log: Vec<(JobId, u32, JobResult)>,
+}
+
+impl Default for JobScheduler {
+    fn default() -> Self {
+        Self::new(DeviceState::default())
+    }
+}
+
+impl JobScheduler {
+    pub fn new(device: DeviceState) -> Self {
+        Self::with_clock(device, RealClock::shared())
+    }
+
+    pub fn with_clock(device: DeviceState, clock: SharedClock) -> Self {
+        Self {
+            jobs: Vec::new(),
+            device,
+            clock,
+            next_id: 1,
+            run_log: Vec::new(),
+        }
+    }
+
+    pub fn device_state(&self) -> DeviceState {
+        self.device
+    }
+
+    pub fn set_device_state(&mut self, device: DeviceState) {
+        self.device = device;
+    }
+
+    pub fn schedule<F>(&mut self, spec: JobSpec, work: F) -> JobId
+    where
+        F: FnMut(u32) -> JobResult + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let now = self.clock.now();
+        self.jobs.push(ScheduledJob {
+            id,
+            earliest_run: now + spec.initial_delay,
+            spec,
+            work: Box::new(work),
+            status: JobStatus::Pending,
+            attempts: 0,
+            scheduled_at: now,
+            ran_past_deadline: false,
+        });
+        id
+    }
+
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        match self.jobs.iter_mut().find(|job| job.id == id) {
+            Some(job) if job.status == JobStatus::Pending => {
+                job.status = JobStatus::Cancelled;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn cancel_all(&mut self) -> usize {
+        let ids: Vec<JobId> = self.jobs.iter().map(|job| job.id).collect();
+        ids.into_iter().filter(|&id| self.cancel(id)).count()
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.jobs.iter().find(|job| job.id == id).map(|job| job.status)
+    }
+
+    pub fn name(&self, id: JobId) -> Option<&str> {
+        self.jobs.iter().find(|job| job.id == id).map(|job| job.spec.name.as_str())
+    }
+
+    pub fn attempts(&self, id: JobId) -> u32 {
+        self.jobs.iter().find(|job| job.id == id).map_or(0, |job| job.attempts)
+    }
+
+    pub fn ran_past_deadline(&self, id: JobId) -> bool {
+        self.jobs.iter().any(|job| job.id == id && job.ran_past_deadline)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.jobs.iter().filter(|job| job.status == JobStatus::Pending).count()
+    }
+
+    pub fn run_log(&self) -> &[(JobId, u32, JobResult)] {
+        &self.run_log
+    }
+
+    // Time until the earliest pending job can run in the current device state, deadlines included.
+    // None if nothing can run before the device state changes
+    pub fn next_wakeup(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Pending)
+            .filter_map(|job| job.next_run(&self.device))
+            .map(|at| at.saturating_sub(now))
+            .min()
+    }
+
+    // Runs every job whose delay has elapsed and whose constraints (or deadline) allow it
+    pub fn run_pending(&mut self) -> usize {
+        let now = self.clock.now();
+        let device = self.device;
+        let mut executed = 0;
+
+        for job in self.jobs.iter_mut() {
+            if !job.is_due(now, &device) {
+                continue;
+            }
+
+            job.ran_past_deadline = !job.spec.constraints.satisfied_by(&device);
+            job.attempts += 1;
+            let result = (job.work)(job.attempts);
+            self.run_log.push((job.id, job.attempts, result));
+            executed += 1;
+
+            match result {
+                JobResult::Success => job.status = JobStatus::Succeeded,
+                JobResult::Failure => job.status = JobStatus::Failed,
+                JobResult::Retry if job.attempts > job.spec.max_retries => {
+                    job.status = JobStatus::Failed
+                }
+                JobResult::Retry => {
+                    job.earliest_run = now + job.spec.backoff.delay(job.attempts);
+                }
+            }
+        }
+
+        executed
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+
+use crate::saved_state::{BundleError, SavedStateBundle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecyclePlatform {
+    Android,
+    Ios,
+}
+
+impl LifecyclePlatform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "android") {
+            LifecyclePlatform::Android
+        } else {
+            LifecyclePlatform::Ios
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LifecycleState {
+    // Android Activity
+    Initialized,
+    Created,
+    Started,
+    Resumed,
+    Paused,
+    Stopped,
+    Destroyed,
+    // iOS UIApplication
+    NotRunning,
+    Inactive,
+    Active,
+    Background,
+    Suspended,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleEvent {
+    Transition { from: LifecycleState, to: LifecycleState },
+    ProcessKilled { last_state: LifecycleState },
+    Relaunched { restored: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleError {
+    IllegalTransition { from: LifecycleState, to: LifecycleState },
+    ProcessDead,
+    ProcessAlive,
+    KillInForeground(LifecycleState),
+    SavedState(BundleError),
+}
+
+impl fmt::Display for LifecycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LifecycleError::IllegalTransition { from, to } => {
+                write!(f, "illegal lifecycle transition {:?} -> {:?}", from, to)
+            }
+            LifecycleError::ProcessDead => write!(f, "app process is dead"),
+            LifecycleError::ProcessAlive => write!(f, "app process is still alive"),
+            LifecycleError::KillInForeground(state) => {
+                write!(f, "system does not kill a foreground app ({:?})", state)
+            }
+            LifecycleError::SavedState(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+type LifecycleCallback = Box<dyn FnMut(&LifecycleEvent) + Send>;
+
+pub struct AppLifecycle {
+    platform: LifecyclePlatform,
+    state: LifecycleState,
+    process_alive: bool,
+    killed_in: Option<LifecycleState>,
+    restored: bool,
+    saved_state: Option<(Vec<u8>, usize)>,
+    launches: u32,
+    history: Vec<LifecycleEvent>,
+    callbacks: Vec<LifecycleCallback>,
+}
+
+impl AppLifecycle {
+    pub fn new(platform: LifecyclePlatform) -> Self {
+        Self {
+            platform,
+            state: initial_state(platform),
+            process_alive: true,
+            killed_in: None,
+            restored: false,
+            saved_state: None,
+            launches: 0,
+            history: Vec::new(),
+            callbacks: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> LifecycleState {
+        self.state
+    }
+
+    pub fn is_process_alive(&self) -> bool {
+        self.process_alive
+    }
+
+    pub fn was_restored(&self) -> bool {
+        self.restored
+    }
+
+    pub fn launches(&self) -> u32 {
+        self.launches
+    }
+
+    pub fn history(&self) -> &[LifecycleEvent] {
+        &self.history
+    }
+
+    pub fn on_event<F>(&mut self, callback: F)
+    where
+        F: FnMut(&LifecycleEvent) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    pub fn is_foreground(&self) -> bool {
+        matches!(
+            self.state,
+            LifecycleState::Started
+                | LifecycleState::Resumed
+                | LifecycleState::Paused
+                | LifecycleState::Inactive
+                | LifecycleState::Active
+        )
+    }
+
+    pub fn transition(&mut self, to: LifecycleState) -> Result<(), LifecycleError> {
+        if !self.process_alive {
+            return Err(LifecycleError::ProcessDead);
+        }
+
+        let from = self.state;
+        if !is_legal_transition(self.platform, from, to) {
+            return Err(LifecycleError::IllegalTransition { from, to });
+        }
+
+        if from == initial_state(self.platform) {
+            self.launches += 1;
+        }
+
+        self.state = to;
+        self.emit(LifecycleEvent::Transition { from, to });
+        Ok(())
+    }
+
+    pub fn move_to_foreground(&mut self) -> Result<(), LifecycleError> {
+        let path: &[LifecycleState] = match (self.platform, self.state) {
+            (LifecyclePlatform::Android, LifecycleState::Initialized) => &[
+                LifecycleState::Created,
+                LifecycleState::Started,
+                LifecycleState::Resumed,
+            ],
+            (LifecyclePlatform::Android, LifecycleState::Created)
+            | (LifecyclePlatform::Android, LifecycleState::Stopped) => {
+                &[LifecycleState::Started, LifecycleState::Resumed]
+            }
+            (LifecyclePlatform::Android, LifecycleState::Started)
+            | (LifecyclePlatform::Android, LifecycleState::Paused) => &[LifecycleState::Resumed],
+            (LifecyclePlatform::Ios, LifecycleState::NotRunning)
+            | (LifecyclePlatform::Ios, LifecycleState::Background) => {
+                &[LifecycleState::Inactive, LifecycleState::Active]
+            }
+            (LifecyclePlatform::Ios, LifecycleState::Suspended) => &[
+                LifecycleState::Background,
+                LifecycleState::Inactive,
+                LifecycleState::Active,
+            ],
+            (LifecyclePlatform::Ios, LifecycleState::Inactive) => &[LifecycleState::Active],
+            (_, state) if state == foreground_state(self.platform) => &[],
+            (_, state) => {
+                return Err(LifecycleError::IllegalTransition {
+                    from: state,
+                    to: foreground_state(self.platform),
+                })
+            }
+        };
+
+        self.walk(path)
+    }
+
+    pub fn move_to_background(&mut self) -> Result<(), LifecycleError> {
+        let path: &[LifecycleState] = match (self.platform, self.state) {
+            (LifecyclePlatform::Android, LifecycleState::Resumed) => {
+                &[LifecycleState::Paused, LifecycleState::Stopped]
+            }
+            (LifecyclePlatform::Android, LifecycleState::Started)
+            | (LifecyclePlatform::Android, LifecycleState::Paused) => &[LifecycleState::Stopped],
+            (LifecyclePlatform::Ios, LifecycleState::Active) => {
+                &[LifecycleState::Inactive, LifecycleState::Background]
+            }
+            (LifecyclePlatform::Ios, LifecycleState::Inactive) => &[LifecycleState::Background],
+            (_, state) if state == background_state(self.platform) => &[],
+            (_, state) => {
+                return Err(LifecycleError::IllegalTransition {
+                    from: state,
+                    to: background_state(self.platform),
+                })
+            }
+        };
+
+        self.walk(path)
+    }
+
+    // onSaveInstanceState / state preservation: the parcel is held outside the app process
+    pub fn save_instance_state(&mut self, bundle: &SavedStateBundle) -> Result<usize, LifecycleError> {
+        if !self.process_alive {
+            return Err(LifecycleError::ProcessDead);
+        }
+
+        let parcel = bundle.to_parcel().map_err(LifecycleError::SavedState)?;
+        let size = parcel.len();
+        self.saved_state = Some((parcel, bundle.limit()));
+        Ok(size)
+    }
+
+    pub fn restored_state(&self) -> Result<Option<SavedStateBundle>, LifecycleError> {
+        match &self.saved_state {
+            Some((parcel, limit)) if self.restored => SavedStateBundle::from_parcel(parcel, *limit)
+                .map(Some)
+                .map_err(LifecycleError::SavedState),
+            _ => Ok(None),
+        }
+    }
+
+    // iOS only: the system freezes a backgrounded app once its background time is up
+    pub fn suspend(&mut self) -> Result<(), LifecycleError> {
+        self.transition(LifecycleState::Suspended)
+    }
+
+    // User-initiated exit: finish() on Android, termination on iOS
+    pub fn finish(&mut self) -> Result<(), LifecycleError> {
+        if self.is_foreground() {
+            self.move_to_background()?;
+        }
+        self.saved_state = None;
+        match self.platform {
+            LifecyclePlatform::Android => self.transition(LifecycleState::Destroyed),
+            LifecyclePlatform::Ios => self.transition(LifecycleState::NotRunning),
+        }
+    }
+
+    // Low-memory kill: no onDestroy / applicationWillTerminate is delivered
+    pub fn kill_process(&mut self) -> Result<(), LifecycleError> {
+        if !self.process_alive {
+            return Err(LifecycleError::ProcessDead);
+        }
+        if self.is_foreground() {
+            return Err(LifecycleError::KillInForeground(self.state));
+        }
+
+        let last_state = self.state;
+        self.process_alive = false;
+        self.killed_in = Some(last_state);
+        self.emit(LifecycleEvent::ProcessKilled { last_state });
+        Ok(())
+    }
+
+    // Starts a new process; state is restored only if the previous one was killed by the system
+    pub fn relaunch(&mut self) -> Result<bool, LifecycleError> {
+        let finished = matches!(
+            self.state,
+            LifecycleState::Destroyed | LifecycleState::NotRunning
+        );
+        if self.process_alive && !finished {
+            return Err(LifecycleError::ProcessAlive);
+        }
+
+        self.restored = self.killed_in.take().is_some();
+        if !self.restored {
+            self.saved_state = None;
+        }
+        self.process_alive = true;
+        self.state = initial_state(self.platform);
+        self.emit(LifecycleEvent::Relaunched { restored: self.restored });
+
+        self.move_to_foreground()?;
+        Ok(self.restored)
+    }
+
+    fn walk(&mut self, path: &[LifecycleState]) -> Result<(), LifecycleError> {
+        for &next in path {
+            self.transition(next)?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, event: LifecycleEvent) {
+        for callback in self.callbacks.iter_mut() {
+            callback(&event);
+        }
+        self.history.push(event);
+    }
+}
+
+fn initial_state(platform: LifecyclePlatform) -> LifecycleState {
+    match platform {
+        LifecyclePlatform::Android => LifecycleState::Initialized,
+        LifecyclePlatform::Ios => LifecycleState::NotRunning,
+    }
+}
+
+fn foreground_state(platform: LifecyclePlatform) -> LifecycleState {
+    match platform {
+        LifecyclePlatform::Android => LifecycleState::Resumed,
+        LifecyclePlatform::Ios => LifecycleState::Active,
+    }
+}
+
+fn background_state(platform: LifecyclePlatform) -> LifecycleState {
+    match platform {
+        LifecyclePlatform::Android => LifecycleState::Stopped,
+        LifecyclePlatform::Ios => LifecycleState::Background,
+    }
+}
+
+pub fn is_legal_transition(
+    platform: LifecyclePlatform,
+    from: LifecycleState,
+    to: LifecycleState,
+) -> bool {
+    use LifecycleState::*;
+
+    match platform {
+        LifecyclePlatform::Android => matches!(
+            (from, to),
+            (Initialized, Created)
+                | (Created, Started)
+                | (Created, Destroyed)
+                | (Started, Resumed)
+                | (Started, Stopped)
+                | (Resumed, Paused)
+                | (Paused, Resumed)
+                | (Paused, Stopped)
+                | (Stopped, Started)
+                | (Stopped, Destroyed)
+        ),
+        LifecyclePlatform::Ios => matches!(
+            (from, to),
+            (NotRunning, Inactive)
+                | (NotRunning, Background)
+                | (Inactive, Active)
+                | (Inactive, Background)
+                | (Active, Inactive)
+                | (Background, Inactive)
+                | (Background, Suspended)
+                | (Background, NotRunning)
+                | (Suspended, Background)
+                | (Suspended, NotRunning)
+        ),
+    }
+}
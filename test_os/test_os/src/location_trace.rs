@@ -0,0 +1,317 @@
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::clock::Clock;
+use crate::nmea::{self, FixDecoder, NmeaError};
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TracePoint {
+    // Relative to the first point of the trace
+    pub time: Duration,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationTrace {
+    pub name: String,
+    pub points: Vec<TracePoint>,
+}
+
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (p1, p2) = (lat1.to_radians(), lat2.to_radians());
+    let dp = p2 - p1;
+    let dl = (lon2 - lon1).to_radians();
+    let a = (dp / 2.0).sin().powi(2) + p1.cos() * p2.cos() * (dl / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+pub fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (p1, p2) = (lat1.to_radians(), lat2.to_radians());
+    let dl = (lon2 - lon1).to_radians();
+    let y = dl.sin() * p2.cos();
+    let x = p1.cos() * p2.sin() - p1.sin() * p2.cos() * dl.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+// Moves a position by metres north/east; fine for the few metres of GNSS error
+pub fn offset_m(lat: f64, lon: f64, north: f64, east: f64) -> (f64, f64) {
+    let dlat = north / EARTH_RADIUS_M;
+    let dlon = east / (EARTH_RADIUS_M * lat.to_radians().cos());
+    (lat + dlat.to_degrees(), lon + dlon.to_degrees())
+}
+
+impl LocationTrace {
+    // Absolute timestamps are made relative to the first point; points must not go back in time
+    pub fn from_points(name: &str, absolute: Vec<(NaiveDateTime, f64, f64, Option<f64>)>) -> Result<Self, String> {
+        let first = absolute.first().ok_or("trace has no points")?.0;
+        let mut points: Vec<TracePoint> = Vec::with_capacity(absolute.len());
+
+        for (i, (at, latitude, longitude, altitude)) in absolute.into_iter().enumerate() {
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                return Err(format!("point {}: invalid coordinates {}, {}", i, latitude, longitude));
+            }
+            let time = (at - first).to_std().map_err(|_| format!("point {}: time goes backwards", i))?;
+            if points.last().is_some_and(|p| time < p.time) {
+                return Err(format!("point {}: time goes backwards", i));
+            }
+            points.push(TracePoint { time, latitude, longitude, altitude });
+        }
+
+        Ok(Self { name: name.to_string(), points })
+    }
+
+    // Track points of a GPX 1.1 file: <trkpt lat=".." lon=".."><ele>..</ele><time>..</time></trkpt>
+    pub fn from_gpx(name: &str, xml: &str) -> Result<Self, String> {
+        let mut absolute = Vec::new();
+        let mut rest = xml;
+
+        while let Some(start) = rest.find("<trkpt") {
+            let body = &rest[start..];
+            let end = body.find("</trkpt>").ok_or("unterminated <trkpt>")?;
+            let point = &body[..end];
+            let open_tag = &point[..point.find('>').ok_or("malformed <trkpt>")?];
+
+            let coord = |attr: &str| -> Result<f64, String> {
+                xml_attr(open_tag, attr)
+                    .ok_or(format!("trkpt without {}", attr))?
+                    .parse()
+                    .map_err(|_| format!("bad {} in trkpt", attr))
+            };
+            let time = xml_element(point, "time").ok_or("trkpt without <time>")?;
+            let at = DateTime::parse_from_rfc3339(time)
+                .map_err(|e| format!("bad time '{}': {}", time, e))?
+                .naive_utc();
+            let altitude = xml_element(point, "ele").and_then(|e| e.parse().ok());
+
+            absolute.push((at, coord("lat")?, coord("lon")?, altitude));
+            rest = &body[end..];
+        }
+
+        Self::from_points(name, absolute)
+    }
+
+    // Epochs of a receiver log; RMC gives the date and closes each epoch, GGA adds altitude
+    pub fn from_nmea(name: &str, log: &str) -> Result<Self, String> {
+        let mut decoder = FixDecoder::new();
+        let mut absolute = Vec::new();
+
+        for (i, line) in log.lines().enumerate() {
+            let line = line.trim();
+            if !line.starts_with('$') {
+                continue;
+            }
+            let sentence = match nmea::parse(line) {
+                Ok(sentence) => sentence,
+                Err(NmeaError::Unsupported(_)) => continue,
+                Err(e) => return Err(format!("line {}: {}", i + 1, e)),
+            };
+            if let Some(fix) = decoder.feed(&sentence) {
+                absolute.push((fix.time, fix.latitude, fix.longitude, fix.altitude));
+            }
+        }
+
+        Self::from_points(name, absolute)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.points.last().map_or(Duration::ZERO, |p| p.time)
+    }
+
+    pub fn length_m(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|w| distance_m(w[0].latitude, w[0].longitude, w[1].latitude, w[1].longitude))
+            .sum()
+    }
+
+    // Linear interpolation between the surrounding points; None past the end of the trace
+    pub fn position_at(&self, t: Duration) -> Option<TracePoint> {
+        let next = self.points.iter().position(|p| p.time >= t)?;
+        if next == 0 {
+            return Some(TracePoint { time: t, ..self.points[0] });
+        }
+        let (a, b) = (&self.points[next - 1], &self.points[next]);
+        let span = (b.time - a.time).as_secs_f64();
+        let f = if span > 0.0 { (t - a.time).as_secs_f64() / span } else { 1.0 };
+        let lerp = |x: f64, y: f64| x + (y - x) * f;
+
+        Some(TracePoint {
+            time: t,
+            latitude: lerp(a.latitude, b.latitude),
+            longitude: lerp(a.longitude, b.longitude),
+            altitude: match (a.altitude, b.altitude) {
+                (Some(x), Some(y)) => Some(lerp(x, y)),
+                (x, y) => x.or(y),
+            },
+        })
+    }
+
+    // Ground speed (m/s) and bearing of the segment containing `t`
+    pub fn velocity_at(&self, t: Duration) -> (f64, f64) {
+        if self.points.len() < 2 {
+            return (0.0, 0.0);
+        }
+        let next = self.points.iter().position(|p| p.time > t).unwrap_or(self.points.len() - 1).max(1);
+        let (a, b) = (&self.points[next - 1], &self.points[next]);
+        let span = (b.time - a.time).as_secs_f64();
+        let distance = distance_m(a.latitude, a.longitude, b.latitude, b.longitude);
+        // Duplicate timestamps happen in real logs; report standing still rather than inf/NaN
+        let speed = if span > 0.0 { distance / span } else { 0.0 };
+        (speed, bearing_deg(a.latitude, a.longitude, b.latitude, b.longitude))
+    }
+}
+
+fn xml_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let len = xml[start..].find(&format!("</{}>", name))?;
+    Some(xml[start..start + len].trim())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackConfig {
+    // 1.0 replays in real time, 10.0 ten times faster
+    pub speed: f64,
+    // Receiver output interval on the playback clock
+    pub fix_interval: Duration,
+    pub time_to_first_fix: Duration,
+    // Horizontal accuracy (68% radius) of a settled fix; fresh fixes start several times worse
+    pub base_accuracy: f32,
+    pub accuracy_jitter: f32,
+    // (start, length) windows on the playback clock without a fix, including reacquisition
+    pub outages: Vec<(Duration, Duration)>,
+    pub seed: u64,
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            fix_interval: Duration::from_secs(1),
+            time_to_first_fix: Duration::from_secs(5),
+            base_accuracy: 5.0,
+            accuracy_jitter: 0.3,
+            outages: Vec::new(),
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocationFix {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub accuracy: f32,
+    pub speed: f32,
+    pub bearing: f32,
+    // Playback clock time of delivery and the matching position in the trace
+    pub timestamp: Duration,
+    pub trace_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LocationEvent {
+    Fix(LocationFix),
+    FixLost { at: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStats {
+    pub fixes: usize,
+    pub lost: usize,
+    pub time_to_first_fix: Option<Duration>,
+}
+
+// Plays `trace` through a simulated receiver on `clock`; the sink returns false to stop early
+pub fn play<F>(trace: &LocationTrace, config: &PlaybackConfig, clock: &dyn Clock, mut sink: F) -> PlaybackStats
+where
+    F: FnMut(&LocationEvent) -> bool,
+{
+    assert!(config.speed > 0.0, "Playback speed must be positive");
+    assert!(!config.fix_interval.is_zero(), "Fix interval must be positive");
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let start = clock.now();
+    let mut stats = PlaybackStats { fixes: 0, lost: 0, time_to_first_fix: None };
+    let mut acquired_at = Some(config.time_to_first_fix);
+    let mut has_fix = false;
+    let mut due = config.time_to_first_fix;
+
+    loop {
+        let trace_time = due.mul_f64(config.speed);
+        let Some(truth) = trace.position_at(trace_time) else {
+            break;
+        };
+
+        let elapsed = clock.elapsed_since(start);
+        if due > elapsed {
+            clock.sleep(due - elapsed);
+        }
+
+        let in_outage = config.outages.iter().any(|(from, len)| due >= *from && due < *from + *len);
+        if in_outage {
+            if has_fix {
+                has_fix = false;
+                stats.lost += 1;
+                acquired_at = None;
+                if !sink(&LocationEvent::FixLost { at: clock.now() }) {
+                    break;
+                }
+            }
+            due += config.fix_interval;
+            continue;
+        }
+
+        let settled_for = due.saturating_sub(*acquired_at.get_or_insert(due)).as_secs_f32();
+        let settling = 1.0 + 2.0 * (-settled_for / 8.0).exp();
+        let jitter = 1.0 + config.accuracy_jitter * rng.gen_range(-1.0f32..1.0);
+        let accuracy = config.base_accuracy * settling * jitter;
+
+        // Reported accuracy is a 68% radius, so per-axis error is about accuracy / 1.5
+        let sigma = accuracy as f64 / 1.5;
+        let north = gaussian(&mut rng) * sigma;
+        let east = gaussian(&mut rng) * sigma;
+        let (latitude, longitude) = offset_m(truth.latitude, truth.longitude, north, east);
+        let (speed, bearing) = trace.velocity_at(trace_time);
+
+        has_fix = true;
+        stats.fixes += 1;
+        stats.time_to_first_fix.get_or_insert(due);
+
+        let fix = LocationFix {
+            latitude,
+            longitude,
+            altitude: truth.altitude,
+            accuracy,
+            speed: speed as f32,
+            bearing: bearing as f32,
+            timestamp: clock.now(),
+            trace_time,
+        };
+        if !sink(&LocationEvent::Fix(fix)) {
+            break;
+        }
+        due += config.fix_interval;
+    }
+
+    stats
+}
+
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
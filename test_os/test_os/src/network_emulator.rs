@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Linux never retransmits sooner than this
+const MIN_RTO: Duration = Duration::from_millis(200);
+const TCP_SEGMENT: usize = 16 * 1024;
+const MAX_DATAGRAM: usize = 65_507;
+// A router queue deeper than this tail-drops datagrams instead of buffering them
+const MAX_QUEUE_DELAY: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkProfile {
+    pub name: String,
+    // Round trip; each direction gets half
+    pub latency: Duration,
+    // Maximum deviation of the round trip from `latency`
+    pub jitter: Duration,
+    // Bytes per second in each direction, 0 for unlimited
+    pub bandwidth: u64,
+    // Per packet: UDP datagrams are dropped, TCP segments pay a retransmission timeout
+    pub loss: f64,
+    // Per TCP segment: the connection is torn down in both directions
+    pub reset: f64,
+}
+
+impl NetworkProfile {
+    pub fn new(name: &str, latency: Duration, bandwidth: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            latency,
+            jitter: Duration::ZERO,
+            bandwidth,
+            loss: 0.0,
+            reset: 0.0,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_resets(mut self, reset: f64) -> Self {
+        self.reset = reset.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn unthrottled() -> Self {
+        Self::new("Unthrottled", Duration::ZERO, 0)
+    }
+
+    pub fn wifi() -> Self {
+        Self::new("WiFi", Duration::from_millis(10), 100 * 1024 * 1024).with_jitter(Duration::from_millis(2))
+    }
+
+    pub fn lte() -> Self {
+        Self::new("4G", Duration::from_millis(50), 50 * 1024 * 1024)
+            .with_jitter(Duration::from_millis(10))
+            .with_loss(0.001)
+    }
+
+    pub fn umts() -> Self {
+        Self::new("3G", Duration::from_millis(150), 5 * 1024 * 1024)
+            .with_jitter(Duration::from_millis(40))
+            .with_loss(0.01)
+    }
+
+    pub fn edge() -> Self {
+        Self::new("Edge", Duration::from_millis(300), 256 * 1024)
+            .with_jitter(Duration::from_millis(80))
+            .with_loss(0.02)
+    }
+
+    pub fn lossy() -> Self {
+        Self::new("Lossy", Duration::from_millis(100), 1024 * 1024)
+            .with_jitter(Duration::from_millis(50))
+            .with_loss(0.1)
+            .with_resets(0.01)
+    }
+
+    // Time the link is busy putting `len` bytes on the wire
+    pub fn transmit_time(&self, len: usize) -> Duration {
+        if self.bandwidth == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(len as f64 / self.bandwidth as f64)
+        }
+    }
+
+    // SRTT + 4 * RTTVAR as in RFC 6298, with the Linux floor
+    pub fn retransmission_timeout(&self) -> Duration {
+        (self.latency + self.jitter * 4).max(MIN_RTO)
+    }
+
+    fn one_way_delay(&self, rng: &mut StdRng) -> Duration {
+        let half = self.latency / 2;
+        if self.jitter.is_zero() {
+            return half;
+        }
+        let spread = self.jitter.as_secs_f64() / 2.0;
+        Duration::from_secs_f64((half.as_secs_f64() + rng.gen_range(-spread..=spread)).max(0.0))
+    }
+}
+
+// The conditions test_network_stress cycles through
+pub fn profiles() -> Vec<NetworkProfile> {
+    vec![
+        NetworkProfile::wifi(),
+        NetworkProfile::lte(),
+        NetworkProfile::umts(),
+        NetworkProfile::edge(),
+        NetworkProfile::lossy(),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmulatorStats {
+    pub connections: usize,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub retransmits: usize,
+    pub resets: usize,
+    pub datagrams: usize,
+    pub datagrams_dropped: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    // Client to upstream
+    Up,
+    Down,
+}
+
+struct Transit {
+    // When the last byte has left, which is how long the sender is held back
+    departs: Instant,
+    arrives: Instant,
+    lost: bool,
+}
+
+struct Connection {
+    client: TcpStream,
+    server: TcpStream,
+    // Listening address the client came in through
+    proxy: SocketAddr,
+    // Still open at both ends, but nothing gets through any more
+    stalled: bool,
+}
+
+impl Connection {
+    fn reset(&self) {
+        let _ = self.client.shutdown(Shutdown::Both);
+        let _ = self.server.shutdown(Shutdown::Both);
+    }
+}
+
+struct Listener {
+    addr: SocketAddr,
+    upstream: SocketAddr,
+    closed: Arc<AtomicBool>,
+}
+
+struct Link {
+    profile: NetworkProfile,
+    rng: StdRng,
+    // One shared medium per direction, so concurrent connections split the bandwidth
+    up_free_at: Instant,
+    down_free_at: Instant,
+    stats: EmulatorStats,
+    next_connection: u64,
+    connections: HashMap<u64, Connection>,
+}
+
+impl Link {
+    fn transmit(&mut self, flow: Flow, len: usize) -> Transit {
+        let now = Instant::now();
+        let serialize = self.profile.transmit_time(len);
+        let free_at = match flow {
+            Flow::Up => {
+                self.stats.bytes_up += len as u64;
+                &mut self.up_free_at
+            }
+            Flow::Down => {
+                self.stats.bytes_down += len as u64;
+                &mut self.down_free_at
+            }
+        };
+        *free_at = (*free_at).max(now) + serialize;
+        let departs = *free_at;
+        let lost = self.profile.loss > 0.0 && self.rng.gen_bool(self.profile.loss);
+        let arrives = departs + self.profile.one_way_delay(&mut self.rng);
+        Transit { departs, arrives, lost }
+    }
+
+    fn reset_all(&mut self) -> usize {
+        let count = self.connections.len();
+        for connection in self.connections.values() {
+            connection.reset();
+        }
+        self.connections.clear();
+        self.stats.resets += count;
+        count
+    }
+}
+
+type SharedLink = Arc<Mutex<Link>>;
+
+// Loopback proxies that push real sockets through one emulated link. Every proxy started on
+// the same emulator shares its profile, which can be switched while traffic is flowing
+pub struct NetworkEmulator {
+    link: SharedLink,
+    shutdown: Arc<AtomicBool>,
+    listeners: Vec<Listener>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl NetworkEmulator {
+    pub fn new(profile: NetworkProfile, seed: u64) -> Self {
+        let now = Instant::now();
+        let link = Link {
+            profile,
+            rng: StdRng::seed_from_u64(seed),
+            up_free_at: now,
+            down_free_at: now,
+            stats: EmulatorStats::default(),
+            next_connection: 0,
+            connections: HashMap::new(),
+        };
+        Self {
+            link: Arc::new(Mutex::new(link)),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            listeners: Vec::new(),
+            workers: Vec::new(),
+        }
+    }
+
+    // Takes effect from the next segment or datagram; data already in flight keeps its timing
+    pub fn set_profile(&self, profile: NetworkProfile) {
+        self.link.lock().unwrap().profile = profile;
+    }
+
+    pub fn stats(&self) -> EmulatorStats {
+        self.link.lock().unwrap().stats
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.link.lock().unwrap().connections.len()
+    }
+
+    // Tears down every proxied TCP connection, as losing the radio does
+    pub fn reset_connections(&self) -> usize {
+        self.link.lock().unwrap().reset_all()
+    }
+
+    // Returns the loopback address clients connect to instead of `upstream`
+    pub fn proxy_tcp(&mut self, upstream: SocketAddr) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let link = Arc::clone(&self.link);
+        let shutdown = Arc::clone(&self.shutdown);
+        let closed = Arc::new(AtomicBool::new(false));
+
+        {
+            let closed = Arc::clone(&closed);
+            self.workers.push(thread::spawn(move || {
+                for client in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) || closed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(client) = client else { continue };
+                    let link = Arc::clone(&link);
+                    thread::spawn(move || {
+                        let _ = serve_connection(client, addr, upstream, &link);
+                    });
+                }
+            }));
+        }
+        self.listeners.push(Listener { addr, upstream, closed });
+        Ok(addr)
+    }
+
+    // Handover to another network: the device gets a new address, so `proxy` stops accepting
+    // and the same upstream is reachable through the returned one. Connections through the old
+    // address are reset, or with `silent` black-holed the way a vanished NAT mapping does it
+    pub fn migrate(&mut self, proxy: SocketAddr, silent: bool) -> io::Result<SocketAddr> {
+        let index = self
+            .listeners
+            .iter()
+            .position(|l| l.addr == proxy)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no proxy on {}", proxy)))?;
+        let old = self.listeners.remove(index);
+        old.closed.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the port is released
+        let _ = TcpStream::connect(old.addr);
+
+        {
+            let mut link = self.link.lock().unwrap();
+            let link = &mut *link;
+            let affected: Vec<u64> = link.connections.iter().filter(|(_, c)| c.proxy == proxy).map(|(id, _)| *id).collect();
+            for id in affected {
+                if silent {
+                    link.connections.get_mut(&id).unwrap().stalled = true;
+                } else if let Some(connection) = link.connections.remove(&id) {
+                    connection.reset();
+                    link.stats.resets += 1;
+                }
+            }
+        }
+        self.proxy_tcp(old.upstream)
+    }
+
+    pub fn proxy_udp(&mut self, upstream: SocketAddr) -> io::Result<SocketAddr> {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0")?);
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let addr = socket.local_addr()?;
+        let (schedule, queue) = mpsc::channel();
+
+        let shutdown = Arc::clone(&self.shutdown);
+        self.workers.push(thread::spawn(move || deliver_datagrams(queue, &shutdown)));
+
+        let link = Arc::clone(&self.link);
+        let shutdown = Arc::clone(&self.shutdown);
+        self.workers.push(thread::spawn(move || {
+            let _ = relay_datagrams(socket, upstream, &link, &schedule, &shutdown);
+        }));
+        Ok(addr)
+    }
+}
+
+impl Drop for NetworkEmulator {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the blocking accepts
+        for listener in &self.listeners {
+            let _ = TcpStream::connect(listener.addr);
+        }
+        self.reset_connections();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn sleep_until(at: Instant) {
+    let now = Instant::now();
+    if at > now {
+        thread::sleep(at - now);
+    }
+}
+
+fn serve_connection(client: TcpStream, proxy: SocketAddr, upstream: SocketAddr, link: &SharedLink) -> io::Result<()> {
+    // An unreachable upstream shows up to the client as an immediate close
+    let server = TcpStream::connect(upstream)?;
+    client.set_nodelay(true)?;
+    server.set_nodelay(true)?;
+
+    let id = {
+        let mut link = link.lock().unwrap();
+        link.next_connection += 1;
+        link.stats.connections += 1;
+        let id = link.next_connection;
+        link.connections.insert(
+            id,
+            Connection { client: client.try_clone()?, server: server.try_clone()?, proxy, stalled: false },
+        );
+        id
+    };
+
+    let up = {
+        let (client, server) = (client.try_clone()?, server.try_clone()?);
+        let link = Arc::clone(link);
+        thread::spawn(move || pump(client, server, Flow::Up, id, &link))
+    };
+    pump(server, client, Flow::Down, id, link);
+    let _ = up.join();
+
+    link.lock().unwrap().connections.remove(&id);
+    Ok(())
+}
+
+// Reads segments from one side and hands them to a writer thread with their arrival time,
+// so the link stays pipelined while the reader is held back by the bandwidth cap
+fn pump(mut from: TcpStream, to: TcpStream, flow: Flow, id: u64, link: &SharedLink) {
+    let (send, arrivals) = mpsc::channel::<(Instant, Vec<u8>)>();
+    let writer = thread::spawn(move || deliver_segments(arrivals, to));
+
+    let mut buffer = vec![0; TCP_SEGMENT];
+    let mut last_arrival = Instant::now();
+    loop {
+        let n = match from.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let transit = {
+            let mut link = link.lock().unwrap();
+            if link.connections.get(&id).is_some_and(|c| c.stalled) {
+                continue;
+            }
+            let reset_chance = link.profile.reset;
+            if reset_chance > 0.0 && link.rng.gen_bool(reset_chance) {
+                if let Some(connection) = link.connections.remove(&id) {
+                    connection.reset();
+                    link.stats.resets += 1;
+                }
+                break;
+            }
+            let mut transit = link.transmit(flow, n);
+            // The segment arrives in order after the retransmission, stalling everything behind it
+            if transit.lost {
+                transit.arrives += link.profile.retransmission_timeout();
+                link.stats.retransmits += 1;
+            }
+            transit
+        };
+        sleep_until(transit.departs);
+        last_arrival = last_arrival.max(transit.arrives);
+        if send.send((last_arrival, buffer[..n].to_vec())).is_err() {
+            break;
+        }
+    }
+    drop(send);
+    let _ = writer.join();
+}
+
+fn deliver_segments(arrivals: Receiver<(Instant, Vec<u8>)>, mut to: TcpStream) {
+    for (at, segment) in arrivals {
+        sleep_until(at);
+        if to.write_all(&segment).is_err() {
+            return;
+        }
+    }
+    // The sender finished cleanly; pass the half-close on
+    let _ = to.shutdown(Shutdown::Write);
+}
+
+struct Datagram {
+    at: Instant,
+    socket: Arc<UdpSocket>,
+    // None for a connected socket
+    to: Option<SocketAddr>,
+    payload: Vec<u8>,
+}
+
+fn admit(link: &SharedLink, flow: Flow, len: usize) -> Option<Instant> {
+    let mut link = link.lock().unwrap();
+    link.stats.datagrams += 1;
+    let backlog = match flow {
+        Flow::Up => link.up_free_at,
+        Flow::Down => link.down_free_at,
+    };
+    if backlog.saturating_duration_since(Instant::now()) > MAX_QUEUE_DELAY {
+        link.stats.datagrams_dropped += 1;
+        return None;
+    }
+    let transit = link.transmit(flow, len);
+    if transit.lost {
+        link.stats.datagrams_dropped += 1;
+        return None;
+    }
+    Some(transit.arrives)
+}
+
+// One upstream socket per client address, like a NAT mapping
+fn relay_datagrams(
+    socket: Arc<UdpSocket>,
+    upstream: SocketAddr,
+    link: &SharedLink,
+    schedule: &Sender<Datagram>,
+    shutdown: &Arc<AtomicBool>,
+) -> io::Result<()> {
+    let mut mappings: HashMap<SocketAddr, Arc<UdpSocket>> = HashMap::new();
+    let mut buffer = vec![0; MAX_DATAGRAM];
+    while !shutdown.load(Ordering::SeqCst) {
+        let (n, client) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e),
+        };
+
+        let mapped = match mappings.get(&client) {
+            Some(mapped) => Arc::clone(mapped),
+            None => {
+                let mapped = Arc::new(UdpSocket::bind("127.0.0.1:0")?);
+                mapped.connect(upstream)?;
+                mapped.set_read_timeout(Some(POLL_INTERVAL))?;
+                let (reply, socket, link, schedule, shutdown) =
+                    (Arc::clone(&mapped), Arc::clone(&socket), Arc::clone(link), schedule.clone(), Arc::clone(shutdown));
+                thread::spawn(move || {
+                    let mut buffer = vec![0; MAX_DATAGRAM];
+                    while !shutdown.load(Ordering::SeqCst) {
+                        let Ok(n) = reply.recv(&mut buffer) else { continue };
+                        if let Some(at) = admit(&link, Flow::Down, n) {
+                            let datagram = Datagram { at, socket: Arc::clone(&socket), to: Some(client), payload: buffer[..n].to_vec() };
+                            if schedule.send(datagram).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+                mappings.insert(client, Arc::clone(&mapped));
+                mapped
+            }
+        };
+
+        if let Some(at) = admit(link, Flow::Up, n) {
+            let datagram = Datagram { at, socket: mapped, to: None, payload: buffer[..n].to_vec() };
+            if schedule.send(datagram).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Jitter may reorder datagrams, so they are sent by arrival time rather than in queue order
+fn deliver_datagrams(queue: Receiver<Datagram>, shutdown: &Arc<AtomicBool>) {
+    let mut pending: Vec<Datagram> = Vec::new();
+    while !shutdown.load(Ordering::SeqCst) {
+        let timeout = pending
+            .iter()
+            .map(|d| d.at)
+            .min()
+            .map_or(POLL_INTERVAL, |at| at.saturating_duration_since(Instant::now()).min(POLL_INTERVAL));
+        match queue.recv_timeout(timeout) {
+            Ok(datagram) => pending.push(datagram),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        pending.sort_by_key(|d| d.at);
+        let due = pending.iter().take_while(|d| d.at <= now).count();
+        for datagram in pending.drain(..due) {
+            let _ = match datagram.to {
+                Some(to) => datagram.socket.send_to(&datagram.payload, to),
+                None => datagram.socket.send(&datagram.payload),
+            };
+        }
+    }
+}
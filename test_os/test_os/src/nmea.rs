@@ -0,0 +1,545 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, PipeReader, Write};
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::clock::SharedClock;
+use crate::location_trace::{self, LocationEvent, LocationFix, LocationTrace, PlaybackConfig};
+
+// Including the leading '$' and the trailing CRLF
+pub const MAX_SENTENCE_LEN: usize = 82;
+const KNOTS_PER_MPS: f64 = 1.943_844;
+// User equivalent range error; accuracy = HDOP * UERE
+const UERE_M: f32 = 5.0;
+const GSA_SLOTS: usize = 12;
+const SATELLITES_PER_GSV: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NmeaError {
+    NotASentence,
+    MissingChecksum,
+    ChecksumMismatch { expected: u8, actual: u8 },
+    Unsupported(String),
+    BadField { sentence: &'static str, field: &'static str },
+}
+
+impl fmt::Display for NmeaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NmeaError::NotASentence => write!(f, "not an NMEA sentence"),
+            NmeaError::MissingChecksum => write!(f, "missing checksum"),
+            NmeaError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: {:02X} != {:02X}", actual, expected)
+            }
+            NmeaError::Unsupported(kind) => write!(f, "unsupported sentence {}", kind),
+            NmeaError::BadField { sentence, field } => write!(f, "bad {} in {}", field, sentence),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gga {
+    pub time: NaiveTime,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    // 0 = no fix, 1 = GPS, 2 = DGPS
+    pub quality: u8,
+    pub satellites: u8,
+    pub hdop: Option<f32>,
+    pub altitude: Option<f64>,
+    pub geoid_separation: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rmc {
+    pub time: NaiveTime,
+    pub valid: bool,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub speed_knots: Option<f64>,
+    pub course: Option<f64>,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gsa {
+    pub automatic: bool,
+    // 1 = no fix, 2 = 2D, 3 = 3D
+    pub fix_type: u8,
+    pub prns: Vec<u8>,
+    pub pdop: Option<f32>,
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SatelliteInView {
+    pub prn: u8,
+    pub elevation: Option<u8>,
+    pub azimuth: Option<u16>,
+    // None while the satellite is not tracked
+    pub snr: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gsv {
+    pub messages: u8,
+    pub number: u8,
+    pub in_view: u8,
+    pub satellites: Vec<SatelliteInView>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sentence {
+    Gga(Gga),
+    Rmc(Rmc),
+    Gsa(Gsa),
+    Gsv(Gsv),
+}
+
+pub fn checksum(body: &str) -> u8 {
+    body.bytes().fold(0, |acc, b| acc ^ b)
+}
+
+// Splits `$GPRMC,...*hh` into fields, rejecting sentences whose checksum does not match
+fn fields(line: &str) -> Result<Vec<&str>, NmeaError> {
+    let body = line.trim_end().strip_prefix('$').ok_or(NmeaError::NotASentence)?;
+    let (body, hex) = body.split_once('*').ok_or(NmeaError::MissingChecksum)?;
+    let expected = u8::from_str_radix(hex, 16).map_err(|_| NmeaError::MissingChecksum)?;
+    let actual = checksum(body);
+    if actual != expected {
+        return Err(NmeaError::ChecksumMismatch { expected, actual });
+    }
+    Ok(body.split(',').collect())
+}
+
+// Any talker is accepted: GP, GN, GL, GA...
+pub fn parse(line: &str) -> Result<Sentence, NmeaError> {
+    let f = fields(line)?;
+    let kind = f[0].get(2..).unwrap_or("");
+    let field = |sentence: &'static str, index: usize, name: &'static str| -> Result<&str, NmeaError> {
+        f.get(index).copied().ok_or(NmeaError::BadField { sentence, field: name })
+    };
+
+    match kind {
+        "GGA" => {
+            let s = "GGA";
+            Ok(Sentence::Gga(Gga {
+                time: parse_time(field(s, 1, "time")?).ok_or(NmeaError::BadField { sentence: s, field: "time" })?,
+                latitude: parse_coord(field(s, 2, "latitude")?, field(s, 3, "latitude")?),
+                longitude: parse_coord(field(s, 4, "longitude")?, field(s, 5, "longitude")?),
+                quality: required(s, "quality", field(s, 6, "quality")?)?,
+                satellites: optional(s, "satellites", field(s, 7, "satellites")?)?.unwrap_or(0),
+                hdop: optional(s, "hdop", field(s, 8, "hdop")?)?,
+                altitude: optional(s, "altitude", field(s, 9, "altitude")?)?,
+                geoid_separation: optional(s, "geoid separation", field(s, 11, "geoid separation")?)?,
+            }))
+        }
+        "RMC" => {
+            let s = "RMC";
+            Ok(Sentence::Rmc(Rmc {
+                time: parse_time(field(s, 1, "time")?).ok_or(NmeaError::BadField { sentence: s, field: "time" })?,
+                valid: field(s, 2, "status")? == "A",
+                latitude: parse_coord(field(s, 3, "latitude")?, field(s, 4, "latitude")?),
+                longitude: parse_coord(field(s, 5, "longitude")?, field(s, 6, "longitude")?),
+                speed_knots: optional(s, "speed", field(s, 7, "speed")?)?,
+                course: optional(s, "course", field(s, 8, "course")?)?,
+                date: NaiveDate::parse_from_str(field(s, 9, "date")?, "%d%m%y")
+                    .map_err(|_| NmeaError::BadField { sentence: s, field: "date" })?,
+            }))
+        }
+        "GSA" => {
+            let s = "GSA";
+            let mut prns = Vec::new();
+            for index in 3..3 + GSA_SLOTS {
+                if let Some(prn) = optional(s, "prn", field(s, index, "prn")?)? {
+                    prns.push(prn);
+                }
+            }
+            Ok(Sentence::Gsa(Gsa {
+                automatic: field(s, 1, "mode")? == "A",
+                fix_type: required(s, "fix type", field(s, 2, "fix type")?)?,
+                prns,
+                pdop: optional(s, "pdop", field(s, 15, "pdop")?)?,
+                hdop: optional(s, "hdop", field(s, 16, "hdop")?)?,
+                vdop: optional(s, "vdop", field(s, 17, "vdop")?)?,
+            }))
+        }
+        "GSV" => {
+            let s = "GSV";
+            let mut satellites = Vec::new();
+            // Up to four (prn, elevation, azimuth, snr) blocks; the last message may carry fewer
+            for block in f.get(4..).unwrap_or(&[]).chunks(4) {
+                if block.len() < 4 {
+                    return Err(NmeaError::BadField { sentence: s, field: "satellite" });
+                }
+                satellites.push(SatelliteInView {
+                    prn: required(s, "prn", block[0])?,
+                    elevation: optional(s, "elevation", block[1])?,
+                    azimuth: optional(s, "azimuth", block[2])?,
+                    snr: optional(s, "snr", block[3])?,
+                });
+            }
+            Ok(Sentence::Gsv(Gsv {
+                messages: required(s, "message count", field(s, 1, "message count")?)?,
+                number: required(s, "message number", field(s, 2, "message number")?)?,
+                in_view: required(s, "satellites in view", field(s, 3, "satellites in view")?)?,
+                satellites,
+            }))
+        }
+        _ => Err(NmeaError::Unsupported(f[0].to_string())),
+    }
+}
+
+fn optional<T: FromStr>(sentence: &'static str, field: &'static str, value: &str) -> Result<Option<T>, NmeaError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(|_| NmeaError::BadField { sentence, field })
+}
+
+fn required<T: FromStr>(sentence: &'static str, field: &'static str, value: &str) -> Result<T, NmeaError> {
+    optional(sentence, field, value)?.ok_or(NmeaError::BadField { sentence, field })
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H%M%S%.f").ok()
+}
+
+// ddmm.mmmm / dddmm.mmmm with hemisphere
+fn parse_coord(value: &str, hemisphere: &str) -> Option<f64> {
+    let dot = value.find('.')?;
+    let degrees: f64 = value[..dot.checked_sub(2)?].parse().ok()?;
+    let minutes: f64 = value[dot - 2..].parse().ok()?;
+    let coord = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(coord),
+        "S" | "W" => Some(-coord),
+        _ => None,
+    }
+}
+
+fn format_time(time: NaiveTime) -> String {
+    let centis = time.nanosecond() / 10_000_000;
+    format!("{:02}{:02}{:02}.{:02}", time.hour(), time.minute(), time.second(), centis)
+}
+
+fn format_coord(coord: Option<f64>, degree_digits: usize, hemispheres: [char; 2]) -> String {
+    let Some(coord) = coord else {
+        return ",".to_string();
+    };
+    // Round on whole 1e-4 minutes first so 59.99999' does not print as 60.0000'
+    let total = (coord.abs() * 60.0 * 10_000.0).round() as u64;
+    let (degrees, minutes) = (total / 600_000, (total % 600_000) as f64 / 10_000.0);
+    let hemisphere = if coord < 0.0 { hemispheres[1] } else { hemispheres[0] };
+    format!("{:0width$}{:07.4},{}", degrees, minutes, hemisphere, width = degree_digits)
+}
+
+fn format_optional<T: fmt::Display>(value: Option<T>, precision: usize) -> String {
+    value.map_or(String::new(), |v| format!("{:.*}", precision, v))
+}
+
+impl Sentence {
+    // Complete sentence with checksum, without the line terminator
+    pub fn to_sentence(&self, talker: &str) -> String {
+        let body = match self {
+            Sentence::Gga(gga) => format!(
+                "{}GGA,{},{},{},{},{:02},{},{},M,{},M,,",
+                talker,
+                format_time(gga.time),
+                format_coord(gga.latitude, 2, ['N', 'S']),
+                format_coord(gga.longitude, 3, ['E', 'W']),
+                gga.quality,
+                gga.satellites,
+                format_optional(gga.hdop, 1),
+                format_optional(gga.altitude, 1),
+                format_optional(gga.geoid_separation, 1),
+            ),
+            Sentence::Rmc(rmc) => format!(
+                "{}RMC,{},{},{},{},{},{},{},,,{}",
+                talker,
+                format_time(rmc.time),
+                if rmc.valid { 'A' } else { 'V' },
+                format_coord(rmc.latitude, 2, ['N', 'S']),
+                format_coord(rmc.longitude, 3, ['E', 'W']),
+                format_optional(rmc.speed_knots, 1),
+                format_optional(rmc.course, 1),
+                rmc.date.format("%d%m%y"),
+                if rmc.valid { 'A' } else { 'N' },
+            ),
+            Sentence::Gsa(gsa) => {
+                let mut slots: Vec<String> = gsa.prns.iter().take(GSA_SLOTS).map(|prn| format!("{:02}", prn)).collect();
+                slots.resize(GSA_SLOTS, String::new());
+                format!(
+                    "{}GSA,{},{},{},{},{},{}",
+                    talker,
+                    if gsa.automatic { 'A' } else { 'M' },
+                    gsa.fix_type,
+                    slots.join(","),
+                    format_optional(gsa.pdop, 1),
+                    format_optional(gsa.hdop, 1),
+                    format_optional(gsa.vdop, 1),
+                )
+            }
+            Sentence::Gsv(gsv) => {
+                let mut body = format!("{}GSV,{},{},{:02}", talker, gsv.messages, gsv.number, gsv.in_view);
+                for sat in &gsv.satellites {
+                    body.push_str(&format!(
+                        ",{:02},{},{},{}",
+                        sat.prn,
+                        sat.elevation.map_or(String::new(), |e| format!("{:02}", e)),
+                        sat.azimuth.map_or(String::new(), |a| format!("{:03}", a)),
+                        sat.snr.map_or(String::new(), |s| format!("{:02}", s)),
+                    ));
+                }
+                body
+            }
+        };
+        format!("${}*{:02X}", body, checksum(&body))
+    }
+}
+
+// Deterministic constellation: satellites low on the horizon are in view but not tracked
+pub fn simulated_sky(count: usize, seed: u64) -> Vec<SatelliteInView> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut prns: Vec<u8> = (1..=32).collect();
+    let mut sky = Vec::with_capacity(count);
+
+    for _ in 0..count.min(prns.len()) {
+        let prn = prns.swap_remove(rng.gen_range(0..prns.len()));
+        let elevation: u8 = rng.gen_range(3..=85);
+        sky.push(SatelliteInView {
+            prn,
+            elevation: Some(elevation),
+            azimuth: Some(rng.gen_range(0..360)),
+            snr: (elevation >= 10).then(|| 20 + elevation / 4 + rng.gen_range(0..8)),
+        });
+    }
+    sky.sort_by_key(|sat| sat.prn);
+    sky
+}
+
+fn gsv_pages(sky: &[SatelliteInView]) -> Vec<Sentence> {
+    let messages = sky.len().div_ceil(SATELLITES_PER_GSV).max(1);
+    (0..messages)
+        .map(|page| {
+            let from = (page * SATELLITES_PER_GSV).min(sky.len());
+            let to = (from + SATELLITES_PER_GSV).min(sky.len());
+            Sentence::Gsv(Gsv {
+                messages: messages as u8,
+                number: page as u8 + 1,
+                in_view: sky.len() as u8,
+                satellites: sky[from..to].to_vec(),
+            })
+        })
+        .collect()
+}
+
+// One receiver epoch in the usual u-blox order: GGA, GSA, GSV..., RMC
+pub fn fix_epoch(fix: &LocationFix, at: NaiveDateTime, sky: &[SatelliteInView]) -> Vec<Sentence> {
+    let used: Vec<u8> = sky.iter().filter(|sat| sat.snr.is_some()).map(|sat| sat.prn).collect();
+    let hdop = fix.accuracy / UERE_M;
+    let fix_type = if fix.altitude.is_some() { 3 } else { 2 };
+
+    let mut sentences = vec![
+        Sentence::Gga(Gga {
+            time: at.time(),
+            latitude: Some(fix.latitude),
+            longitude: Some(fix.longitude),
+            quality: 1,
+            satellites: used.len() as u8,
+            hdop: Some(hdop),
+            altitude: fix.altitude,
+            geoid_separation: fix.altitude.map(|_| -29.9),
+        }),
+        Sentence::Gsa(Gsa {
+            automatic: true,
+            fix_type,
+            prns: used,
+            pdop: Some(hdop * 1.6),
+            hdop: Some(hdop),
+            vdop: Some(hdop * 1.3),
+        }),
+    ];
+    sentences.extend(gsv_pages(sky));
+    sentences.push(Sentence::Rmc(Rmc {
+        time: at.time(),
+        valid: true,
+        latitude: Some(fix.latitude),
+        longitude: Some(fix.longitude),
+        speed_knots: Some(fix.speed as f64 * KNOTS_PER_MPS),
+        course: Some(fix.bearing as f64),
+        date: at.date(),
+    }));
+    sentences
+}
+
+// What a receiver keeps sending while it has no position
+pub fn no_fix_epoch(at: NaiveDateTime, sky: &[SatelliteInView]) -> Vec<Sentence> {
+    let mut sentences = vec![
+        Sentence::Gga(Gga {
+            time: at.time(),
+            latitude: None,
+            longitude: None,
+            quality: 0,
+            satellites: 0,
+            hdop: None,
+            altitude: None,
+            geoid_separation: None,
+        }),
+        Sentence::Gsa(Gsa { automatic: true, fix_type: 1, prns: Vec::new(), pdop: None, hdop: None, vdop: None }),
+    ];
+    sentences.extend(gsv_pages(sky));
+    sentences.push(Sentence::Rmc(Rmc {
+        time: at.time(),
+        valid: false,
+        latitude: None,
+        longitude: None,
+        speed_knots: None,
+        course: None,
+        date: at.date(),
+    }));
+    sentences
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GnssFix {
+    pub time: NaiveDateTime,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    // m/s and degrees from true north
+    pub speed: f64,
+    pub course: Option<f64>,
+    pub hdop: Option<f32>,
+    pub satellites_used: usize,
+    pub satellites_in_view: usize,
+}
+
+// Assembles sentences of one epoch into a fix; RMC closes the epoch, as it does on most receivers
+#[derive(Debug, Default)]
+pub struct FixDecoder {
+    gga: Option<Gga>,
+    gsa: Option<Gsa>,
+    in_view: usize,
+}
+
+impl FixDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, sentence: &Sentence) -> Option<GnssFix> {
+        match sentence {
+            Sentence::Gga(gga) => self.gga = Some(*gga),
+            Sentence::Gsa(gsa) => self.gsa = Some(gsa.clone()),
+            Sentence::Gsv(gsv) => self.in_view = gsv.in_view as usize,
+            Sentence::Rmc(rmc) => {
+                // GGA of another epoch says nothing about this one
+                let gga = self.gga.take().filter(|gga| gga.time == rmc.time && gga.quality > 0);
+                let gsa = self.gsa.take();
+                let (Some(latitude), Some(longitude)) = (rmc.latitude, rmc.longitude) else {
+                    return None;
+                };
+                if !rmc.valid {
+                    return None;
+                }
+                return Some(GnssFix {
+                    time: rmc.date.and_time(rmc.time),
+                    latitude,
+                    longitude,
+                    altitude: gga.and_then(|gga| gga.altitude),
+                    speed: rmc.speed_knots.unwrap_or(0.0) / KNOTS_PER_MPS,
+                    course: rmc.course,
+                    hdop: gga.and_then(|gga| gga.hdop).or(gsa.as_ref().and_then(|gsa| gsa.hdop)),
+                    satellites_used: gsa.map_or(gga.map_or(0, |gga| gga.satellites as usize), |gsa| gsa.prns.len()),
+                    satellites_in_view: self.in_view,
+                });
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceConfig {
+    // Receivers are configurable between 1 and 10 epochs per second
+    pub rate_hz: u32,
+    pub talker: String,
+    // UTC of the first trace point
+    pub start: NaiveDateTime,
+    pub satellites: usize,
+    pub playback: PlaybackConfig,
+}
+
+// Fake GNSS receiver writing NMEA lines into a pipe, like a serial device node would
+pub struct NmeaDevice {
+    lines: io::Lines<BufReader<PipeReader>>,
+    writer: JoinHandle<io::Result<usize>>,
+}
+
+impl NmeaDevice {
+    // Plays the trace on `clock`; the playback fix interval is replaced by 1 / rate_hz
+    pub fn spawn(trace: LocationTrace, config: DeviceConfig, clock: SharedClock) -> Result<Self, String> {
+        if !(1..=10).contains(&config.rate_hz) {
+            return Err(format!("unsupported NMEA rate {}Hz, expected 1..=10", config.rate_hz));
+        }
+        let (reader, mut pipe) = io::pipe().map_err(|e| format!("failed to open pipe: {}", e))?;
+
+        let writer = thread::spawn(move || {
+            let sky = simulated_sky(config.satellites, config.playback.seed);
+            let playback = PlaybackConfig {
+                fix_interval: Duration::from_secs(1) / config.rate_hz,
+                ..config.playback.clone()
+            };
+            let origin = clock.now();
+            let utc = |trace_time: Duration| config.start + chrono::Duration::from_std(trace_time).unwrap();
+            let mut written = 0;
+            let mut result = Ok(());
+
+            location_trace::play(&trace, &playback, clock.as_ref(), |event| {
+                let sentences = match event {
+                    LocationEvent::Fix(fix) => fix_epoch(fix, utc(fix.trace_time), &sky),
+                    LocationEvent::FixLost { at } => no_fix_epoch(utc((*at - origin).mul_f64(playback.speed)), &sky),
+                };
+                // One write per epoch so a reader never sees half of it
+                let mut epoch = String::new();
+                for sentence in &sentences {
+                    epoch.push_str(&sentence.to_sentence(&config.talker));
+                    epoch.push_str("\r\n");
+                }
+                result = pipe.write_all(epoch.as_bytes());
+                written += sentences.len();
+                result.is_ok()
+            });
+
+            match result {
+                // The reader closed the device
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(written),
+                other => other.map(|_| written),
+            }
+        });
+
+        Ok(Self { lines: BufReader::new(reader).lines(), writer })
+    }
+
+    // Waits for the writer thread; returns the number of sentences written
+    pub fn close(self) -> io::Result<usize> {
+        drop(self.lines);
+        self.writer.join().map_err(|_| io::Error::other("NMEA writer panicked"))?
+    }
+}
+
+// Lines as they arrive, CRLF stripped; ends with the trace
+impl Iterator for NmeaDevice {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next().map(|line| line.map(|l| l.trim_end().to_string()))
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use crate::clock::SharedClock;
+use crate::display_text;
+
+// NotificationManagerService limits per app
+pub const MAX_ACTIVE_NOTIFICATIONS: usize = 50;
+pub const MAX_UPDATES_PER_SECOND: usize = 5;
+// Columns of a collapsed shade row on a phone in portrait
+pub const COLLAPSED_TITLE_WIDTH: usize = 24;
+pub const COLLAPSED_BODY_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Importance {
+    // Channel blocked by the user
+    None,
+    Min,
+    Low,
+    Default,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationChannel {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub importance: Importance,
+    pub bypass_dnd: bool,
+}
+
+impl NotificationChannel {
+    pub fn new(id: &str, name: &str, description: &str, importance: Importance) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            importance,
+            bypass_dnd: false,
+        }
+    }
+
+    pub fn bypassing_dnd(mut self) -> Self {
+        self.bypass_dnd = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub channel_id: String,
+    pub title: String,
+    pub body: String,
+    pub group: Option<String>,
+    // Updates of an already posted notification stay silent
+    pub only_alert_once: bool,
+    // Tapping removes it from the shade
+    pub auto_cancel: bool,
+}
+
+impl Notification {
+    pub fn new(channel_id: &str, title: &str, body: &str) -> Self {
+        Self {
+            channel_id: channel_id.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            group: None,
+            only_alert_once: false,
+            auto_cancel: true,
+        }
+    }
+
+    pub fn in_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    pub fn alert_once(mut self) -> Self {
+        self.only_alert_once = true;
+        self
+    }
+
+    // Title and body as a collapsed shade row shows them
+    pub fn collapsed(&self) -> (String, String) {
+        (
+            display_text::truncate(&self.title, COLLAPSED_TITLE_WIDTH),
+            display_text::truncate(&self.body, COLLAPSED_BODY_WIDTH),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interruption {
+    HeadsUp,
+    Sound,
+    Silent,
+    // Would have alerted, but Do Not Disturb is on
+    SuppressedByDnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DndMode {
+    Off,
+    // Only channels allowed to bypass DND may alert
+    PriorityOnly,
+    TotalSilence,
+}
+
+// Scheduled DND window on the manager clock, like an automatic "Sleeping" rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct DndRule {
+    pub name: String,
+    pub start: Duration,
+    pub end: Duration,
+    pub mode: DndMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delivery {
+    pub id: u32,
+    pub updated: bool,
+    pub interruption: Interruption,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostedNotification {
+    pub id: u32,
+    // App-chosen key; posting with the same key updates in place
+    pub key: String,
+    pub notification: Notification,
+    pub posted_at: Duration,
+    pub updated_at: Duration,
+    pub updates: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupSummary {
+    pub group: String,
+    pub children: Vec<u32>,
+    // Inbox-style lines, newest first
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationError {
+    PermissionDenied,
+    UnknownChannel(String),
+    ChannelBlocked(String),
+    UnknownId(u32),
+    RateLimited,
+    TooManyNotifications { limit: usize },
+}
+
+impl fmt::Display for NotificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationError::PermissionDenied => write!(f, "notification permission not granted"),
+            NotificationError::UnknownChannel(id) => write!(f, "no notification channel '{}'", id),
+            NotificationError::ChannelBlocked(id) => write!(f, "notification channel '{}' is blocked", id),
+            NotificationError::UnknownId(id) => write!(f, "no active notification {}", id),
+            NotificationError::RateLimited => write!(f, "update rate above {}/s, shedding", MAX_UPDATES_PER_SECOND),
+            NotificationError::TooManyNotifications { limit } => write!(f, "{} notifications already active", limit),
+        }
+    }
+}
+
+pub struct NotificationManager {
+    clock: SharedClock,
+    permission_granted: bool,
+    channels: Vec<NotificationChannel>,
+    active: Vec<PostedNotification>,
+    next_id: u32,
+    dnd: DndMode,
+    dnd_rules: Vec<DndRule>,
+    recent_updates: VecDeque<Duration>,
+}
+
+impl NotificationManager {
+    // Starts without permission, as on iOS and Android 13+
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            permission_granted: false,
+            channels: Vec::new(),
+            active: Vec::new(),
+            next_id: 1,
+            dnd: DndMode::Off,
+            dnd_rules: Vec::new(),
+            recent_updates: VecDeque::new(),
+        }
+    }
+
+    pub fn set_permission(&mut self, granted: bool) {
+        self.permission_granted = granted;
+    }
+
+    // Re-creating a channel updates its name and description; importance can only be lowered
+    pub fn create_channel(&mut self, channel: NotificationChannel) {
+        match self.channels.iter_mut().find(|c| c.id == channel.id) {
+            Some(existing) => {
+                existing.name = channel.name;
+                existing.description = channel.description;
+                existing.importance = existing.importance.min(channel.importance);
+            }
+            None => self.channels.push(channel),
+        }
+    }
+
+    pub fn channel(&self, id: &str) -> Option<&NotificationChannel> {
+        self.channels.iter().find(|c| c.id == id)
+    }
+
+    // Deleting a channel cancels everything posted to it
+    pub fn delete_channel(&mut self, id: &str) -> Result<usize, NotificationError> {
+        let index = self
+            .channels
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| NotificationError::UnknownChannel(id.to_string()))?;
+        self.channels.remove(index);
+        let before = self.active.len();
+        self.active.retain(|p| p.notification.channel_id != id);
+        Ok(before - self.active.len())
+    }
+
+    pub fn set_dnd(&mut self, mode: DndMode) {
+        self.dnd = mode;
+    }
+
+    pub fn add_dnd_rule(&mut self, rule: DndRule) {
+        self.dnd_rules.push(rule);
+    }
+
+    // The strictest of the manual mode and any active rule
+    pub fn dnd_mode(&self) -> DndMode {
+        let now = self.clock.now();
+        self.dnd_rules
+            .iter()
+            .filter(|rule| now >= rule.start && now < rule.end)
+            .map(|rule| rule.mode)
+            .fold(self.dnd, DndMode::max)
+    }
+
+    pub fn notify(&mut self, key: &str, notification: Notification) -> Result<Delivery, NotificationError> {
+        if !self.permission_granted {
+            return Err(NotificationError::PermissionDenied);
+        }
+        let channel = self
+            .channel(&notification.channel_id)
+            .ok_or_else(|| NotificationError::UnknownChannel(notification.channel_id.clone()))?;
+        if channel.importance == Importance::None {
+            return Err(NotificationError::ChannelBlocked(channel.id.clone()));
+        }
+
+        let mut interruption = match channel.importance {
+            Importance::High => Interruption::HeadsUp,
+            Importance::Default => Interruption::Sound,
+            _ => Interruption::Silent,
+        };
+        let dnd = self.dnd_mode();
+        let blocked_by_dnd = match dnd {
+            DndMode::Off => false,
+            DndMode::PriorityOnly => !channel.bypass_dnd,
+            DndMode::TotalSilence => true,
+        };
+        if blocked_by_dnd && interruption != Interruption::Silent {
+            interruption = Interruption::SuppressedByDnd;
+        }
+
+        let now = self.clock.now();
+        if let Some(posted) = self.active.iter_mut().find(|p| p.key == key) {
+            // Only updates are rate limited, so progress notifications cannot flood the shade
+            while self.recent_updates.front().is_some_and(|t| now.saturating_sub(*t) >= Duration::from_secs(1)) {
+                self.recent_updates.pop_front();
+            }
+            if self.recent_updates.len() >= MAX_UPDATES_PER_SECOND {
+                return Err(NotificationError::RateLimited);
+            }
+            self.recent_updates.push_back(now);
+
+            if notification.only_alert_once {
+                interruption = Interruption::Silent;
+            }
+            posted.notification = notification;
+            posted.updated_at = now;
+            posted.updates += 1;
+            return Ok(Delivery { id: posted.id, updated: true, interruption });
+        }
+
+        if self.active.len() >= MAX_ACTIVE_NOTIFICATIONS {
+            return Err(NotificationError::TooManyNotifications { limit: MAX_ACTIVE_NOTIFICATIONS });
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.active.push(PostedNotification {
+            id,
+            key: key.to_string(),
+            notification,
+            posted_at: now,
+            updated_at: now,
+            updates: 0,
+        });
+        Ok(Delivery { id, updated: false, interruption })
+    }
+
+    pub fn cancel(&mut self, id: u32) -> Result<PostedNotification, NotificationError> {
+        let index = self
+            .active
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or(NotificationError::UnknownId(id))?;
+        Ok(self.active.remove(index))
+    }
+
+    pub fn cancel_all(&mut self) -> usize {
+        let count = self.active.len();
+        self.active.clear();
+        count
+    }
+
+    pub fn tap(&mut self, id: u32) -> Result<PostedNotification, NotificationError> {
+        let posted = self.get(id).ok_or(NotificationError::UnknownId(id))?.clone();
+        if posted.notification.auto_cancel {
+            self.cancel(id)?;
+        }
+        Ok(posted)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&PostedNotification> {
+        self.active.iter().find(|p| p.id == id)
+    }
+
+    pub fn active(&self) -> &[PostedNotification] {
+        &self.active
+    }
+
+    // A group is bundled under a summary once it has two or more children
+    pub fn group_summary(&self, group: &str) -> Option<GroupSummary> {
+        let mut children: Vec<&PostedNotification> =
+            self.active.iter().filter(|p| p.notification.group.as_deref() == Some(group)).collect();
+        if children.len() < 2 {
+            return None;
+        }
+        children.sort_by_key(|p| std::cmp::Reverse((p.updated_at, p.id)));
+        Some(GroupSummary {
+            group: group.to_string(),
+            children: children.iter().map(|p| p.id).collect(),
+            lines: children.iter().map(|p| display_text::truncate(&p.notification.title, COLLAPSED_BODY_WIDTH)).collect(),
+        })
+    }
+}
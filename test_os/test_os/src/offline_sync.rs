@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+pub type Fields = Map<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub fields: Fields,
+    pub version: u64,
+    // Client time of the write that produced this version, in milliseconds
+    pub updated_at: u64,
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    // Only the fields that changed
+    Upsert(Fields),
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub seq: u64,
+    pub idempotency_key: String,
+    pub collection: String,
+    pub record_id: String,
+    pub change: Change,
+    // Version and fields the client saw when making the change; None for a new record
+    pub base_version: Option<u64>,
+    pub base_fields: Fields,
+    pub at_ms: u64,
+}
+
+impl Operation {
+    fn to_json(&self) -> Value {
+        let change = match &self.change {
+            Change::Upsert(fields) => json!({ "upsert": fields }),
+            Change::Delete => json!("delete"),
+        };
+        json!({
+            "seq": self.seq,
+            "key": self.idempotency_key,
+            "collection": self.collection,
+            "id": self.record_id,
+            "change": change,
+            "base_version": self.base_version,
+            "base": self.base_fields,
+            "at_ms": self.at_ms,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let change = match &value["change"] {
+            Value::String(s) if s == "delete" => Change::Delete,
+            change => Change::Upsert(change["upsert"].as_object()?.clone()),
+        };
+        Some(Self {
+            seq: value["seq"].as_u64()?,
+            idempotency_key: value["key"].as_str()?.to_string(),
+            collection: value["collection"].as_str()?.to_string(),
+            record_id: value["id"].as_str()?.to_string(),
+            change,
+            base_version: value["base_version"].as_u64(),
+            base_fields: value["base"].as_object()?.clone(),
+            at_ms: value["at_ms"].as_u64()?,
+        })
+    }
+
+    // What the client's copy of the record looks like after this change
+    pub fn local_fields(&self) -> Fields {
+        let mut fields = self.base_fields.clone();
+        if let Change::Upsert(patch) = &self.change {
+            fields.extend(patch.clone());
+        }
+        fields
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Applied {
+    pub version: u64,
+    // The idempotency key was seen before; nothing was written
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    // Transient: keep the operation and retry on the next sync
+    Unavailable,
+    Conflict { current: Record },
+    // Permanent: the server will never accept this operation
+    Rejected(String),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Unavailable => write!(f, "sync server unavailable"),
+            SyncError::Conflict { current } => write!(f, "conflict with server version {}", current.version),
+            SyncError::Rejected(reason) => write!(f, "rejected: {}", reason),
+        }
+    }
+}
+
+pub trait SyncTarget {
+    fn apply(&mut self, op: &Operation) -> Result<Applied, SyncError>;
+}
+
+pub trait ConflictResolver {
+    fn name(&self) -> &'static str;
+
+    // Change to write over the server's record, or None to keep the server's version
+    fn resolve(&self, op: &Operation, server: &Record) -> Option<Change>;
+}
+
+// The whole local record replaces the server's if it was written later
+pub struct LastWriterWins;
+
+impl ConflictResolver for LastWriterWins {
+    fn name(&self) -> &'static str {
+        "last-writer-wins"
+    }
+
+    fn resolve(&self, op: &Operation, server: &Record) -> Option<Change> {
+        if op.at_ms <= server.updated_at {
+            return None;
+        }
+        Some(match op.change {
+            Change::Upsert(_) => Change::Upsert(op.local_fields()),
+            Change::Delete => Change::Delete,
+        })
+    }
+}
+
+pub struct ServerWins;
+
+impl ConflictResolver for ServerWins {
+    fn name(&self) -> &'static str {
+        "server-wins"
+    }
+
+    fn resolve(&self, _op: &Operation, _server: &Record) -> Option<Change> {
+        None
+    }
+}
+
+// Three-way merge against the base: fields only one side changed are kept from that side,
+// fields both changed go to the later writer. A remote edit beats a local delete
+pub struct FieldMerge;
+
+impl ConflictResolver for FieldMerge {
+    fn name(&self) -> &'static str {
+        "field-merge"
+    }
+
+    fn resolve(&self, op: &Operation, server: &Record) -> Option<Change> {
+        let Change::Upsert(patch) = &op.change else {
+            return None;
+        };
+        let merged: Fields = patch
+            .iter()
+            .filter(|(field, local)| {
+                let remote = server.fields.get(*field);
+                let remote_changed = remote != op.base_fields.get(*field);
+                remote != Some(*local) && (!remote_changed || op.at_ms > server.updated_at)
+            })
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect();
+        (!merged.is_empty()).then_some(Change::Upsert(merged))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub applied: usize,
+    pub duplicates: usize,
+    pub conflicts: usize,
+    pub kept_server: usize,
+    pub rejected: usize,
+    pub remaining: usize,
+}
+
+// Ordered, persisted queue of local changes; one JSON operation per line
+pub struct Outbox {
+    path: PathBuf,
+    pending: Vec<Operation>,
+    dead_letters: Vec<(Operation, String)>,
+    next_seq: u64,
+}
+
+impl Outbox {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let pending = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    serde_json::from_str(line)
+                        .ok()
+                        .and_then(|value| Operation::from_json(&value))
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("bad outbox entry: {}", line)))
+                })
+                .collect::<io::Result<Vec<_>>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let next_seq = pending.iter().map(|op| op.seq + 1).max().unwrap_or(1);
+        Ok(Self { path: path.to_path_buf(), pending, dead_letters: Vec::new(), next_seq })
+    }
+
+    pub fn pending(&self) -> &[Operation] {
+        &self.pending
+    }
+
+    pub fn dead_letters(&self) -> &[(Operation, String)] {
+        &self.dead_letters
+    }
+
+    // `base` is the client's cached copy of the record, None when creating it
+    pub fn enqueue(
+        &mut self,
+        collection: &str,
+        record_id: &str,
+        change: Change,
+        base: Option<&Record>,
+        at: Duration,
+    ) -> io::Result<String> {
+        let op = Operation {
+            seq: self.next_seq,
+            idempotency_key: uuid::Uuid::new_v4().to_string(),
+            collection: collection.to_string(),
+            record_id: record_id.to_string(),
+            change,
+            base_version: base.map(|record| record.version),
+            base_fields: base.map(|record| record.fields.clone()).unwrap_or_default(),
+            at_ms: at.as_millis() as u64,
+        };
+        self.next_seq += 1;
+        let key = op.idempotency_key.clone();
+        self.pending.push(op);
+        self.persist()?;
+        Ok(key)
+    }
+
+    // Write to a temp file and rename so a crash never leaves a half-written outbox
+    fn persist(&self) -> io::Result<()> {
+        let mut contents = String::new();
+        for op in &self.pending {
+            contents.push_str(&op.to_json().to_string());
+            contents.push('\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    // Replays operations in order. A transient failure stops the sync so later operations never
+    // overtake earlier ones; rejected operations are moved to the dead letters
+    pub fn sync(&mut self, target: &mut dyn SyncTarget, resolver: &dyn ConflictResolver) -> io::Result<SyncReport> {
+        let mut report = SyncReport::default();
+
+        while let Some(op) = self.pending.first().cloned() {
+            let outcome = match target.apply(&op) {
+                Err(SyncError::Conflict { current }) => {
+                    report.conflicts += 1;
+                    match resolver.resolve(&op, &current) {
+                        Some(change) => {
+                            let resolved = Operation {
+                                idempotency_key: format!("{}:resolved", op.idempotency_key),
+                                change,
+                                base_version: Some(current.version),
+                                ..op.clone()
+                            };
+                            target.apply(&resolved).map(Some)
+                        }
+                        None => {
+                            report.kept_server += 1;
+                            Ok(None)
+                        }
+                    }
+                }
+                other => other.map(Some),
+            };
+
+            match outcome {
+                Ok(applied) => {
+                    if let Some(applied) = applied {
+                        if applied.duplicate {
+                            report.duplicates += 1;
+                        } else {
+                            report.applied += 1;
+                        }
+                        self.rebase(&op, applied.version);
+                    }
+                }
+                Err(SyncError::Unavailable) => break,
+                Err(SyncError::Rejected(reason)) => {
+                    report.rejected += 1;
+                    self.dead_letters.push((op.clone(), reason));
+                }
+                // The record changed again while resolving; retry on the next sync
+                Err(SyncError::Conflict { .. }) => break,
+            }
+            self.pending.remove(0);
+            self.persist()?;
+        }
+
+        report.remaining = self.pending.len();
+        Ok(report)
+    }
+
+    // Later offline edits of the same record were based on the version this one replaced
+    fn rebase(&mut self, done: &Operation, version: u64) {
+        for op in self.pending.iter_mut().skip(1) {
+            if op.collection == done.collection && op.record_id == done.record_id {
+                op.base_version = Some(version);
+                op.base_fields = done.local_fields();
+            }
+        }
+    }
+}
+
+// In-process sync backend with idempotency keys, optimistic versioning and fault injection
+#[derive(Default)]
+pub struct MockSyncServer {
+    records: HashMap<(String, String), Record>,
+    applied: HashMap<String, Applied>,
+    // Idempotency keys in the order they were written
+    pub writes: Vec<String>,
+    available_for: Option<usize>,
+    drop_next_ack: bool,
+}
+
+impl MockSyncServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, collection: &str, id: &str) -> Option<&Record> {
+        self.records.get(&(collection.to_string(), id.to_string()))
+    }
+
+    pub fn records(&self, collection: &str) -> usize {
+        self.records.keys().filter(|(c, _)| c == collection).count()
+    }
+
+    // A write from another device
+    pub fn remote_write(&mut self, collection: &str, id: &str, patch: Fields, at: Duration) -> &Record {
+        let record = self.records.entry((collection.to_string(), id.to_string())).or_insert(Record {
+            fields: Fields::new(),
+            version: 0,
+            updated_at: 0,
+            deleted: false,
+        });
+        record.fields.extend(patch);
+        record.version += 1;
+        record.updated_at = at.as_millis() as u64;
+        record.deleted = false;
+        record
+    }
+
+    // Accept `count` more writes, then answer Unavailable until `recover`
+    pub fn fail_after(&mut self, count: usize) {
+        self.available_for = Some(count);
+    }
+
+    pub fn recover(&mut self) {
+        self.available_for = None;
+    }
+
+    // Apply the next write but lose the response, as on a dropped connection
+    pub fn drop_next_ack(&mut self) {
+        self.drop_next_ack = true;
+    }
+}
+
+impl SyncTarget for MockSyncServer {
+    fn apply(&mut self, op: &Operation) -> Result<Applied, SyncError> {
+        if let Some(applied) = self.applied.get(&op.idempotency_key) {
+            return Ok(Applied { duplicate: true, ..*applied });
+        }
+        match &mut self.available_for {
+            Some(0) => return Err(SyncError::Unavailable),
+            Some(left) => *left -= 1,
+            None => {}
+        }
+        if op.collection.is_empty() || op.record_id.is_empty() {
+            return Err(SyncError::Rejected("collection and id are required".to_string()));
+        }
+
+        let key = (op.collection.clone(), op.record_id.clone());
+        let current = self.records.get(&key);
+        if current.map(|record| record.version) != op.base_version {
+            return match current {
+                Some(record) => Err(SyncError::Conflict { current: record.clone() }),
+                None => Err(SyncError::Rejected(format!("{}/{} does not exist", op.collection, op.record_id))),
+            };
+        }
+
+        let record = self.records.entry(key).or_insert(Record {
+            fields: Fields::new(),
+            version: 0,
+            updated_at: 0,
+            deleted: false,
+        });
+        match &op.change {
+            Change::Upsert(patch) => {
+                record.fields.extend(patch.clone());
+                record.deleted = false;
+            }
+            Change::Delete => {
+                record.fields.clear();
+                record.deleted = true;
+            }
+        }
+        record.version += 1;
+        record.updated_at = op.at_ms;
+
+        let applied = Applied { version: record.version, duplicate: false };
+        self.applied.insert(op.idempotency_key.clone(), applied);
+        self.writes.push(op.idempotency_key.clone());
+
+        if self.drop_next_ack {
+            self.drop_next_ack = false;
+            return Err(SyncError::Unavailable);
+        }
+        Ok(applied)
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::backoff::BackoffPolicy;
+
+// FCM rejects data payloads above 4KB
+pub const MAX_PAYLOAD_BYTES: usize = 4096;
+// FCM keeps undelivered messages for four weeks by default
+pub const DEFAULT_TTL: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GatewayStats {
+    pub accepted: usize,
+    pub delivered: usize,
+    pub expired: usize,
+    pub collapsed: usize,
+    pub rejected: usize,
+}
+
+struct Pending {
+    id: u64,
+    title: String,
+    body: String,
+    collapse_key: Option<String>,
+    // None for TTL 0 messages handed straight to a waiting device
+    expires_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Device {
+    queue: Vec<Pending>,
+    waiting: usize,
+}
+
+#[derive(Default)]
+struct GatewayState {
+    devices: HashMap<String, Device>,
+    next_id: u64,
+    fail_next: usize,
+    retry_after: Option<Duration>,
+    stats: GatewayStats,
+}
+
+type Shared = Arc<(Mutex<GatewayState>, Condvar)>;
+
+// FCM/APNs-like HTTP API on 127.0.0.1:
+//   POST   /v1/register           {"app"}                                  -> {"token"}
+//   DELETE /v1/register/<token>
+//   POST   /v1/send               {"token","title","body","ttl_ms"?,"collapse_key"?} -> {"message_id"}
+//   GET    /v1/receive/<token>?wait_ms=N  long poll                        -> [{"message_id","title","body","collapse_key"}]
+pub struct PushGateway {
+    addr: SocketAddr,
+    shared: Shared,
+    shutdown: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl PushGateway {
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shared: Shared = Arc::new((Mutex::new(GatewayState::default()), Condvar::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let shared = Arc::clone(&shared);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let shared = Arc::clone(&shared);
+                    thread::spawn(move || {
+                        let _ = serve(stream, &shared);
+                    });
+                }
+            })
+        };
+
+        Ok(Self { addr, shared, shutdown, acceptor: Some(acceptor) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stats(&self) -> GatewayStats {
+        self.shared.0.lock().unwrap().stats
+    }
+
+    // The next `count` sends get 503, optionally with a Retry-After header
+    pub fn fail_next(&self, count: usize, retry_after: Option<Duration>) {
+        let mut state = self.shared.0.lock().unwrap();
+        state.fail_next = count;
+        state.retry_after = retry_after;
+    }
+}
+
+impl Drop for PushGateway {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the blocking accept
+        let _ = TcpStream::connect(self.addr);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((_, value)) = header.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("content-length")) {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (status, headers, response) = route(method, target, &body, shared);
+    let payload = response.to_string();
+    let mut writer = stream;
+    write!(writer, "HTTP/1.1 {} {}\r\n", status, reason(status))?;
+    for (name, value) in headers {
+        write!(writer, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        writer,
+        "Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        payload.len(),
+        payload
+    )?;
+    writer.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+fn error(status: u16, code: &str, message: &str) -> (u16, Vec<(String, String)>, Value) {
+    (status, Vec::new(), json!({ "error": code, "message": message }))
+}
+
+fn route(method: &str, target: &str, body: &[u8], shared: &Shared) -> (u16, Vec<(String, String)>, Value) {
+    let (lock, wakeup) = &**shared;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let request: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+
+    match (method, path) {
+        ("POST", "/v1/register") => {
+            if request["app"].as_str().is_none_or(str::is_empty) {
+                return error(400, "INVALID_ARGUMENT", "app is required");
+            }
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            lock.lock().unwrap().devices.insert(token.clone(), Device::default());
+            (200, Vec::new(), json!({ "token": token }))
+        }
+        ("DELETE", path) if path.starts_with("/v1/register/") => {
+            let token = &path["/v1/register/".len()..];
+            match lock.lock().unwrap().devices.remove(token) {
+                Some(_) => {
+                    wakeup.notify_all();
+                    (200, Vec::new(), json!({}))
+                }
+                None => error(404, "UNREGISTERED", "unknown token"),
+            }
+        }
+        ("POST", "/v1/send") => {
+            let mut state = lock.lock().unwrap();
+            if state.fail_next > 0 {
+                state.fail_next -= 1;
+                let headers = state
+                    .retry_after
+                    .map(|delay| vec![("Retry-After".to_string(), delay.as_secs().to_string())])
+                    .unwrap_or_default();
+                return (503, headers, json!({ "error": "UNAVAILABLE", "message": "try again later" }));
+            }
+            let (Some(token), Some(title)) = (request["token"].as_str(), request["title"].as_str()) else {
+                state.stats.rejected += 1;
+                return error(400, "INVALID_ARGUMENT", "token and title are required");
+            };
+            let text = request["body"].as_str().unwrap_or("");
+            if title.is_empty() || title.len() + text.len() > MAX_PAYLOAD_BYTES {
+                state.stats.rejected += 1;
+                return error(400, "INVALID_ARGUMENT", "empty title or payload too large");
+            }
+            let ttl = request["ttl_ms"].as_u64().map_or(DEFAULT_TTL, Duration::from_millis);
+            let collapse_key = request["collapse_key"].as_str().map(str::to_string);
+
+            state.next_id += 1;
+            let id = state.next_id;
+            let state = &mut *state;
+            let Some(device) = state.devices.get_mut(token) else {
+                state.stats.rejected += 1;
+                return error(404, "UNREGISTERED", "unknown token");
+            };
+            state.stats.accepted += 1;
+
+            // TTL 0 is "now or never": delivered only to a device that is connected right now
+            let expires_at = if ttl.is_zero() {
+                if device.waiting == 0 {
+                    state.stats.expired += 1;
+                    return (200, Vec::new(), json!({ "message_id": id }));
+                }
+                None
+            } else {
+                Some(Instant::now() + ttl)
+            };
+            // A newer message with the same collapse key replaces the undelivered one
+            if let Some(key) = &collapse_key {
+                let before = device.queue.len();
+                device.queue.retain(|p| p.collapse_key.as_ref() != Some(key));
+                state.stats.collapsed += before - device.queue.len();
+            }
+            device.queue.push(Pending {
+                id,
+                title: title.to_string(),
+                body: text.to_string(),
+                collapse_key,
+                expires_at,
+            });
+            wakeup.notify_all();
+            (200, Vec::new(), json!({ "message_id": id }))
+        }
+        ("GET", path) if path.starts_with("/v1/receive/") => {
+            let token = &path["/v1/receive/".len()..];
+            let wait = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("wait_ms="))
+                .and_then(|ms| ms.parse().ok())
+                .map_or(Duration::ZERO, Duration::from_millis);
+            let deadline = Instant::now() + wait;
+
+            let mut state = lock.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                let state_ref = &mut *state;
+                let Some(device) = state_ref.devices.get_mut(token) else {
+                    return error(404, "UNREGISTERED", "unknown token");
+                };
+                let before = device.queue.len();
+                device.queue.retain(|p| p.expires_at.is_none_or(|at| at > now));
+                state_ref.stats.expired += before - device.queue.len();
+
+                if !device.queue.is_empty() || now >= deadline {
+                    let messages: Vec<Value> = device
+                        .queue
+                        .drain(..)
+                        .map(|p| {
+                            json!({
+                                "message_id": p.id,
+                                "title": p.title,
+                                "body": p.body,
+                                "collapse_key": p.collapse_key,
+                            })
+                        })
+                        .collect();
+                    state_ref.stats.delivered += messages.len();
+                    return (200, Vec::new(), Value::Array(messages));
+                }
+
+                device.waiting += 1;
+                state = wakeup.wait_timeout(state, deadline - now).unwrap().0;
+                if let Some(device) = state.devices.get_mut(token) {
+                    device.waiting -= 1;
+                }
+            }
+        }
+        _ => error(404, "NOT_FOUND", "no such endpoint"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushError {
+    InvalidArgument(String),
+    Unregistered,
+    Unavailable { retry_after: Option<Duration> },
+    Transport(String),
+}
+
+impl fmt::Display for PushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::InvalidArgument(message) => write!(f, "invalid argument: {}", message),
+            PushError::Unregistered => write!(f, "device token is not registered"),
+            PushError::Unavailable { retry_after } => write!(f, "gateway unavailable, retry after {:?}", retry_after),
+            PushError::Transport(message) => write!(f, "transport error: {}", message),
+        }
+    }
+}
+
+struct HttpResponse {
+    status: u16,
+    retry_after: Option<Duration>,
+    body: Value,
+}
+
+fn http(addr: SocketAddr, method: &str, path: &str, body: Option<&Value>) -> Result<HttpResponse, PushError> {
+    let transport = |e: io::Error| PushError::Transport(e.to_string());
+    let mut stream = TcpStream::connect(addr).map_err(transport)?;
+    let payload = body.map_or(String::new(), Value::to_string);
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        addr,
+        payload.len(),
+        payload
+    )
+    .map_err(transport)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(transport)?;
+    let (head, body) = response.split_once("\r\n\r\n").ok_or(PushError::Transport("truncated response".to_string()))?;
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or(PushError::Transport("bad status line".to_string()))?;
+    let retry_after = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .map(Duration::from_secs);
+
+    Ok(HttpResponse {
+        status,
+        retry_after,
+        body: serde_json::from_str(body).unwrap_or(Value::Null),
+    })
+}
+
+fn check(response: HttpResponse) -> Result<Value, PushError> {
+    match response.status {
+        200 => Ok(response.body),
+        400 => Err(PushError::InvalidArgument(response.body["message"].as_str().unwrap_or("").to_string())),
+        404 => Err(PushError::Unregistered),
+        503 => Err(PushError::Unavailable { retry_after: response.retry_after }),
+        status => Err(PushError::Transport(format!("unexpected status {}", status))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushMessage {
+    pub token: String,
+    pub title: String,
+    pub body: String,
+    pub ttl: Option<Duration>,
+    pub collapse_key: Option<String>,
+}
+
+impl PushMessage {
+    pub fn new(token: &str, title: &str, body: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            ttl: None,
+            collapse_key: None,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_collapse_key(mut self, key: &str) -> Self {
+        self.collapse_key = Some(key.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendReceipt {
+    pub message_id: u64,
+    pub attempts: u32,
+}
+
+// App server side: retries 503s, honouring Retry-After and backing off otherwise
+pub struct PushSender {
+    addr: SocketAddr,
+    backoff: BackoffPolicy,
+    max_attempts: u32,
+}
+
+impl PushSender {
+    pub fn new(addr: SocketAddr, backoff: BackoffPolicy, max_attempts: u32) -> Self {
+        Self { addr, backoff, max_attempts: max_attempts.max(1) }
+    }
+
+    pub fn send(&self, message: &PushMessage) -> Result<SendReceipt, PushError> {
+        let request = json!({
+            "token": message.token,
+            "title": message.title,
+            "body": message.body,
+            "ttl_ms": message.ttl.map(|ttl| ttl.as_millis() as u64),
+            "collapse_key": message.collapse_key,
+        });
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match http(self.addr, "POST", "/v1/send", Some(&request)).and_then(check) {
+                Ok(response) => {
+                    let message_id = response["message_id"]
+                        .as_u64()
+                        .ok_or(PushError::Transport("response without message_id".to_string()))?;
+                    return Ok(SendReceipt { message_id, attempts });
+                }
+                Err(PushError::Unavailable { retry_after }) if attempts < self.max_attempts => {
+                    thread::sleep(retry_after.unwrap_or_else(|| self.backoff.delay(attempts)));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMessage {
+    pub message_id: u64,
+    pub title: String,
+    pub body: String,
+    pub collapse_key: Option<String>,
+}
+
+// Device side: holds the token and long-polls the gateway
+pub struct PushReceiver {
+    addr: SocketAddr,
+    token: String,
+}
+
+impl PushReceiver {
+    pub fn register(addr: SocketAddr, app: &str) -> Result<Self, PushError> {
+        let response = check(http(addr, "POST", "/v1/register", Some(&json!({ "app": app })))?)?;
+        let token = response["token"]
+            .as_str()
+            .ok_or(PushError::Transport("response without token".to_string()))?;
+        Ok(Self { addr, token: token.to_string() })
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    // Waits up to `wait` for messages; an empty result means none arrived
+    pub fn poll(&self, wait: Duration) -> Result<Vec<ReceivedMessage>, PushError> {
+        let path = format!("/v1/receive/{}?wait_ms={}", self.token, wait.as_millis());
+        let response = check(http(self.addr, "GET", &path, None)?)?;
+        let messages = response.as_array().ok_or(PushError::Transport("expected a message list".to_string()))?;
+        Ok(messages
+            .iter()
+            .map(|m| ReceivedMessage {
+                message_id: m["message_id"].as_u64().unwrap_or(0),
+                title: m["title"].as_str().unwrap_or("").to_string(),
+                body: m["body"].as_str().unwrap_or("").to_string(),
+                collapse_key: m["collapse_key"].as_str().map(str::to_string),
+            })
+            .collect())
+    }
+
+    pub fn unregister(self) -> Result<(), PushError> {
+        check(http(self.addr, "DELETE", &format!("/v1/register/{}", self.token), None)?).map(|_| ())
+    }
+}
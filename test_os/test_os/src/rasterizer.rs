@@ -0,0 +1,431 @@
+use std::ops::AddAssign;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex {
+    // Pixel coordinates, z in 0..1 with smaller values closer to the viewer
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub u: f32,
+    pub v: f32,
+    pub color: [f32; 4],
+}
+
+impl Vertex {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z, u: 0.0, v: 0.0, color: [1.0; 4] }
+    }
+
+    pub fn with_uv(mut self, u: f32, v: f32) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blend {
+    Replace,
+    // Source over destination, using the fragment alpha
+    Alpha,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrawState<'a> {
+    pub texture: Option<&'a Texture>,
+    pub blend: Blend,
+    pub depth_test: bool,
+}
+
+impl<'a> DrawState<'a> {
+    pub fn opaque() -> Self {
+        Self { texture: None, blend: Blend::Replace, depth_test: true }
+    }
+
+    pub fn with_texture(mut self, texture: &'a Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    // Blended geometry is depth tested but does not write depth, like a transparent pass
+    pub fn with_alpha_blend(mut self) -> Self {
+        self.blend = Blend::Alpha;
+        self
+    }
+
+    pub fn without_depth_test(mut self) -> Self {
+        self.depth_test = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RasterStats {
+    pub triangles: u64,
+    // Degenerate or entirely off screen
+    pub culled: u64,
+    pub fragments: u64,
+    pub depth_rejected: u64,
+    pub blended: u64,
+    pub texel_fetches: u64,
+}
+
+impl RasterStats {
+    // Framebuffer and texture bytes read or written, the figure fill-rate bound GPUs are limited by
+    pub fn memory_traffic(&self) -> u64 {
+        let depth_reads = (self.fragments + self.depth_rejected) * 4;
+        let writes = self.fragments * 8;
+        depth_reads + writes + self.blended * 4 + self.texel_fetches * 4
+    }
+}
+
+impl AddAssign for RasterStats {
+    fn add_assign(&mut self, other: Self) {
+        self.triangles += other.triangles;
+        self.culled += other.culled;
+        self.fragments += other.fragments;
+        self.depth_rejected += other.depth_rejected;
+        self.blended += other.blended;
+        self.texel_fetches += other.texel_fetches;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    texels: Vec<[u8; 4]>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, texels: Vec<[u8; 4]>) -> Self {
+        assert_eq!(texels.len(), width * height, "texel count does not match {}x{}", width, height);
+        Self { width, height, texels }
+    }
+
+    pub fn checkerboard(size: usize, cells: usize, a: [u8; 4], b: [u8; 4]) -> Self {
+        let cell = (size / cells.max(1)).max(1);
+        let texels = (0..size * size)
+            .map(|i| if (i % size / cell + i / size / cell).is_multiple_of(2) { a } else { b })
+            .collect();
+        Self::new(size, size, texels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.texels.len() * 4
+    }
+
+    // Bilinear filtering with repeat addressing, texel centres at half-integer coordinates
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0.0; 4];
+        }
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let x0 = (x.floor() as isize).rem_euclid(self.width as isize) as usize;
+        let y0 = (y.floor() as isize).rem_euclid(self.height as isize) as usize;
+        let x1 = if x0 + 1 == self.width { 0 } else { x0 + 1 };
+        let y1 = if y0 + 1 == self.height { 0 } else { y0 + 1 };
+
+        let (row0, row1) = (y0 * self.width, y1 * self.width);
+        let (a, b) = (&self.texels[row0 + x0], &self.texels[row0 + x1]);
+        let (c, d) = (&self.texels[row1 + x0], &self.texels[row1 + x1]);
+        let mut out = [0.0; 4];
+        for i in 0..4 {
+            let top = a[i] as f32 + (b[i] as f32 - a[i] as f32) * fx;
+            let bottom = c[i] as f32 + (d[i] as f32 - c[i] as f32) * fx;
+            out[i] = (top + (bottom - top) * fy) / 255.0;
+        }
+        out
+    }
+}
+
+fn to_unorm(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+// Vertex positions are snapped to 1/16 pixel so edge functions are exact integers: two triangles
+// sharing an edge then agree on every pixel along it
+const SUBPIXEL_BITS: u32 = 4;
+const SUBPIXEL: f32 = (1 << SUBPIXEL_BITS) as f32;
+
+fn snap(v: &Vertex) -> (i64, i64) {
+    ((v.x * SUBPIXEL).round() as i64, (v.y * SUBPIXEL).round() as i64)
+}
+
+// Edge function: positive when p is on the inner side of a->b for a triangle with positive area
+fn edge(a: (i64, i64), b: (i64, i64), p: (i64, i64)) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+// Top-left fill rule: pixels exactly on a shared edge belong to one of the two triangles only,
+// so edges that are not top or left need a strictly positive edge function
+fn fill_bias(a: (i64, i64), b: (i64, i64)) -> i64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    if (dy == 0 && dx > 0) || dy < 0 {
+        0
+    } else {
+        1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    // RGBA8, row major, no padding
+    color: Vec<u8>,
+    depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, color: vec![0; width * height * 4], depth: vec![1.0; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.color
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.color.len() + self.depth.len() * 4
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.color[i], self.color[i + 1], self.color[i + 2], self.color[i + 3]]
+    }
+
+    pub fn clear(&mut self, color: [u8; 4]) {
+        for pixel in self.color.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+        self.depth.fill(1.0);
+    }
+
+    pub fn draw_triangle(&mut self, vertices: &[Vertex; 3], state: &DrawState) -> RasterStats {
+        let mut stats = RasterStats { triangles: 1, ..RasterStats::default() };
+        let [v0, mut v1, mut v2] = *vertices;
+
+        // Setup: both windings are drawn, so flip clockwise triangles instead of culling them
+        let (p0, mut p1, mut p2) = (snap(&v0), snap(&v1), snap(&v2));
+        let mut area = edge(p0, p1, p2);
+        if area < 0 {
+            std::mem::swap(&mut v1, &mut v2);
+            std::mem::swap(&mut p1, &mut p2);
+            area = -area;
+        }
+        let to_pixel = |c: i64, limit: usize| (c.max(0) as usize).div_ceil(1 << SUBPIXEL_BITS).min(limit);
+        let min_x = to_pixel(p0.0.min(p1.0).min(p2.0) - (1 << SUBPIXEL_BITS), self.width);
+        let min_y = to_pixel(p0.1.min(p1.1).min(p2.1) - (1 << SUBPIXEL_BITS), self.height);
+        let max_x = to_pixel(p0.0.max(p1.0).max(p2.0), self.width);
+        let max_y = to_pixel(p0.1.max(p1.1).max(p2.1), self.height);
+        if area == 0 || min_x >= max_x || min_y >= max_y {
+            stats.culled = 1;
+            return stats;
+        }
+
+        let edges = [(p1, p2), (p2, p0), (p0, p1)];
+        let bias = edges.map(|(a, b)| fill_bias(a, b));
+        // Per-pixel increments of each edge function along x and y
+        let step_x = edges.map(|(a, b)| (a.1 - b.1) << SUBPIXEL_BITS);
+        let step_y = edges.map(|(a, b)| (b.0 - a.0) << SUBPIXEL_BITS);
+        // Sample at pixel centres
+        let half = 1 << (SUBPIXEL_BITS - 1);
+        let origin = (((min_x as i64) << SUBPIXEL_BITS) + half, ((min_y as i64) << SUBPIXEL_BITS) + half);
+        let mut row = edges.map(|(a, b)| edge(a, b, origin));
+        let inv_area = 1.0 / area as f32;
+
+        for y in min_y..max_y {
+            let [mut w0, mut w1, mut w2] = row;
+            for x in min_x..max_x {
+                if w0 >= bias[0] && w1 >= bias[1] && w2 >= bias[2] {
+                    let l = [w0 as f32 * inv_area, w1 as f32 * inv_area, w2 as f32 * inv_area];
+                    self.shade(x, y, [&v0, &v1, &v2], l, state, &mut stats);
+                }
+                w0 += step_x[0];
+                w1 += step_x[1];
+                w2 += step_x[2];
+            }
+            for i in 0..3 {
+                row[i] += step_y[i];
+            }
+        }
+        stats
+    }
+
+    fn shade(&mut self, x: usize, y: usize, v: [&Vertex; 3], l: [f32; 3], state: &DrawState, stats: &mut RasterStats) {
+        let index = y * self.width + x;
+        let z = l[0] * v[0].z + l[1] * v[1].z + l[2] * v[2].z;
+        if state.depth_test && z >= self.depth[index] {
+            stats.depth_rejected += 1;
+            return;
+        }
+
+        let mut color = [0.0; 4];
+        for (c, out) in color.iter_mut().enumerate() {
+            *out = l[0] * v[0].color[c] + l[1] * v[1].color[c] + l[2] * v[2].color[c];
+        }
+        if let Some(texture) = state.texture {
+            let u = l[0] * v[0].u + l[1] * v[1].u + l[2] * v[2].u;
+            let t = l[0] * v[0].v + l[1] * v[1].v + l[2] * v[2].v;
+            let texel = texture.sample(u, t);
+            for c in 0..4 {
+                color[c] *= texel[c];
+            }
+            stats.texel_fetches += 4;
+        }
+
+        let out = &mut self.color[index * 4..index * 4 + 4];
+        match state.blend {
+            Blend::Replace => {
+                for c in 0..4 {
+                    out[c] = to_unorm(color[c]);
+                }
+                if state.depth_test {
+                    self.depth[index] = z;
+                }
+            }
+            Blend::Alpha => {
+                let alpha = color[3].clamp(0.0, 1.0);
+                for c in 0..3 {
+                    let dst = out[c] as f32 / 255.0;
+                    out[c] = to_unorm(color[c] * alpha + dst * (1.0 - alpha));
+                }
+                let dst = out[3] as f32 / 255.0;
+                out[3] = to_unorm(alpha + dst * (1.0 - alpha));
+                stats.blended += 1;
+            }
+        }
+        stats.fragments += 1;
+    }
+
+    pub fn draw_triangles(&mut self, triangles: &[[Vertex; 3]], state: &DrawState) -> RasterStats {
+        let mut stats = RasterStats::default();
+        for triangle in triangles {
+            stats += self.draw_triangle(triangle, state);
+        }
+        stats
+    }
+}
+
+// Axis-aligned rectangle as two triangles sharing a diagonal, uv covering the texture `repeat` times
+pub fn quad(x: f32, y: f32, width: f32, height: f32, z: f32, color: [f32; 4], repeat: f32) -> [[Vertex; 3]; 2] {
+    let corner = |dx: f32, dy: f32| {
+        Vertex::new(x + dx * width, y + dy * height, z).with_uv(dx * repeat, dy * repeat).with_color(color)
+    };
+    let (a, b, c, d) = (corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0));
+    [[a, b, c], [a, c, d]]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    // Separable box filter with the given radius
+    BoxBlur(usize),
+    // Sobel gradient magnitude of the luminance
+    EdgeDetect,
+    Grayscale,
+}
+
+fn luminance(pixel: &[u8]) -> f32 {
+    0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32
+}
+
+// Runs a full-screen compute pass from `src` into `dst`, returning the number of pixels written
+pub fn dispatch(kernel: Kernel, src: &Framebuffer, dst: &mut Framebuffer) -> u64 {
+    assert_eq!((src.width, src.height), (dst.width, dst.height), "compute pass needs equally sized targets");
+    let (width, height) = (src.width, src.height);
+    if width == 0 || height == 0 {
+        return 0;
+    }
+    match kernel {
+        Kernel::Grayscale => {
+            for (out, pixel) in dst.color.chunks_exact_mut(4).zip(src.color.chunks_exact(4)) {
+                let y = to_unorm(luminance(pixel) / 255.0);
+                out.copy_from_slice(&[y, y, y, pixel[3]]);
+            }
+        }
+        Kernel::EdgeDetect => {
+            let luma: Vec<f32> = src.color.chunks_exact(4).map(luminance).collect();
+            let at = |x: usize, y: usize| luma[y.min(height - 1) * width + x.min(width - 1)];
+            for y in 0..height {
+                for x in 0..width {
+                    let (l, u) = (x.saturating_sub(1), y.saturating_sub(1));
+                    let gx = at(x + 1, u) + 2.0 * at(x + 1, y) + at(x + 1, y + 1) - at(l, u) - 2.0 * at(l, y) - at(l, y + 1);
+                    let gy = at(l, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1) - at(l, u) - 2.0 * at(x, u) - at(x + 1, u);
+                    let magnitude = to_unorm((gx * gx + gy * gy).sqrt() / (4.0 * 255.0));
+                    let i = (y * width + x) * 4;
+                    dst.color[i..i + 4].copy_from_slice(&[magnitude, magnitude, magnitude, 255]);
+                }
+            }
+        }
+        Kernel::BoxBlur(radius) => {
+            // Horizontal pass into a scratch buffer, vertical pass into dst, edges clamped
+            let taps = (2 * radius + 1) as u32;
+            let stride = width * 4;
+            let mut scratch = vec![0u8; src.color.len()];
+            for (row, out) in src.color.chunks_exact(stride).zip(scratch.chunks_exact_mut(stride)) {
+                blur_line(row, out, width, radius, taps);
+            }
+            // Vertical pass walks rows in order with one running sum per column, to stay cache friendly
+            let clamped = |y: isize| y.clamp(0, height as isize - 1) as usize * stride;
+            let mut sums: Vec<u32> = vec![0; stride];
+            for y in -(radius as isize)..=radius as isize {
+                let row = clamped(y);
+                for (sum, &value) in sums.iter_mut().zip(&scratch[row..row + stride]) {
+                    *sum += value as u32;
+                }
+            }
+            for y in 0..height {
+                let (entering, leaving) = (clamped(y as isize + radius as isize + 1), clamped(y as isize - radius as isize));
+                let out = &mut dst.color[y * stride..(y + 1) * stride];
+                for i in 0..stride {
+                    out[i] = ((sums[i] + taps / 2) / taps) as u8;
+                    sums[i] = sums[i] + scratch[entering + i] as u32 - scratch[leaving + i] as u32;
+                }
+            }
+        }
+    }
+    (width * height) as u64
+}
+
+// Sliding-window box filter over a line of RGBA pixels
+fn blur_line(input: &[u8], output: &mut [u8], len: usize, radius: usize, taps: u32) {
+    let clamped = |i: isize| i.clamp(0, len as isize - 1) as usize * 4;
+    let mut sums = [0u32; 4];
+    for i in -(radius as isize)..=radius as isize {
+        let p = clamped(i);
+        for c in 0..4 {
+            sums[c] += input[p + c] as u32;
+        }
+    }
+    for i in 0..len {
+        let (entering, leaving) = (clamped((i + radius + 1) as isize), clamped(i as isize - radius as isize));
+        for c in 0..4 {
+            output[i * 4 + c] = ((sums[c] + taps / 2) / taps) as u8;
+            sums[c] = sums[c] + input[entering + c] as u32 - input[leaving + c] as u32;
+        }
+    }
+}
@@ -5,6 +5,22 @@ use std::path::PathBuf;
 use std::sync::{Arc, Barrier};
 use std::thread;
 
+mod clock;
+mod getevent;
+mod input_dispatch;
+mod sensor_timing;
+#[cfg(any(target_os = "android", target_os = "ios"))]
+mod stress_workloads;
+mod touch;
+
+use clock::{Clock, SimulatedClock};
+#[cfg(any(target_os = "android", target_os = "ios"))]
+use clock::RealClock;
+use getevent::GeteventConfig;
+use input_dispatch::{DispatchReport, InputDispatcher};
+use sensor_timing::RateTolerance;
+#[cfg(any(target_os = "android", target_os = "ios"))]
+use stress_workloads::CpuStress;
 
 fn get_test_dir() -> PathBuf {
     #[cfg(target_os = "android")]
@@ -230,26 +246,58 @@ fn get_cpu_time() -> Duration {
 #[test]
 fn test_sensor_performance() {
    
-    let start = Instant::now();
-    
-    
-    let mut samples = 0;
-    let sample_duration = Duration::from_micros(16667); // 60Hz
-    
-    while start.elapsed() < Duration::from_secs(1) {
-        thread::sleep(sample_duration);
-        samples += 1;
+    let timestamps = record_sample_times(&RealClock::new(), Duration::from_micros(16667)); // 60Hz
+    let report = sensor_timing::analyze("sampling_loop", &timestamps, 60.0);
+    println!("Sensor sampling rate: {:.1} Hz", report.rate_hz);
+    print!("{}", report);
+    
+    // Те же ±5Hz, что и раньше, плюс ограничение на джиттер и пропуски
+    let tolerance = RateTolerance {
+        rate_error: 0.085,
+        max_jitter: Duration::from_millis(2),
+        max_drop_rate: 0.02,
+        ..RateTolerance::new(60.0)
+    };
+    if let Err(violations) = report.check(&tolerance) {
+        panic!("Unstable sensor sampling: {}", violations.join("; "));
     }
+}
+
+
+#[test]
+fn test_sensor_sampling_logic() {
     
-    let actual_fps = samples as f64 / start.elapsed().as_secs_f64();
-    println!("Sensor sampling rate: {:.1} Hz", actual_fps);
-    
+    let clock = SimulatedClock::new();
+    let timestamps = record_sample_times(&clock, Duration::from_micros(16667));
+    // Сенсор и хост читают одни и те же часы, так что дрейфа быть не может
+    let sync_points: Vec<(Duration, Duration)> = timestamps.iter().map(|&t| (t, t)).collect();
+    let report = sensor_timing::analyze_with_host("sampling_loop", &timestamps, &sync_points, 60.0);
     
     assert!(
-        actual_fps > 55.0 && actual_fps < 65.0,
-        "Unstable sensor sampling: {:.1} Hz",
-        actual_fps
+        (report.rate_hz - 60.0).abs() < 0.5,
+        "Sampling loop miscounts on an ideal clock: {:.1} Hz",
+        report.rate_hz
     );
+    assert_eq!(report.jitter, Duration::ZERO, "Ideal clock should have no jitter");
+    assert_eq!(report.dropped, 0);
+    assert_eq!(report.histogram.len(), 1, "All intervals should land in one bucket");
+    assert!(clock.now() >= Duration::from_secs(1), "Sampling window should cover a full second");
+    assert_eq!(report.drift_ppm, Some(0.0), "Shared clock should not drift");
+    if let Err(violations) = report.check(&RateTolerance::new(60.0)) {
+        panic!("Ideal sampling loop outside tolerance: {}", violations.join("; "));
+    }
+}
+
+fn record_sample_times(clock: &dyn Clock, sample_duration: Duration) -> Vec<Duration> {
+    let start = clock.now();
+    let mut timestamps = vec![Duration::ZERO];
+    
+    while clock.elapsed_since(start) < Duration::from_secs(1) {
+        clock.sleep(sample_duration);
+        timestamps.push(clock.elapsed_since(start));
+    }
+    
+    timestamps
 }
 
 
@@ -295,31 +343,145 @@ fn check_mobile_performance(test_name: &str, current: Duration, baseline: Durati
 #[cfg(any(target_os = "android", target_os = "ios"))]
 #[test]
 fn test_touch_latency() {
-    use std::time::{Instant, Duration};
     
+    let idle = run_input_dispatch(250);
+    print_dispatch_report("idle", &idle);
+    
+    assert_eq!(idle.latency.count, 250, "Every injected event should reach the UI thread");
+    assert_eq!(idle.out_of_order, 0, "Events dispatched out of order");
+    assert!(
+        idle.latency.p95 < Duration::from_millis(16),
+        "Touch latency p95 too high: {:?}",
+        idle.latency.p95
+    );
     
-    let mut total_latency = Duration::new(0, 0);
-    let mut events = 0;
+    // Та же нагрузка, что и в test_cpu_multi_threading_stress
+    let stress_threads = thread::available_parallelism().map_or(4, |n| n.get()) * 2;
+    let stress = CpuStress::start(stress_threads);
+    let loaded = run_input_dispatch(250);
+    let stress_ops = stress.stop();
+    print_dispatch_report("cpu_stress", &loaded);
+    println!("Stress operations during dispatch: {}", stress_ops);
     
-    for i in 0..100 {
-        let event_time = Instant::now();
-        
-        
-        thread::sleep(Duration::from_micros(1000)); 
-        let processed_time = Instant::now();
-        
-        total_latency += processed_time.duration_since(event_time);
-        events += 1;
+    assert_eq!(loaded.latency.count, 250, "Events lost under CPU stress");
+    assert_eq!(loaded.out_of_order, 0, "Events dispatched out of order under CPU stress");
+    assert!(
+        loaded.latency.p99 < Duration::from_millis(32),
+        "Touch latency p99 under CPU stress too high: {:?}",
+        loaded.latency.p99
+    );
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn run_input_dispatch(events: usize) -> DispatchReport {
+    // Swipes back and forth, one MOVE every 8ms as a 120Hz digitizer would report them
+    let mut stream = Vec::with_capacity(events);
+    let mut start = Duration::ZERO;
+    while stream.len() < events {
+        let swipe = touch::swipe_stream((100.0, 960.0), (980.0, 960.0), Duration::from_millis(200), start);
+        start = swipe.last().map_or(start, |e| e.time) + Duration::from_millis(8);
+        stream.extend(swipe);
     }
+    stream.truncate(events);
+    
+    dispatch_stream(stream)
+}
+
+fn dispatch_stream(stream: Vec<touch::MotionEvent>) -> DispatchReport {
+    let events = stream.len();
+    let dispatcher = InputDispatcher::start(|event| {
+        // hit-test + invalidate: немного реальной работы на UI-потоке
+        let mut acc = 0.0f32;
+        for p in &event.pointers {
+            for i in 0..200 {
+                acc += (p.x * i as f32).sin() * p.pressure;
+            }
+        }
+        std::hint::black_box(acc);
+    });
     
-    let avg_latency = total_latency / events;
-    println!("Average touch latency: {:?}", avg_latency);
+    let producer = dispatcher.spawn_producer(stream);
+    let injected = producer.join().expect("Producer thread panicked");
+    assert_eq!(injected, events, "Producer failed to inject every event");
     
+    dispatcher.finish()
+}
 
-    assert!(
-        avg_latency < Duration::from_millis(16),
-        "Touch latency too high: {:?}",
-        avg_latency
+fn print_dispatch_report(label: &str, report: &DispatchReport) {
+    let latency = &report.latency;
+    println!(
+        "[{}] touch latency over {} events: p50={:?} p95={:?} p99={:?} max={:?} mean={:?}",
+        label, latency.count, latency.p50, latency.p95, latency.p99, latency.max, latency.mean
     );
 }
 
+// Синтетические потоки из touch.rs через распознаватель, без устройства
+#[test]
+fn test_synthetic_gesture_streams() {
+    
+    let recognizer = touch::GestureRecognizer::default();
+    let streams = [
+        ("tap", touch::tap_stream(540.0, 960.0, Duration::ZERO)),
+        ("double_tap", touch::double_tap_stream(540.0, 960.0, Duration::ZERO)),
+        ("long_press", touch::long_press_stream(540.0, 960.0, Duration::ZERO)),
+        ("swipe", touch::swipe_stream((540.0, 1600.0), (540.0, 400.0), Duration::from_millis(180), Duration::ZERO)),
+        ("pinch", touch::pinch_stream((540.0, 960.0), 300.0, 700.0, Duration::from_millis(250), Duration::ZERO)),
+        ("multitouch", touch::multitouch_stream(3, (300.0, 960.0), Duration::ZERO)),
+    ];
+    for (expected, stream) in &streams {
+        let names: Vec<&str> = recognizer.recognize(stream).iter().map(|g| g.name()).collect();
+        assert_eq!(names, vec![*expected], "Synthetic {} stream misrecognized", expected);
+    }
+    
+    // Касание, отменённое системой (жест навигации, ладонь), не должно стать тапом
+    let mut cancelled = touch::tap_stream(540.0, 960.0, Duration::ZERO);
+    cancelled.last_mut().unwrap().action = touch::TouchAction::Cancel;
+    assert!(recognizer.recognize(&cancelled).is_empty(), "Cancelled stroke recognized as a gesture");
+}
+
+// Захваты `adb shell getevent -lt` с реальных устройств, имя начинается с ожидаемого жеста
+const GETEVENT_CAPTURES: &[(&str, &str)] = &[
+    ("pinch_out", include_str!("../captures/getevent/pinch_out.txt")),
+    ("swipe_up", include_str!("../captures/getevent/swipe_up.txt")),
+    ("tap", include_str!("../captures/getevent/tap.txt")),
+];
+
+#[test]
+fn test_getevent_capture_replay() {
+    let recognizer = touch::GestureRecognizer::default();
+    
+    for &(stem, log) in GETEVENT_CAPTURES {
+        let events = getevent::parse_getevent(log, GeteventConfig::default())
+            .unwrap_or_else(|e| panic!("Failed to parse capture: {}", e));
+        
+        let recognized = recognizer.recognize(&events);
+        println!("{}: {} motion events -> {:?}", stem, events.len(), recognized);
+        
+        assert_eq!(recognized.len(), 1, "Capture '{}' recognized as {:?}", stem, recognized);
+        assert!(
+            stem.starts_with(recognized[0].name()),
+            "Capture '{}' misclassified as {:?}",
+            stem,
+            recognized[0]
+        );
+        
+        let report = dispatch_stream(events.clone());
+        print_dispatch_report(stem, &report);
+        
+        assert_eq!(report.latency.count, events.len(), "Capture '{}' lost events in dispatch", stem);
+        assert_eq!(report.out_of_order, 0, "Capture '{}' dispatched out of order", stem);
+        assert!(
+            report.latency.p95 < Duration::from_millis(16),
+            "Capture '{}' latency p95 too high: {:?}",
+            stem,
+            report.latency.p95
+        );
+    }
+    
+    let repeat = "[  81306.481413] /dev/input/event0: EV_KEY       KEY_VOLUMEDOWN       REPEAT";
+    let held = getevent::parse_line(repeat).expect("Key autorepeat should parse").expect("Expected an event");
+    assert_eq!((held.code.as_str(), held.value), ("KEY_VOLUMEDOWN", 2));
+    
+    let broken = "[  81305.512413] /dev/input/event3: EV_ABS       ABS_MT_POSITION_X    zz";
+    assert!(getevent::parse_getevent(broken, GeteventConfig::default()).is_err());
+}
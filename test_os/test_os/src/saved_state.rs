@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+// Binder transaction buffer is 1MB per process; larger saves throw TransactionTooLargeException
+pub const DEFAULT_BUNDLE_LIMIT: usize = 1024 * 1024;
+
+const PARCEL_MAGIC: &[u8; 4] = b"BNDL";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    StrMap(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleError {
+    TransactionTooLarge { size: usize, limit: usize },
+    Corrupt(String),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BundleError::TransactionTooLarge { size, limit } => {
+                write!(f, "saved state too large: {} bytes > {} bytes", size, limit)
+            }
+            BundleError::Corrupt(reason) => write!(f, "corrupt saved state parcel: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedStateBundle {
+    entries: BTreeMap<String, BundleValue>,
+    limit: usize,
+}
+
+impl Default for SavedStateBundle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SavedStateBundle {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_BUNDLE_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn put(&mut self, key: &str, value: BundleValue) {
+        self.entries.insert(key.to_string(), value);
+    }
+
+    pub fn put_bool(&mut self, key: &str, value: bool) {
+        self.put(key, BundleValue::Bool(value));
+    }
+
+    pub fn put_int(&mut self, key: &str, value: i64) {
+        self.put(key, BundleValue::Int(value));
+    }
+
+    pub fn put_float(&mut self, key: &str, value: f64) {
+        self.put(key, BundleValue::Float(value));
+    }
+
+    pub fn put_string(&mut self, key: &str, value: &str) {
+        self.put(key, BundleValue::Str(value.to_string()));
+    }
+
+    pub fn put_bytes(&mut self, key: &str, value: &[u8]) {
+        self.put(key, BundleValue::Bytes(value.to_vec()));
+    }
+
+    pub fn put_string_map(&mut self, key: &str, value: &HashMap<String, String>) {
+        let map = value.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        self.put(key, BundleValue::StrMap(map));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&BundleValue> {
+        self.entries.get(key)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.entries.get(key) {
+            Some(BundleValue::Bool(v)) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.entries.get(key) {
+            Some(BundleValue::Int(v)) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, key: &str) -> Option<f64> {
+        match self.entries.get(key) {
+            Some(BundleValue::Float(v)) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        match self.entries.get(key) {
+            Some(BundleValue::Str(v)) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.entries.get(key) {
+            Some(BundleValue::Bytes(v)) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn get_string_map(&self, key: &str) -> Option<HashMap<String, String>> {
+        match self.entries.get(key) {
+            Some(BundleValue::StrMap(v)) => {
+                Some(v.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn parcel_size(&self) -> usize {
+        self.encode().len()
+    }
+
+    pub fn to_parcel(&self) -> Result<Vec<u8>, BundleError> {
+        let parcel = self.encode();
+        if parcel.len() > self.limit {
+            return Err(BundleError::TransactionTooLarge {
+                size: parcel.len(),
+                limit: self.limit,
+            });
+        }
+        Ok(parcel)
+    }
+
+    pub fn from_parcel(parcel: &[u8], limit: usize) -> Result<Self, BundleError> {
+        if parcel.len() > limit {
+            return Err(BundleError::TransactionTooLarge { size: parcel.len(), limit });
+        }
+        let mut reader = ParcelReader { data: parcel, pos: 0 };
+
+        if reader.take(4)? != PARCEL_MAGIC {
+            return Err(BundleError::Corrupt("bad magic".to_string()));
+        }
+
+        let mut bundle = Self::with_limit(limit);
+        let count = reader.read_u32()?;
+        for _ in 0..count {
+            let key = reader.read_string()?;
+            let value = match reader.read_u8()? {
+                0 => BundleValue::Bool(reader.read_u8()? != 0),
+                1 => BundleValue::Int(i64::from_le_bytes(reader.read_array()?)),
+                2 => BundleValue::Float(f64::from_le_bytes(reader.read_array()?)),
+                3 => BundleValue::Str(reader.read_string()?),
+                4 => {
+                    let len = reader.read_u32()? as usize;
+                    BundleValue::Bytes(reader.take(len)?.to_vec())
+                }
+                5 => {
+                    let mut map = BTreeMap::new();
+                    for _ in 0..reader.read_u32()? {
+                        let k = reader.read_string()?;
+                        map.insert(k, reader.read_string()?);
+                    }
+                    BundleValue::StrMap(map)
+                }
+                tag => return Err(BundleError::Corrupt(format!("unknown value tag {}", tag))),
+            };
+            bundle.entries.insert(key, value);
+        }
+
+        if reader.pos != parcel.len() {
+            return Err(BundleError::Corrupt("trailing bytes".to_string()));
+        }
+        Ok(bundle)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(PARCEL_MAGIC);
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for (key, value) in &self.entries {
+            write_string(&mut out, key);
+            match value {
+                BundleValue::Bool(v) => {
+                    out.push(0);
+                    out.push(*v as u8);
+                }
+                BundleValue::Int(v) => {
+                    out.push(1);
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                BundleValue::Float(v) => {
+                    out.push(2);
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                BundleValue::Str(v) => {
+                    out.push(3);
+                    write_string(&mut out, v);
+                }
+                BundleValue::Bytes(v) => {
+                    out.push(4);
+                    out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                    out.extend_from_slice(v);
+                }
+                BundleValue::StrMap(map) => {
+                    out.push(5);
+                    out.extend_from_slice(&(map.len() as u32).to_le_bytes());
+                    for (k, v) in map {
+                        write_string(&mut out, k);
+                        write_string(&mut out, v);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct ParcelReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ParcelReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BundleError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| BundleError::Corrupt(format!("truncated at byte {}", self.pos)))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BundleError> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.take(N)?);
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BundleError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, BundleError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_string(&mut self) -> Result<String, BundleError> {
+        let len = self.read_u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| BundleError::Corrupt("invalid utf-8 string".to_string()))
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::time::Duration;
+
+// An interval longer than this many periods means samples went missing
+const DROP_THRESHOLD: f64 = 1.5;
+const HISTOGRAM_BUCKETS_PER_PERIOD: usize = 10;
+const HISTOGRAM_PERIODS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateTolerance {
+    pub nominal_hz: f64,
+    // Fraction of nominal, e.g. 0.1 accepts 90..110Hz for a 100Hz sensor
+    pub rate_error: f64,
+    pub max_jitter: Duration,
+    pub max_drop_rate: f64,
+    pub max_drift_ppm: f64,
+}
+
+impl RateTolerance {
+    pub fn new(nominal_hz: f64) -> Self {
+        Self {
+            nominal_hz,
+            rate_error: 0.1,
+            max_jitter: Duration::from_secs_f64(0.1 / nominal_hz),
+            max_drop_rate: 0.01,
+            max_drift_ppm: 500.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingReport {
+    pub sensor: String,
+    pub samples: usize,
+    pub nominal_interval: Duration,
+    pub mean_interval: Duration,
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    pub rate_hz: f64,
+    // Standard deviation of the intervals that are not gaps
+    pub jitter: Duration,
+    // (bucket lower bound, count); buckets are a tenth of the nominal period, the last one is open-ended
+    pub histogram: Vec<(Duration, usize)>,
+    pub dropped: usize,
+    pub non_monotonic: usize,
+    pub drift_ppm: Option<f64>,
+}
+
+impl TimingReport {
+    pub fn drop_rate(&self) -> f64 {
+        let expected = self.samples + self.dropped;
+        if expected == 0 { 0.0 } else { self.dropped as f64 / expected as f64 }
+    }
+
+    pub fn check(&self, tolerance: &RateTolerance) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+
+        let rate_error = (self.rate_hz - tolerance.nominal_hz).abs() / tolerance.nominal_hz;
+        if rate_error > tolerance.rate_error {
+            violations.push(format!(
+                "rate {:.2}Hz is {:.1}% off nominal {:.0}Hz",
+                self.rate_hz,
+                rate_error * 100.0,
+                tolerance.nominal_hz
+            ));
+        }
+        if self.jitter > tolerance.max_jitter {
+            violations.push(format!("jitter {:?} exceeds {:?}", self.jitter, tolerance.max_jitter));
+        }
+        if self.drop_rate() > tolerance.max_drop_rate {
+            violations.push(format!(
+                "dropped {} samples ({:.2}%), limit {:.2}%",
+                self.dropped,
+                self.drop_rate() * 100.0,
+                tolerance.max_drop_rate * 100.0
+            ));
+        }
+        if self.non_monotonic > 0 {
+            violations.push(format!("{} timestamps did not increase", self.non_monotonic));
+        }
+        if let Some(ppm) = self.drift_ppm.filter(|ppm| ppm.abs() > tolerance.max_drift_ppm) {
+            violations.push(format!("clock drift {:.0}ppm exceeds {:.0}ppm", ppm, tolerance.max_drift_ppm));
+        }
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "[{}] {} samples, {:.2}Hz, interval mean={:?} min={:?} max={:?}, jitter={:?}, dropped={}, non-monotonic={}, drift={}",
+            self.sensor,
+            self.samples,
+            self.rate_hz,
+            self.mean_interval,
+            self.min_interval,
+            self.max_interval,
+            self.jitter,
+            self.dropped,
+            self.non_monotonic,
+            self.drift_ppm.map_or("n/a".to_string(), |ppm| format!("{:.0}ppm", ppm))
+        )?;
+        let peak = self.histogram.iter().map(|(_, count)| *count).max().unwrap_or(1);
+        for (bucket, count) in &self.histogram {
+            let bar = "#".repeat((count * 40).div_ceil(peak));
+            writeln!(f, "  {:>10.3}ms {:>6} {}", bucket.as_secs_f64() * 1000.0, count, bar)?;
+        }
+        Ok(())
+    }
+}
+
+// Event timestamps as the sensor HAL reports them, in arrival order
+pub fn analyze(sensor: &str, timestamps: &[Duration], nominal_hz: f64) -> TimingReport {
+    let period = 1.0 / nominal_hz;
+    let bucket_width = period / HISTOGRAM_BUCKETS_PER_PERIOD as f64;
+    let bucket_count = HISTOGRAM_BUCKETS_PER_PERIOD * HISTOGRAM_PERIODS + 1;
+    let mut buckets = vec![0usize; bucket_count];
+
+    let mut intervals = Vec::with_capacity(timestamps.len());
+    let mut dropped = 0;
+    let mut non_monotonic = 0;
+
+    for pair in timestamps.windows(2) {
+        let interval = pair[1].as_secs_f64() - pair[0].as_secs_f64();
+        if interval <= 0.0 {
+            non_monotonic += 1;
+            continue;
+        }
+        // Nudge so an interval of exactly one bucket width does not fall into the bucket below
+        let bucket = (interval / bucket_width + 1e-6) as usize;
+        buckets[bucket.min(bucket_count - 1)] += 1;
+
+        if interval > period * DROP_THRESHOLD {
+            dropped += (interval / period).round() as usize - 1;
+        } else {
+            intervals.push(interval);
+        }
+    }
+
+    let mean = if intervals.is_empty() { 0.0 } else { intervals.iter().sum::<f64>() / intervals.len() as f64 };
+    let variance = if intervals.is_empty() {
+        0.0
+    } else {
+        intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64
+    };
+
+    let span = match (timestamps.first(), timestamps.iter().max()) {
+        (Some(first), Some(last)) => last.saturating_sub(*first).as_secs_f64(),
+        _ => 0.0,
+    };
+    let rate_hz = if span > 0.0 { (timestamps.len() - 1) as f64 / span } else { 0.0 };
+
+    TimingReport {
+        sensor: sensor.to_string(),
+        samples: timestamps.len(),
+        nominal_interval: Duration::from_secs_f64(period),
+        mean_interval: Duration::from_secs_f64(mean),
+        min_interval: Duration::from_secs_f64(intervals.iter().copied().reduce(f64::min).unwrap_or(0.0)),
+        max_interval: Duration::from_secs_f64(intervals.iter().copied().reduce(f64::max).unwrap_or(0.0)),
+        rate_hz,
+        jitter: Duration::from_secs_f64(variance.sqrt()),
+        histogram: buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(i, count)| (Duration::from_secs_f64(bucket_width * i as f64), *count))
+            .collect(),
+        dropped,
+        non_monotonic,
+        drift_ppm: None,
+    }
+}
+
+// Same as `analyze`, plus drift of the sensor clock against the host clock
+pub fn analyze_with_host(sensor: &str, timestamps: &[Duration], sync_points: &[(Duration, Duration)], nominal_hz: f64) -> TimingReport {
+    let mut report = analyze(sensor, timestamps, nominal_hz);
+    report.drift_ppm = estimate_drift_ppm(sync_points);
+    report
+}
+
+// (sensor timestamp, host receive time) pairs for samples delivered as soon as they were taken,
+// e.g. the newest sample of each FIFO batch; older batched samples would bias the fit.
+// Least-squares slope of the offset over host time; positive means the sensor clock runs fast
+pub fn estimate_drift_ppm(sync_points: &[(Duration, Duration)]) -> Option<f64> {
+    let n = sync_points.len();
+    if n < 2 {
+        return None;
+    }
+
+    let xs: Vec<f64> = sync_points.iter().map(|(_, host)| host.as_secs_f64()).collect();
+    let ys: Vec<f64> = sync_points.iter().zip(&xs).map(|((t, _), x)| t.as_secs_f64() - x).collect();
+    let mean_x = xs.iter().sum::<f64>() / n as f64;
+    let mean_y = ys.iter().sum::<f64>() / n as f64;
+
+    let covariance: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    (variance > 0.0).then(|| covariance / variance * 1e6)
+}
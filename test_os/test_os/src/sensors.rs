@@ -0,0 +1,320 @@
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::clock::Clock;
+
+pub const STANDARD_GRAVITY: f32 = 9.80665;
+pub const SEA_LEVEL_PRESSURE_HPA: f32 = 1013.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    Accelerometer,
+    Gyroscope,
+    Magnetometer,
+    Barometer,
+}
+
+impl SensorKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "accelerometer" => Some(SensorKind::Accelerometer),
+            "gyroscope" => Some(SensorKind::Gyroscope),
+            "magnetometer" => Some(SensorKind::Magnetometer),
+            "barometer" => Some(SensorKind::Barometer),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorKind::Accelerometer => "accelerometer",
+            SensorKind::Gyroscope => "gyroscope",
+            SensorKind::Magnetometer => "magnetometer",
+            SensorKind::Barometer => "barometer",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            SensorKind::Accelerometer => "m/s²",
+            SensorKind::Gyroscope => "rad/s",
+            SensorKind::Magnetometer => "µT",
+            SensorKind::Barometer => "hPa",
+        }
+    }
+
+    pub fn axes(&self) -> usize {
+        match self {
+            SensorKind::Barometer => 1,
+            _ => 3,
+        }
+    }
+
+    // Device lying flat and still in San Francisco at sea level
+    pub fn resting_value(&self) -> [f32; 3] {
+        match self {
+            SensorKind::Accelerometer => [0.0, 0.0, STANDARD_GRAVITY],
+            SensorKind::Gyroscope => [0.0, 0.0, 0.0],
+            SensorKind::Magnetometer => [0.0, 22.9, -42.1],
+            SensorKind::Barometer => [SEA_LEVEL_PRESSURE_HPA, 0.0, 0.0],
+        }
+    }
+}
+
+// Per-sample error model; bias drift is a random walk in units per sqrt(second)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseModel {
+    pub noise_std: f32,
+    pub bias: [f32; 3],
+    pub bias_drift: f32,
+    pub resolution: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl NoiseModel {
+    // Roughly a phone-grade 6-axis IMU, AK09918-class magnetometer and BMP-class barometer
+    pub fn for_kind(kind: SensorKind) -> Self {
+        match kind {
+            SensorKind::Accelerometer => Self {
+                noise_std: 0.02,
+                bias: [0.04, -0.03, 0.05],
+                bias_drift: 0.0005,
+                resolution: 0.0024,
+                min: -8.0 * STANDARD_GRAVITY,
+                max: 8.0 * STANDARD_GRAVITY,
+            },
+            SensorKind::Gyroscope => Self {
+                noise_std: 0.005,
+                bias: [0.008, -0.005, 0.003],
+                bias_drift: 0.0002,
+                resolution: 0.00107,
+                min: -34.9,
+                max: 34.9,
+            },
+            SensorKind::Magnetometer => Self {
+                noise_std: 0.4,
+                bias: [4.5, -3.0, 2.0],
+                bias_drift: 0.01,
+                resolution: 0.15,
+                min: -4912.0,
+                max: 4912.0,
+            },
+            SensorKind::Barometer => Self {
+                noise_std: 0.03,
+                bias: [0.4, 0.0, 0.0],
+                bias_drift: 0.001,
+                resolution: 0.01,
+                min: 300.0,
+                max: 1100.0,
+            },
+        }
+    }
+
+    pub fn quantize(&self, value: f32) -> f32 {
+        let clamped = value.clamp(self.min, self.max);
+        if self.resolution > 0.0 {
+            (clamped / self.resolution).round() * self.resolution
+        } else {
+            clamped
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorConfig {
+    pub rate_hz: f64,
+    pub max_report_latency: Duration,
+    pub fifo_capacity: usize,
+    pub noise: NoiseModel,
+    pub seed: u64,
+}
+
+impl SensorConfig {
+    pub fn for_kind(kind: SensorKind) -> Self {
+        let rate_hz = match kind {
+            SensorKind::Accelerometer | SensorKind::Gyroscope => 100.0,
+            SensorKind::Magnetometer => 50.0,
+            SensorKind::Barometer => 25.0,
+        };
+        Self {
+            rate_hz,
+            max_report_latency: Duration::ZERO,
+            fifo_capacity: 3000,
+            noise: NoiseModel::for_kind(kind),
+            seed: 0x5e45_0000 + kind as u64,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_nanos((1e9 / self.rate_hz).round() as u64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorSample {
+    pub kind: SensorKind,
+    pub values: [f32; 3],
+    pub timestamp: Duration,
+}
+
+impl SensorSample {
+    pub fn magnitude(&self) -> f32 {
+        self.values.iter().map(|v| v * v).sum::<f32>().sqrt()
+    }
+}
+
+pub trait SensorSource: Send {
+    fn config(&self) -> &SensorConfig;
+
+    // Noise-free physical value at sensor time `t`
+    fn true_value(&self, t: Duration) -> [f32; 3];
+
+    // One measurement at sensor time `t`; calls must move forward in time
+    fn measure(&mut self, t: Duration) -> SensorSample;
+}
+
+type Signal = Box<dyn Fn(Duration) -> [f32; 3] + Send>;
+
+pub struct SimulatedSensor {
+    kind: SensorKind,
+    config: SensorConfig,
+    signal: Signal,
+    bias: [f32; 3],
+    last_time: Option<Duration>,
+    rng: StdRng,
+}
+
+impl SimulatedSensor {
+    pub fn new(kind: SensorKind) -> Self {
+        Self::with_config(kind, SensorConfig::for_kind(kind))
+    }
+
+    pub fn with_config(kind: SensorKind, config: SensorConfig) -> Self {
+        let resting = kind.resting_value();
+        Self {
+            kind,
+            config,
+            signal: Box::new(move |_| resting),
+            bias: config.noise.bias,
+            last_time: None,
+            rng: StdRng::seed_from_u64(config.seed),
+        }
+    }
+
+    pub fn accelerometer() -> Self {
+        Self::new(SensorKind::Accelerometer)
+    }
+
+    // Replaces the resting signal, e.g. with a motion profile
+    pub fn with_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Fn(Duration) -> [f32; 3] + Send + 'static,
+    {
+        self.signal = Box::new(signal);
+        self
+    }
+
+    fn gaussian(&mut self, std: f32) -> f32 {
+        if std <= 0.0 {
+            return 0.0;
+        }
+        // Box-Muller
+        let u1: f32 = self.rng.gen_range(f32::EPSILON..1.0);
+        let u2: f32 = self.rng.gen_range(0.0..1.0);
+        std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+    }
+}
+
+impl SensorSource for SimulatedSensor {
+    fn config(&self) -> &SensorConfig {
+        &self.config
+    }
+
+    fn true_value(&self, t: Duration) -> [f32; 3] {
+        (self.signal)(t)
+    }
+
+    fn measure(&mut self, t: Duration) -> SensorSample {
+        let noise = self.config.noise;
+        let dt = self.last_time.map_or(0.0, |last| t.saturating_sub(last).as_secs_f32());
+        self.last_time = Some(t);
+
+        let truth = self.true_value(t);
+        let mut values = [0.0; 3];
+        for axis in 0..self.kind.axes() {
+            self.bias[axis] += self.gaussian(noise.bias_drift * dt.sqrt());
+            let raw = truth[axis] + self.bias[axis] + self.gaussian(noise.noise_std);
+            values[axis] = noise.quantize(raw);
+        }
+
+        SensorSample {
+            kind: self.kind,
+            values,
+            timestamp: t,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorBatch {
+    pub delivered_at: Duration,
+    pub samples: Vec<SensorSample>,
+}
+
+impl SensorBatch {
+    pub fn max_latency(&self) -> Duration {
+        self.samples
+            .first()
+            .map_or(Duration::ZERO, |s| self.delivered_at.saturating_sub(s.timestamp))
+    }
+}
+
+// Hardware FIFO: samples at the configured rate and wakes the host only when the oldest
+// sample would exceed max_report_latency or the FIFO fills up; zero latency means streaming
+pub fn run_batched(source: &mut dyn SensorSource, clock: &dyn Clock, duration: Duration) -> Vec<SensorBatch> {
+    let config = *source.config();
+    let period = config.period();
+    let capacity = config.fifo_capacity.max(1);
+    let start = clock.now();
+
+    let mut batches = Vec::new();
+    let mut fifo: Vec<SensorSample> = Vec::with_capacity(capacity);
+    let mut k: u32 = 0;
+
+    loop {
+        let due = period * k;
+        if due >= duration {
+            break;
+        }
+        let elapsed = clock.elapsed_since(start);
+        if due > elapsed {
+            clock.sleep(due - elapsed);
+        }
+
+        let sample = source.measure(clock.now());
+        fifo.push(sample);
+        k += 1;
+
+        let oldest = fifo[0].timestamp;
+        let next_due = start + period * k;
+        // Flush before the next sample would push the oldest one past the deadline
+        let deadline_hit = next_due.saturating_sub(oldest) > config.max_report_latency;
+        if fifo.len() >= capacity || deadline_hit {
+            batches.push(SensorBatch {
+                delivered_at: clock.now(),
+                samples: std::mem::take(&mut fifo),
+            });
+        }
+    }
+
+    if !fifo.is_empty() {
+        batches.push(SensorBatch {
+            delivered_at: clock.now(),
+            samples: fifo,
+        });
+    }
+    batches
+}
@@ -3,16 +3,36 @@
 
 use std::time::{Duration, Instant, SystemTime};
 use std::thread;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Write, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
 use std::collections::{HashMap, VecDeque};
 use std::sync::mpsc::{self, Sender, Receiver};
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 use backtrace::Backtrace;
 
+mod backoff;
+mod download_manager;
+mod golden;
+mod handover;
+mod http_load;
+mod network_emulator;
+mod rasterizer;
+mod stress_workloads;
+
+use backoff::BackoffPolicy;
+use download_manager::{DownloadError, DownloadManager, DownloadReport, DownloadRequest};
+use golden::{GoldenError, Goldens, Image, Tolerance};
+use handover::{FlowReport, HandoverConfig, Outcome};
+use http_load::{Endpoint, HttpServer, LoadConfig, LoadReport};
+use network_emulator::{NetworkEmulator, NetworkProfile};
+use rasterizer::{quad, DrawState, Framebuffer, Kernel, RasterStats, Texture, Vertex};
+use sha2::{Digest, Sha256};
+use stress_workloads::CpuStress;
+
 #[derive(Debug, Clone)]
 struct SystemMetrics {
     cpu_usage: f32,
@@ -35,6 +55,8 @@ struct StressTestConfig {
     max_open_files: usize,
     max_battery_drain_percent: f32,
     max_temperature_celsius: f32,
+    // Unoptimized builds rasterize an order of magnitude slower
+    max_frame_time: Duration,
     enable_throttling_protection: bool,
 }
 
@@ -49,6 +71,7 @@ impl Default for StressTestConfig {
             max_open_files: if cfg!(target_os = "android") { 200 } else { 100 },
             max_battery_drain_percent: 0.5,
             max_temperature_celsius: 45.0,
+            max_frame_time: Duration::from_millis(if cfg!(debug_assertions) { 400 } else { 33 }),
             enable_throttling_protection: true,
         }
     }
@@ -60,89 +83,9 @@ fn test_cpu_multi_threading_stress() {
     
     let config = StressTestConfig::default();
     let metrics = Arc::new(Mutex::new(Vec::new()));
-    let stop_signal = Arc::new(AtomicBool::new(false));
-    let completed_operations = Arc::new(AtomicU64::new(0));
     let start_time = Instant::now();
     
-    let mut thread_handles = vec![];
-    
-    let workloads: Vec<Box<dyn Fn(Arc<AtomicBool>, Arc<AtomicU64>) + Send>> = vec![
-        Box::new(|stop, counter| {
-            let mut rng = rand::thread_rng();
-            while !stop.load(Ordering::Relaxed) {
-                for _ in 0..1000 {
-                    let a: f64 = rng.gen();
-                    let b: f64 = rng.gen();
-                    let _ = a.sin() * b.cos() + (a * b).tan();
-                }
-                counter.fetch_add(1, Ordering::Relaxed);
-                thread::yield_now();
-            }
-        }),
-        
-        Box::new(|stop, counter| {
-            while !stop.load(Ordering::Relaxed) {
-                let size = rand::thread_rng().gen_range(1024..1024*1024);
-                let vec = vec![0u8; size];
-                drop(vec);
-                counter.fetch_add(1, Ordering::Relaxed);
-            }
-        }),
-        
-        Box::new(|stop, counter| {
-            let lock = Arc::new(Mutex::new(0u64));
-            let mut handles = vec![];
-            
-            for _ in 0..5 {
-                let lock = Arc::clone(&lock);
-                let stop = Arc::clone(&stop);
-                handles.push(thread::spawn(move || {
-                    while !stop.load(Ordering::Relaxed) {
-                        let mut data = lock.lock().unwrap();
-                        *data = data.wrapping_add(1);
-                        drop(data);
-                        counter.fetch_add(1, Ordering::Relaxed);
-                        thread::yield_now();
-                    }
-                }));
-            }
-            
-            for handle in handles {
-                handle.join().unwrap();
-            }
-        }),
-        
-        Box::new(|stop, counter| {
-            while !stop.load(Ordering::Relaxed) {
-                let mut result = 0.0;
-                for i in 0..1000 {
-                    result += (i as f64).sqrt() * (i as f64).sin();
-                }
-                black_box(result);
-                counter.fetch_add(1, Ordering::Relaxed);
-            }
-        }),
-        
-        Box::new(|stop, counter| {
-            while !stop.load(Ordering::Relaxed) {
-                for _ in 0..100 {
-                    thread::yield_now();
-                }
-                counter.fetch_add(1, Ordering::Relaxed);
-            }
-        }),
-    ];
-    
-    for workload in workloads {
-        for _ in 0..(config.max_threads / workloads.len()) {
-            let stop = Arc::clone(&stop_signal);
-            let counter = Arc::clone(&completed_operations);
-            let handle = thread::spawn(move || {
-                workload(stop, counter);
-            });
-            thread_handles.push(handle);
-        }
-    }
+    let stress = CpuStress::start(config.max_threads);
     
     let monitor_interval = Duration::from_secs(1);
     let mut monitor_count = 0;
@@ -157,7 +100,7 @@ fn test_cpu_multi_threading_stress() {
         check_limits(&current_metrics, &config);
         
         if monitor_count % 5 == 0 {
-            let ops = completed_operations.load(Ordering::Relaxed);
+            let ops = stress.operations();
             let elapsed = start_time.elapsed().as_secs();
             println!("Progress: {}s/{}s, OPS: {}/s", 
                 elapsed, config.test_duration.as_secs(),
@@ -165,13 +108,8 @@ fn test_cpu_multi_threading_stress() {
         }
     }
     
-    stop_signal.store(true, Ordering::Relaxed);
-    
-    for handle in thread_handles {
-        let _ = handle.join();
-    }
-    
-    analyze_stress_results(metrics.lock().unwrap().clone(), completed_operations.load(Ordering::Relaxed));
+    let completed_operations = stress.stop();
+    analyze_stress_results(metrics.lock().unwrap().clone(), completed_operations);
     
     println!("✓ CPU stress test completed");
 }
@@ -458,39 +396,81 @@ fn test_network_stress() {
     let config = StressTestConfig::default();
     let start_time = Instant::now();
     
-    let network_conditions = vec![
-        ("WiFi", Duration::from_millis(10), 100 * 1024 * 1024),
-        ("4G", Duration::from_millis(50), 50 * 1024 * 1024),
-        ("3G", Duration::from_millis(150), 5 * 1024 * 1024),
-        ("Edge", Duration::from_millis(300), 256 * 1024),
-        ("Lossy", Duration::from_millis(100), 1 * 1024 * 1024),
-    ];
-    
-    for (condition_name, latency, bandwidth) in network_conditions {
+    let echo = start_echo_servers();
+    let mut emulator = NetworkEmulator::new(NetworkProfile::unthrottled(), 44);
+    let tcp_proxy = emulator.proxy_tcp(echo.0).expect("tcp proxy");
+    let udp_proxy = emulator.proxy_udp(echo.1).expect("udp proxy");
+    let server = start_api_server();
+    let http_proxy = emulator.proxy_tcp(server.addr()).expect("http proxy");
+    let files = HttpServer::start(network_emulator::profiles().iter()
+        .map(|p| Endpoint::new(&format!("/files/{}", p.name)).with_payload((p.bandwidth / 10) as usize))
+        .collect()).expect("file server");
+    let files_proxy = emulator.proxy_tcp(files.addr()).expect("file proxy");
+    
+    // Baseline without the emulator: only the scripted 503s may fail
+    let direct = run_http_load(server.addr(), 100);
+    assert_eq!(direct.transport_errors(), 0, "direct requests failed: {}", direct);
+    assert!(direct.success_rate() >= 0.9);
+    
+    // A fresh connection per request, as clients without keep-alive make them
+    let cold_config = LoadConfig::new(40, 4, &["/api/small", "/api/upload"])
+        .without_keep_alive()
+        .with_timeout(Duration::from_secs(2));
+    let cold = http_load::run_load(server.addr(), &cold_config);
+    println!("  HTTP load without keep-alive: {}", cold);
+    assert_eq!(cold.succeeded, 40, "requests on fresh connections failed: {}", cold);
+    
+    for profile in network_emulator::profiles() {
+        let (condition_name, bandwidth) = (profile.name.as_str(), profile.bandwidth);
         println!("Testing network condition: {}", condition_name);
         
-        simulate_network_condition(condition_name, latency, bandwidth);
+        apply_network_condition(&emulator, &profile, tcp_proxy, udp_proxy);
         
         let http_start = Instant::now();
-        let http_success = test_http_requests(100);
+        let http_success = test_http_requests(http_proxy, 100);
         let http_duration = http_start.elapsed();
         
         println!("  HTTP requests: {} success, {:?}", http_success, http_duration);
+        assert!(http_success > 50, "{}: most requests should survive the link", condition_name);
         
-        let download_start = Instant::now();
-        let downloaded = simulate_data_download(bandwidth / 10);
-        let download_duration = download_start.elapsed();
-        
-        let actual_bandwidth = (downloaded * 8) as f64 / download_duration.as_secs_f64();
-        println!("  Download: {}MB, {:.2}Mbps", downloaded / (1024*1024), actual_bandwidth / 1_000_000.0);
+        let download = test_data_download(&files, files_proxy, &profile);
+        let actual_bandwidth = download.throughput() * 8.0;
+        println!("  Download: {}KB, resumed at {}KB, {:.2}Mbps over {} attempts",
+            download.total_bytes / 1024, download.resumed_from / 1024, actual_bandwidth / 1_000_000.0, download.attempts);
+        assert!(download.throughput() <= bandwidth as f64 * 1.1, "{}: faster than the emulated link", condition_name);
         
         if condition_name != "WiFi" {
-            simulate_network_handover();
+            test_network_handover(HandoverConfig::new(NetworkProfile::wifi(), profile.clone()));
         }
         
         thread::sleep(Duration::from_secs(1));
     }
     
+    emulator.set_profile(NetworkProfile::lte());
+    test_corrupted_download(&files, files_proxy);
+    
+    let stats = emulator.stats();
+    println!("Emulator: {} connections, {} retransmits, {} resets, {}/{} datagrams dropped",
+        stats.connections, stats.retransmits, stats.resets, stats.datagrams_dropped, stats.datagrams);
+    assert!(stats.bytes_up > 0 && stats.bytes_down > 0);
+    
+    // Switching profiles mid-connection applies to the very next exchange
+    emulator.set_profile(NetworkProfile::wifi());
+    let mut stream = TcpStream::connect(tcp_proxy).unwrap();
+    let fast = echo_round_trip(&mut stream).unwrap();
+    emulator.set_profile(NetworkProfile::edge());
+    let slow = echo_round_trip(&mut stream).unwrap();
+    println!("Runtime switch WiFi -> Edge: {:?} -> {:?}", fast, slow);
+    assert!(slow > fast);
+    
+    assert!(emulator.reset_connections() >= 1);
+    assert_eq!(emulator.active_connections(), 0, "connections survived the reset");
+    assert!(echo_round_trip(&mut stream).is_err(), "reset connection still carries data");
+    
+    // Without a reset the old path just goes quiet; only the client timeout gets requests unstuck
+    let silent = test_network_handover(HandoverConfig::new(NetworkProfile::wifi(), NetworkProfile::lte()).silent());
+    assert!(silent.iter().any(|r| r.affected == Some(Outcome::Hung)), "black-holed handover hung nothing");
+    
     println!("✓ Network stress test completed");
 }
 
@@ -504,41 +484,58 @@ fn test_gpu_stress() {
     let mut frame_times = Vec::new();
     let mut gpu_memory_usage = Vec::new();
     
+    let mut target = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+    let mut post = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+    let texture = Texture::checkerboard(64, 8, [240, 240, 240, 255], [40, 90, 200, 255]);
+    let mut rng = StdRng::seed_from_u64(49);
+    let mut totals = RasterStats::default();
+    
+    // A minimized window leaves zero-sized targets; passes over them must be no-ops
+    for (width, height) in [(0, GPU_HEIGHT), (GPU_WIDTH, 0)] {
+        let (empty, mut empty_post) = (Framebuffer::new(width, height), Framebuffer::new(width, height));
+        for kernel in [Kernel::BoxBlur(2), Kernel::EdgeDetect, Kernel::Grayscale] {
+            assert_eq!(run_compute_shader(kernel, &empty, &mut empty_post), 0);
+        }
+    }
+    assert_eq!(Texture::new(0, 0, Vec::new()).sample(0.5, 0.5), [0.0; 4]);
+    assert_eq!((texture.width(), texture.height()), (64, 64));
+    
+    // Дальний треугольник не перекрывает ближний, пока тест глубины не выключен
+    let covering = |z: f32, color| [(0.0, 0.0), (64.0, 0.0), (0.0, 64.0)].map(|(x, y)| Vertex::new(x, y, z).with_color(color));
+    let mut depth_target = Framebuffer::new(16, 16);
+    depth_target.clear([0, 0, 0, 255]);
+    depth_target.draw_triangle(&covering(0.2, [1.0, 0.0, 0.0, 1.0]), &DrawState::opaque());
+    let rejected = depth_target.draw_triangle(&covering(0.8, [0.0, 0.0, 1.0, 1.0]), &DrawState::opaque());
+    assert_eq!((depth_target.pixel(4, 4), rejected.fragments), ([255, 0, 0, 255], 0));
+    depth_target.draw_triangle(&covering(0.8, [0.0, 0.0, 1.0, 1.0]), &DrawState::opaque().without_depth_test());
+    assert_eq!(depth_target.pixel(4, 4), [0, 0, 255, 255]);
+    
     while start_time.elapsed() < config.test_duration {
         let frame_start = Instant::now();
         
-        let workloads = vec![
-            || {
-                for _ in 0..1000 {
-                    black_box(render_triangle());
-                }
-            },
-            || {
-                for _ in 0..500 {
-                    black_box(render_textured_quad());
-                }
-            },
-            || {
-                for _ in 0..200 {
-                    black_box(run_compute_shader());
-                }
-            },
-        ];
-        
-        for workload in workloads {
-            workload();
+        target.clear([0, 0, 0, 255]);
+        let mut stats = RasterStats::default();
+        for _ in 0..1000 {
+            stats += render_triangle(&mut target, &mut rng);
+        }
+        for _ in 0..500 {
+            stats += render_textured_quad(&mut target, &texture, &mut rng);
+        }
+        for kernel in [Kernel::BoxBlur(2), Kernel::EdgeDetect] {
+            black_box(run_compute_shader(kernel, &target, &mut post));
         }
+        totals += stats;
         
         let frame_time = frame_start.elapsed();
         frame_times.push(frame_time);
         
-        let gpu_memory = get_gpu_memory_usage();
+        let gpu_memory = get_gpu_memory_usage(&[&target, &post], &texture);
         gpu_memory_usage.push(gpu_memory);
         
         if frame_times.len() > 60 {
             let avg_frame_time = frame_times.iter().sum::<Duration>() / frame_times.len() as u32;
             assert!(
-                avg_frame_time < Duration::from_millis(33),
+                avg_frame_time < config.max_frame_time,
                 "GPU too slow: avg {:.1}ms",
                 avg_frame_time.as_secs_f64() * 1000.0
             );
@@ -547,9 +544,74 @@ fn test_gpu_stress() {
         thread::sleep(Duration::from_millis(16));
     }
     
+    let render_time = frame_times.iter().sum::<Duration>().as_secs_f64();
+    println!("{} frames, {} triangles ({} culled), {} fragments ({} depth rejected)",
+        frame_times.len(), totals.triangles, totals.culled, totals.fragments, totals.depth_rejected);
+    println!("Fill rate: {:.1} Mpix/s, memory traffic: {:.1} MB/s, GPU memory: {} KB",
+        totals.fragments as f64 / render_time / 1e6,
+        totals.memory_traffic() as f64 / render_time / 1024.0 / 1024.0,
+        gpu_memory_usage.last().copied().unwrap_or_default() / 1024);
+    assert!(totals.fragments > 0, "Nothing was rasterized");
+    
     println!("✓ GPU stress test completed");
 }
 
+// Эталонные кадры из captures/golden вшиты в бинарь: дерева исходников на устройстве нет
+const GOLDENS: golden::EmbeddedGoldens = &[
+    ("triangles", include_bytes!("../captures/golden/triangles.png")),
+    ("textured_quads", include_bytes!("../captures/golden/textured_quads.png")),
+    ("complex_scene", include_bytes!("../captures/golden/complex_scene.png")),
+    ("edge_detect", include_bytes!("../captures/golden/edge_detect.png")),
+];
+
+// UPDATE_GOLDENS=1 пишет новые эталоны в каталог теста на устройстве, оттуда их копируют в captures/golden
+#[test]
+fn test_gpu_golden_images() {
+    println!("=== GPU GOLDEN IMAGES TEST ===");
+    
+    let goldens = Goldens::new(GOLDENS, get_mobile_test_dir().join("goldens"));
+    let tolerance = Tolerance::new(0.05, 0.002);
+    
+    let mut failures = Vec::new();
+    for (name, image) in reference_scenes(0) {
+        match goldens.check(name, &image, &tolerance) {
+            Ok(report) => println!("{}: {}", name, report),
+            Err(error) => {
+                println!("{}: {}", name, error);
+                failures.push(name);
+            }
+        }
+    }
+    if goldens.is_regenerating() {
+        println!("Goldens regenerated in {:?}", goldens.output());
+    }
+    assert!(failures.is_empty(), "Rendering differs from goldens: {:?}", failures);
+    
+    // Шум в младшем бите заметен побайтово, но не глазу
+    let (name, reference) = reference_scenes(0).swap_remove(2);
+    let mut rng = StdRng::seed_from_u64(50);
+    let noisy = Image::new(
+        reference.width,
+        reference.height,
+        reference.rgba.iter().map(|&c| c.saturating_add_signed(rng.gen_range(-1..=1))).collect(),
+    );
+    let report = golden::compare(&reference, &noisy, &tolerance);
+    assert!(report.max_distance > 0.0 && report.differing == 0, "LSB noise should be tolerated: {}", report);
+    assert!(!golden::compare(&reference, &noisy, &Tolerance::exact()).passes(&Tolerance::exact()));
+    
+    // Другой кадр анимации должен провалить сравнение и оставить diff
+    let (_, next_frame) = reference_scenes(12).swap_remove(2);
+    match goldens.regenerating(false).check(name, &next_frame, &tolerance) {
+        Err(GoldenError::Mismatch { report, diff }) => {
+            println!("Regression caught: {}", report);
+            assert!(diff.exists(), "Diff image should be written to {:?}", diff);
+        }
+        other => panic!("Changed frame should not match the golden: {:?}", other),
+    }
+    
+    println!("✓ GPU golden images test completed");
+}
+
 #[test]
 fn test_multimedia_stress() {
     println!("=== MULTIMEDIA STRESS TEST ===");
@@ -651,8 +713,9 @@ fn test_comprehensive_system_stress() {
     handles.push(thread::spawn({
         let stop = Arc::clone(&stop_signal);
         move || {
+            let server = start_api_server();
             while !stop.load(Ordering::Relaxed) {
-                simulate_network_traffic(Duration::from_millis(100));
+                simulate_network_traffic(server.addr(), Duration::from_millis(100));
             }
         }
     }));
@@ -660,8 +723,13 @@ fn test_comprehensive_system_stress() {
     handles.push(thread::spawn({
         let stop = Arc::clone(&stop_signal);
         move || {
+            let mut target = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+            let mut post = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+            let texture = Texture::checkerboard(64, 8, [240, 240, 240, 255], [40, 90, 200, 255]);
+            let mut frame = 0;
             while !stop.load(Ordering::Relaxed) {
-                black_box(render_complex_scene());
+                black_box(render_complex_scene(&mut target, &mut post, &texture, frame));
+                frame += 1;
             }
         }
     }));
@@ -854,46 +922,199 @@ fn measure_pressure_response() -> Duration {
     start.elapsed()
 }
 
-fn simulate_network_condition(name: &str, latency: Duration, bandwidth: u64) {
-    println!("  Setting network: {}, latency={:?}, bandwidth={}Mbps", 
-        name, latency, bandwidth / 1024 / 1024);
-    thread::sleep(Duration::from_millis(50));
+// TCP and UDP echo servers on their own ephemeral loopback ports, the upstreams the emulator proxies to
+fn start_echo_servers() -> (SocketAddr, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let tcp = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || {
+                let mut reader = stream.try_clone().unwrap();
+                let mut writer = stream;
+                let _ = std::io::copy(&mut reader, &mut writer);
+            });
+        }
+    });
+    
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let udp = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 2048];
+        while let Ok((n, from)) = socket.recv_from(&mut buffer) {
+            let _ = socket.send_to(&buffer[..n], from);
+        }
+    });
+    
+    (tcp, udp)
 }
 
-fn test_http_requests(count: usize) -> usize {
-    let mut successes = 0;
-    for i in 0..count {
-        thread::sleep(Duration::from_micros(500));
-        if i % 10 != 0 {
-            successes += 1;
+fn echo_round_trip(stream: &mut TcpStream) -> std::io::Result<Duration> {
+    let ping = [0x5Au8; 64];
+    let mut pong = [0u8; 64];
+    let start = Instant::now();
+    stream.write_all(&ping)?;
+    stream.read_exact(&mut pong)?;
+    Ok(start.elapsed())
+}
+
+// Switches the emulated link and probes it with real TCP round trips and UDP datagrams
+fn apply_network_condition(emulator: &NetworkEmulator, profile: &NetworkProfile, tcp_proxy: SocketAddr, udp_proxy: SocketAddr) {
+    println!("  Setting network: {}, latency={:?}±{:?}, bandwidth={}KB/s, loss={:.1}%", 
+        profile.name, profile.latency, profile.jitter, profile.bandwidth / 1024, profile.loss * 100.0);
+    emulator.set_profile(profile.clone());
+    
+    let mut rtts = Vec::new();
+    let mut resets = 0;
+    let mut stream = TcpStream::connect(tcp_proxy).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    while rtts.len() < 10 {
+        match echo_round_trip(&mut stream) {
+            Ok(rtt) => rtts.push(rtt),
+            Err(_) => {
+                // Lossy tears connections down; reconnect like an app would
+                resets += 1;
+                assert!(resets <= 5, "{}: too many connection resets", profile.name);
+                stream = TcpStream::connect(tcp_proxy).unwrap();
+                stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            }
         }
     }
-    successes
+    rtts.sort();
+    let min_rtt = rtts[0];
+    println!("  TCP RTT: min {:?}, median {:?}, max {:?}, {} resets", min_rtt, rtts[rtts.len() / 2], rtts[rtts.len() - 1], resets);
+    assert!(min_rtt + profile.jitter >= profile.latency, "{}: RTT {:?} below emulated latency", profile.name, min_rtt);
+    
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(udp_proxy).unwrap();
+    socket.set_read_timeout(Some(profile.latency + profile.jitter + Duration::from_millis(200))).unwrap();
+    let sent = 50;
+    for i in 0..sent {
+        socket.send(&[i as u8; 32]).unwrap();
+    }
+    let mut received = 0;
+    let mut buffer = [0u8; 64];
+    while socket.recv(&mut buffer).is_ok() {
+        received += 1;
+    }
+    println!("  UDP: {}/{} datagrams echoed", received, sent);
+    assert!(received > 0 && received <= sent);
+    if profile.loss == 0.0 {
+        assert_eq!(received, sent, "{}: datagrams lost on a lossless link", profile.name);
+    }
+}
+
+// Small JSON-sized, large media-sized, an upload answering 201 and a flaky endpoint answering
+// every 10th request with 503
+fn start_api_server() -> HttpServer {
+    HttpServer::start(vec![
+        Endpoint::new("/api/small").with_latency(Duration::from_millis(2)).with_payload(1024),
+        Endpoint::new("/api/large").with_latency(Duration::from_millis(5)).with_payload(32 * 1024),
+        Endpoint::new("/api/upload").with_latency(Duration::from_millis(2)).with_status(201),
+        Endpoint::new("/api/flaky").with_latency(Duration::from_millis(2)).with_payload(512).failing_every(10, 503),
+    ]).expect("http server")
+}
+
+fn run_http_load(target: SocketAddr, count: usize) -> LoadReport {
+    let config = LoadConfig::new(count, 8, &["/api/small", "/api/large", "/api/flaky"]);
+    let report = http_load::run_load(target, &config);
+    println!("  HTTP load: {}", report);
+    report
+}
+
+fn test_http_requests(target: SocketAddr, count: usize) -> usize {
+    run_http_load(target, count).succeeded
+}
+
+fn download_request(files_proxy: SocketAddr, profile: &NetworkProfile) -> (DownloadRequest, usize) {
+    let size = (profile.bandwidth / 10) as usize;
+    let destination = get_mobile_test_dir().join("downloads").join(format!("{}.bin", profile.name));
+    fs::create_dir_all(destination.parent().unwrap()).unwrap();
+    fs::remove_file(&destination).ok();
+    fs::remove_file(DownloadManager::part_path(&destination)).ok();
+    fs::remove_file(DownloadManager::progress_path(&destination)).ok();
+    
+    let request = DownloadRequest {
+        server: files_proxy,
+        path: format!("/files/{}", profile.name),
+        destination,
+        sha256: format!("{:x}", Sha256::digest(http_load::payload(size))),
+    };
+    (request, size)
 }
 
-fn simulate_data_download(target_bytes: u64) -> u64 {
-    let chunk_size = 64 * 1024;
-    let mut downloaded = 0;
-    
-    while downloaded < target_bytes {
-        let chunk = vec![0u8; chunk_size as usize];
-        black_box(chunk);
-        downloaded += chunk_size;
-        thread::sleep(Duration::from_micros(100));
+// A session that gives up on the first error, like an app killed mid-download, with the server
+// dropping the connection a third of the way in. Returns the bytes it left on disk
+fn interrupted_download(files: &HttpServer, request: &DownloadRequest, size: usize) -> u64 {
+    let session = DownloadManager::new(BackoffPolicy::Exponential(Duration::from_millis(100)), 1);
+    files.cut_next_response(size / 3);
+    let error = session.download(request).expect_err("download should be cut off by the server");
+    let kept = DownloadManager::progress(request).expect("progress kept after the drop");
+    println!("  Download interrupted ({}) at {}KB", error, kept / 1024);
+    assert!(kept > 0 && kept < size as u64, "{} of {} bytes kept after the drop", kept, size);
+    kept
+}
+
+// Downloads a tenth of a second's worth of the link, interrupted once and then resumed by a
+// fresh manager from what the first session left on disk
+fn test_data_download(files: &HttpServer, files_proxy: SocketAddr, profile: &NetworkProfile) -> DownloadReport {
+    let (request, size) = download_request(files_proxy, profile);
+    let kept = interrupted_download(files, &request, size);
+    
+    let restarted = DownloadManager::new(BackoffPolicy::Exponential(Duration::from_millis(100)), 5);
+    let report = restarted.download(&request).expect("resumed download");
+    assert_eq!(report.resumed_from, kept);
+    assert_eq!(fs::metadata(&request.destination).unwrap().len(), size as u64);
+    assert!(DownloadManager::progress(&request).is_none(), "progress left behind after completion");
+    report
+}
+
+// Bytes corrupted on disk between sessions must fail verification and not be resumed again
+fn test_corrupted_download(files: &HttpServer, files_proxy: SocketAddr) {
+    let (request, size) = download_request(files_proxy, &NetworkProfile::lte());
+    let manager = DownloadManager::new(BackoffPolicy::Linear(Duration::from_millis(100)), 5)
+        .with_timeout(Duration::from_secs(2));
+    interrupted_download(files, &request, size);
+    
+    let part = DownloadManager::part_path(&request.destination);
+    let mut file = OpenOptions::new().write(true).open(&part).unwrap();
+    file.write_all(b"#").unwrap();
+    drop(file);
+    
+    match manager.download(&request) {
+        Err(DownloadError::ChecksumMismatch { .. }) => {}
+        other => panic!("corrupted download not detected: {:?}", other),
     }
+    assert!(!part.exists() && DownloadManager::progress(&request).is_none());
     
-    downloaded
+    let fresh = manager.download(&request).expect("download after discarding corrupt data");
+    assert_eq!(fresh.resumed_from, 0);
+    println!("Corrupted partial download rejected and fetched again ({}KB)", fresh.total_bytes / 1024);
 }
 
-fn simulate_network_handover() {
-    println!("  Simulating network handover...");
-    thread::sleep(Duration::from_millis(500));
+fn test_network_handover(config: HandoverConfig) -> Vec<FlowReport> {
+    println!("  Handover {} -> {}{}:", config.from.name, config.to.name, if config.silent { " (silent)" } else { "" });
+    let reports = handover::run_handover(&config).expect("handover scenario");
+    let recovery_budget = config.switch_delay + config.request_timeout * 2;
+    
+    for report in &reports {
+        println!("    {}", report);
+        let recovery = report.recovery.unwrap_or_else(|| panic!("{:?} never recovered", report.flow));
+        if config.silent {
+            if report.affected == Some(Outcome::Hung) {
+                assert!(recovery >= config.request_timeout);
+            }
+        } else {
+            assert_ne!(report.affected, Some(Outcome::Hung), "{:?} hung after a reset", report.flow);
+            assert!(recovery <= recovery_budget, "{:?} took {:?} to recover", report.flow, recovery);
+        }
+    }
+    reports
 }
 
-fn simulate_network_traffic(duration: Duration) {
+fn simulate_network_traffic(target: SocketAddr, duration: Duration) {
     let start = Instant::now();
     while start.elapsed() < duration {
-        let _ = test_http_requests(5);
+        let _ = test_http_requests(target, 5);
     }
 }
 
@@ -905,25 +1126,119 @@ fn black_box<T>(x: T) -> T {
     }
 }
 
-fn render_triangle() {
-    thread::sleep(Duration::from_micros(10));
+const GPU_WIDTH: usize = 480;
+const GPU_HEIGHT: usize = 270;
+
+fn random_color(rng: &mut StdRng, alpha: f32) -> [f32; 4] {
+    [rng.gen_range(0.2..1.0), rng.gen_range(0.2..1.0), rng.gen_range(0.2..1.0), alpha]
 }
 
-fn render_textured_quad() {
-    thread::sleep(Duration::from_micros(20));
+// Небольшой треугольник со случайной глубиной и градиентом по вершинам
+fn render_triangle(target: &mut Framebuffer, rng: &mut StdRng) -> RasterStats {
+    let (cx, cy) = (rng.gen_range(0.0..target.width() as f32), rng.gen_range(0.0..target.height() as f32));
+    let z = rng.gen_range(0.1..0.9);
+    let vertices = [0, 1, 2].map(|_| {
+        Vertex::new(cx + rng.gen_range(-12.0..12.0), cy + rng.gen_range(-12.0..12.0), z)
+            .with_color(random_color(rng, 1.0))
+    });
+    target.draw_triangle(&vertices, &DrawState::opaque())
 }
 
-fn run_compute_shader() {
-    thread::sleep(Duration::from_micros(30));
+// Полупрозрачный квад с билинейной выборкой текстуры
+fn render_textured_quad(target: &mut Framebuffer, texture: &Texture, rng: &mut StdRng) -> RasterStats {
+    let size = rng.gen_range(8.0..16.0);
+    let x = rng.gen_range(-size..target.width() as f32);
+    let y = rng.gen_range(-size..target.height() as f32);
+    let alpha = rng.gen_range(0.5..1.0);
+    let color = random_color(rng, alpha);
+    let state = DrawState::opaque().with_texture(texture).with_alpha_blend();
+    target.draw_triangles(&quad(x, y, size, size, rng.gen_range(0.0..0.5), color, 1.5), &state)
 }
 
-fn render_complex_scene() {
-    thread::sleep(Duration::from_micros(100));
+fn run_compute_shader(kernel: Kernel, source: &Framebuffer, output: &mut Framebuffer) -> u64 {
+    rasterizer::dispatch(kernel, source, output)
 }
 
-fn get_gpu_memory_usage() -> u64 {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(50..200) * 1024 * 1024
+// Детерминированная сцена: фон, сетка текстурированных квадов, вращающийся веер,
+// прозрачный слой поверх и размытие
+fn render_complex_scene(target: &mut Framebuffer, post: &mut Framebuffer, texture: &Texture, frame: u32) -> RasterStats {
+    let (width, height) = (target.width() as f32, target.height() as f32);
+    target.clear([0, 0, 0, 255]);
+    
+    let top = [0.1, 0.2, 0.4, 1.0];
+    let bottom = [0.8, 0.5, 0.3, 1.0];
+    let corner = |x: f32, y: f32, color| Vertex::new(x, y, 0.99).with_color(color);
+    let background = [
+        [corner(0.0, 0.0, top), corner(width, 0.0, top), corner(width, height, bottom)],
+        [corner(0.0, 0.0, top), corner(width, height, bottom), corner(0.0, height, bottom)],
+    ];
+    let mut stats = target.draw_triangles(&background, &DrawState::opaque());
+    
+    let textured = DrawState::opaque().with_texture(texture);
+    let (cell_w, cell_h) = (width / 8.0, height / 6.0);
+    for row in 0..6 {
+        for col in 0..8 {
+            let tint = [0.5 + col as f32 / 16.0, 0.5 + row as f32 / 12.0, 1.0, 1.0];
+            let tile = quad(col as f32 * cell_w + 4.0, row as f32 * cell_h + 4.0, cell_w - 8.0, cell_h - 8.0, 0.8, tint, 2.0);
+            stats += target.draw_triangles(&tile, &textured);
+        }
+    }
+    
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let radius = height * 0.45;
+    let spin = frame as f32 * 0.05;
+    for i in 0..64 {
+        let angle = |k: f32| spin + (i as f32 + k) * std::f32::consts::TAU / 64.0;
+        let color = [(i % 4) as f32 / 3.0, 1.0 - (i % 8) as f32 / 7.0, 0.6, 1.0];
+        let blade = [
+            Vertex::new(cx, cy, 0.2 + i as f32 / 200.0).with_color(color),
+            Vertex::new(cx + radius * angle(0.0).cos(), cy + radius * angle(0.0).sin(), 0.5).with_color(color),
+            Vertex::new(cx + radius * angle(0.6).cos(), cy + radius * angle(0.6).sin(), 0.5).with_color(color),
+        ];
+        stats += target.draw_triangle(&blade, &DrawState::opaque());
+    }
+    
+    let overlay = quad(width * 0.1, height * 0.6, width * 0.8, height * 0.3, 0.1, [1.0, 1.0, 1.0, 0.35], 4.0);
+    stats += target.draw_triangles(&overlay, &textured.with_alpha_blend());
+    
+    run_compute_shader(Kernel::BoxBlur(1), target, post);
+    stats
+}
+
+fn snapshot(target: &Framebuffer) -> Image {
+    Image::new(target.width() as u32, target.height() as u32, target.rgba().to_vec())
+}
+
+// Детерминированные сцены для сравнения с эталонами; `frame` задаёт кадр анимации
+fn reference_scenes(frame: u32) -> Vec<(&'static str, Image)> {
+    let texture = Texture::checkerboard(64, 8, [240, 240, 240, 255], [40, 90, 200, 255]);
+    let mut target = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+    let mut post = Framebuffer::new(GPU_WIDTH, GPU_HEIGHT);
+    let mut scenes = Vec::new();
+    
+    let mut rng = StdRng::seed_from_u64(50);
+    target.clear([0, 0, 0, 255]);
+    for _ in 0..300 {
+        render_triangle(&mut target, &mut rng);
+    }
+    scenes.push(("triangles", snapshot(&target)));
+    
+    target.clear([64, 64, 64, 255]);
+    for _ in 0..200 {
+        render_textured_quad(&mut target, &texture, &mut rng);
+    }
+    scenes.push(("textured_quads", snapshot(&target)));
+    
+    render_complex_scene(&mut target, &mut post, &texture, frame);
+    scenes.push(("complex_scene", snapshot(&post)));
+    
+    run_compute_shader(Kernel::EdgeDetect, &target, &mut post);
+    scenes.push(("edge_detect", snapshot(&post)));
+    scenes
+}
+
+fn get_gpu_memory_usage(targets: &[&Framebuffer], texture: &Texture) -> u64 {
+    (targets.iter().map(|target| target.bytes()).sum::<usize>() + texture.bytes()) as u64
 }
 
 fn capture_camera_frames(width: u32, height: u32, target_frames: u32) -> u32 {
@@ -0,0 +1,120 @@
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use rand::Rng;
+
+pub type Workload = Arc<dyn Fn(Arc<AtomicBool>, Arc<AtomicU64>) + Send + Sync>;
+
+// trig math, allocation churn, lock contention, sqrt/sin loops, scheduler yield storm
+pub fn cpu_workloads() -> Vec<Workload> {
+    vec![
+        Arc::new(|stop, counter| {
+            let mut rng = rand::thread_rng();
+            while !stop.load(Ordering::Relaxed) {
+                for _ in 0..1000 {
+                    let a: f64 = rng.gen_range(0.0..1.0);
+                    let b: f64 = rng.gen_range(0.0..1.0);
+                    black_box(a.sin() * b.cos() + (a * b).tan());
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now();
+            }
+        }),
+        Arc::new(|stop, counter| {
+            while !stop.load(Ordering::Relaxed) {
+                let size = rand::thread_rng().gen_range(1024..1024 * 1024);
+                let vec = vec![0u8; size];
+                drop(black_box(vec));
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }),
+        Arc::new(|stop, counter| {
+            let lock = Arc::new(Mutex::new(0u64));
+            let mut handles = vec![];
+
+            for _ in 0..5 {
+                let lock = Arc::clone(&lock);
+                let stop = Arc::clone(&stop);
+                let counter = Arc::clone(&counter);
+                handles.push(thread::spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        let mut data = lock.lock().unwrap();
+                        *data = data.wrapping_add(1);
+                        drop(data);
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        thread::yield_now();
+                    }
+                }));
+            }
+
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        }),
+        Arc::new(|stop, counter| {
+            while !stop.load(Ordering::Relaxed) {
+                let mut result = 0.0;
+                for i in 0..1000 {
+                    result += (i as f64).sqrt() * (i as f64).sin();
+                }
+                black_box(result);
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }),
+        Arc::new(|stop, counter| {
+            while !stop.load(Ordering::Relaxed) {
+                for _ in 0..100 {
+                    thread::yield_now();
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }),
+    ]
+}
+
+pub struct CpuStress {
+    stop: Arc<AtomicBool>,
+    operations: Arc<AtomicU64>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl CpuStress {
+    // Spreads `threads` evenly over the workloads (at least one thread each)
+    pub fn start(threads: usize) -> Self {
+        let workloads = cpu_workloads();
+        let per_workload = (threads / workloads.len()).max(1);
+        let stop = Arc::new(AtomicBool::new(false));
+        let operations = Arc::new(AtomicU64::new(0));
+        let mut handles = vec![];
+
+        for workload in &workloads {
+            for _ in 0..per_workload {
+                let workload = Arc::clone(workload);
+                let stop = Arc::clone(&stop);
+                let operations = Arc::clone(&operations);
+                handles.push(thread::spawn(move || workload(stop, operations)));
+            }
+        }
+
+        Self {
+            stop,
+            operations,
+            handles,
+        }
+    }
+
+    // Completed workload iterations so far, summed over all threads
+    pub fn operations(&self) -> u64 {
+        self.operations.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(mut self) -> u64 {
+        self.stop.store(true, Ordering::Relaxed);
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+        self.operations()
+    }
+}
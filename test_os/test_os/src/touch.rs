@@ -0,0 +1,354 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchAction {
+    Down,
+    Move,
+    Up,
+    PointerDown,
+    PointerUp,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerCoords {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+// MotionEvent-style sample: `pointers` includes the pointer going down or up in this event
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionEvent {
+    pub action: TouchAction,
+    pub action_pointer: u32,
+    pub pointers: Vec<PointerCoords>,
+    pub time: Duration,
+}
+
+impl MotionEvent {
+    pub fn pointer(&self, id: u32) -> Option<&PointerCoords> {
+        self.pointers.iter().find(|p| p.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gesture {
+    Tap { x: f32, y: f32 },
+    DoubleTap { x: f32, y: f32 },
+    LongPress { x: f32, y: f32 },
+    Swipe { direction: SwipeDirection, distance: f32, velocity: f32 },
+    Pinch { scale: f32 },
+    MultiTouch { fingers: usize },
+}
+
+impl Gesture {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gesture::Tap { .. } => "tap",
+            Gesture::DoubleTap { .. } => "double_tap",
+            Gesture::LongPress { .. } => "long_press",
+            Gesture::Swipe { .. } => "swipe",
+            Gesture::Pinch { .. } => "pinch",
+            Gesture::MultiTouch { .. } => "multitouch",
+        }
+    }
+}
+
+// Defaults follow Android ViewConfiguration at ~2.75 density (8dp slop, 100dp double-tap slop)
+#[derive(Debug, Clone, Copy)]
+pub struct GestureConfig {
+    pub touch_slop: f32,
+    pub double_tap_slop: f32,
+    pub long_press_timeout: Duration,
+    pub double_tap_timeout: Duration,
+    pub pinch_threshold: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            touch_slop: 22.0,
+            double_tap_slop: 275.0,
+            long_press_timeout: Duration::from_millis(500),
+            double_tap_timeout: Duration::from_millis(300),
+            pinch_threshold: 0.1,
+        }
+    }
+}
+
+pub struct GestureRecognizer {
+    config: GestureConfig,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+struct Stroke<'a> {
+    events: &'a [MotionEvent],
+}
+
+impl<'a> Stroke<'a> {
+    fn start(&self) -> Duration {
+        self.events[0].time
+    }
+
+    fn end(&self) -> Duration {
+        self.events[self.events.len() - 1].time
+    }
+
+    fn max_fingers(&self) -> usize {
+        self.events.iter().map(|e| e.pointers.len()).max().unwrap_or(0)
+    }
+
+    fn first_point(&self) -> (f32, f32) {
+        let p = &self.events[0].pointers[0];
+        (p.x, p.y)
+    }
+
+    fn last_point(&self, id: u32) -> (f32, f32) {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|e| e.pointer(id))
+            .map_or(self.first_point(), |p| (p.x, p.y))
+    }
+
+    fn max_travel(&self, id: u32) -> f32 {
+        let (x0, y0) = self.first_point();
+        self.events
+            .iter()
+            .filter_map(|e| e.pointer(id))
+            .map(|p| distance((x0, y0), (p.x, p.y)))
+            .fold(0.0, f32::max)
+    }
+
+    fn spans(&self) -> Option<(f32, f32)> {
+        let two_down: Vec<&MotionEvent> =
+            self.events.iter().filter(|e| e.pointers.len() >= 2).collect();
+        let first = two_down.first()?;
+        let last = two_down.last()?;
+        Some((span(first), span(last)))
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn recognize(&self, events: &[MotionEvent]) -> Vec<Gesture> {
+        let mut gestures: Vec<(Gesture, Duration, Duration)> = Vec::new();
+
+        for stroke in split_strokes(events) {
+            let gesture = self.classify_stroke(&stroke);
+
+            // A tap that closely follows another tap upgrades it to a double tap
+            if let Gesture::Tap { x, y } = gesture {
+                if let Some((Gesture::Tap { x: px, y: py }, _, prev_end)) = gestures.last() {
+                    let gap = stroke.start().saturating_sub(*prev_end);
+                    if gap <= self.config.double_tap_timeout
+                        && distance((*px, *py), (x, y)) <= self.config.double_tap_slop
+                    {
+                        let (px, py) = (*px, *py);
+                        gestures.pop();
+                        gestures.push((Gesture::DoubleTap { x: px, y: py }, stroke.start(), stroke.end()));
+                        continue;
+                    }
+                }
+            }
+
+            gestures.push((gesture, stroke.start(), stroke.end()));
+        }
+
+        gestures.into_iter().map(|(gesture, _, _)| gesture).collect()
+    }
+
+    fn classify_stroke(&self, stroke: &Stroke) -> Gesture {
+        let fingers = stroke.max_fingers();
+
+        if fingers >= 2 {
+            if let Some((start_span, end_span)) = stroke.spans() {
+                let scale = if start_span > 0.0 { end_span / start_span } else { 1.0 };
+                if (scale - 1.0).abs() >= self.config.pinch_threshold {
+                    return Gesture::Pinch { scale };
+                }
+            }
+            return Gesture::MultiTouch { fingers };
+        }
+
+        let id = stroke.events[0].action_pointer;
+        let (x, y) = stroke.first_point();
+        let duration = stroke.end().saturating_sub(stroke.start());
+
+        if stroke.max_travel(id) > self.config.touch_slop {
+            let (x1, y1) = stroke.last_point(id);
+            let (dx, dy) = (x1 - x, y1 - y);
+            let direction = if dx.abs() >= dy.abs() {
+                if dx >= 0.0 { SwipeDirection::Right } else { SwipeDirection::Left }
+            } else if dy >= 0.0 {
+                SwipeDirection::Down
+            } else {
+                SwipeDirection::Up
+            };
+            let distance = distance((x, y), (x1, y1));
+            let seconds = duration.as_secs_f32().max(0.001);
+            return Gesture::Swipe { direction, distance, velocity: distance / seconds };
+        }
+
+        if duration >= self.config.long_press_timeout {
+            Gesture::LongPress { x, y }
+        } else {
+            Gesture::Tap { x, y }
+        }
+    }
+}
+
+fn split_strokes(events: &[MotionEvent]) -> Vec<Stroke<'_>> {
+    let mut strokes = Vec::new();
+    let mut start = None;
+
+    for (i, event) in events.iter().enumerate() {
+        match event.action {
+            TouchAction::Down => start = Some(i),
+            TouchAction::Up | TouchAction::Cancel => {
+                if let Some(s) = start.take() {
+                    if event.action == TouchAction::Up {
+                        strokes.push(Stroke { events: &events[s..=i] });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    strokes
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn span(event: &MotionEvent) -> f32 {
+    let a = &event.pointers[0];
+    let b = &event.pointers[1];
+    distance((a.x, a.y), (b.x, b.y))
+}
+
+// Synthetic stream builders; `start` is the stream timestamp of the first DOWN
+
+const MOVE_INTERVAL: Duration = Duration::from_millis(8);
+
+fn pointer(id: u32, x: f32, y: f32) -> PointerCoords {
+    PointerCoords { id, x, y, pressure: 0.8 }
+}
+
+fn event(action: TouchAction, action_pointer: u32, pointers: Vec<PointerCoords>, time: Duration) -> MotionEvent {
+    MotionEvent { action, action_pointer, pointers, time }
+}
+
+pub fn tap_stream(x: f32, y: f32, start: Duration) -> Vec<MotionEvent> {
+    press_stream(x, y, start, Duration::from_millis(60))
+}
+
+pub fn double_tap_stream(x: f32, y: f32, start: Duration) -> Vec<MotionEvent> {
+    let mut events = tap_stream(x, y, start);
+    events.extend(tap_stream(x + 4.0, y + 3.0, start + Duration::from_millis(160)));
+    events
+}
+
+pub fn long_press_stream(x: f32, y: f32, start: Duration) -> Vec<MotionEvent> {
+    press_stream(x, y, start, Duration::from_millis(650))
+}
+
+fn press_stream(x: f32, y: f32, start: Duration, hold: Duration) -> Vec<MotionEvent> {
+    let mut events = vec![event(TouchAction::Down, 0, vec![pointer(0, x, y)], start)];
+
+    // Finger jitter well inside the touch slop
+    let mut t = MOVE_INTERVAL;
+    let mut jitter = 1.5;
+    while t < hold {
+        events.push(event(TouchAction::Move, 0, vec![pointer(0, x + jitter, y)], start + t));
+        jitter = -jitter;
+        t += MOVE_INTERVAL;
+    }
+
+    events.push(event(TouchAction::Up, 0, vec![pointer(0, x, y)], start + hold));
+    events
+}
+
+pub fn swipe_stream(from: (f32, f32), to: (f32, f32), duration: Duration, start: Duration) -> Vec<MotionEvent> {
+    let steps = (duration.as_millis() / MOVE_INTERVAL.as_millis()).max(1) as u32;
+    let mut events = vec![event(TouchAction::Down, 0, vec![pointer(0, from.0, from.1)], start)];
+
+    for step in 1..=steps {
+        let f = step as f32 / steps as f32;
+        let p = pointer(0, from.0 + (to.0 - from.0) * f, from.1 + (to.1 - from.1) * f);
+        let action = if step == steps { TouchAction::Up } else { TouchAction::Move };
+        events.push(event(action, 0, vec![p], start + duration * step / steps));
+    }
+
+    events
+}
+
+pub fn pinch_stream(center: (f32, f32), start_span: f32, end_span: f32, duration: Duration, start: Duration) -> Vec<MotionEvent> {
+    let fingers = |span: f32| {
+        vec![
+            pointer(0, center.0 - span / 2.0, center.1),
+            pointer(1, center.0 + span / 2.0, center.1),
+        ]
+    };
+    let steps = (duration.as_millis() / MOVE_INTERVAL.as_millis()).max(1) as u32;
+
+    let mut events = vec![
+        event(TouchAction::Down, 0, fingers(start_span)[..1].to_vec(), start),
+        event(TouchAction::PointerDown, 1, fingers(start_span), start + Duration::from_millis(4)),
+    ];
+
+    for step in 1..=steps {
+        let f = step as f32 / steps as f32;
+        let span = start_span + (end_span - start_span) * f;
+        events.push(event(TouchAction::Move, 0, fingers(span), start + duration * step / steps));
+    }
+
+    let end = start + duration;
+    let last = fingers(end_span);
+    events.push(event(TouchAction::PointerUp, 1, last.clone(), end + Duration::from_millis(4)));
+    events.push(event(TouchAction::Up, 0, last[..1].to_vec(), end + Duration::from_millis(8)));
+    events
+}
+
+pub fn multitouch_stream(fingers: u32, center: (f32, f32), start: Duration) -> Vec<MotionEvent> {
+    let placed: Vec<PointerCoords> = (0..fingers)
+        .map(|id| pointer(id, center.0 + id as f32 * 120.0, center.1))
+        .collect();
+
+    let mut events = Vec::new();
+    let mut t = start;
+    for id in 0..fingers as usize {
+        let action = if id == 0 { TouchAction::Down } else { TouchAction::PointerDown };
+        events.push(event(action, id as u32, placed[..=id].to_vec(), t));
+        t += Duration::from_millis(5);
+    }
+
+    t += Duration::from_millis(80);
+    for id in (0..fingers as usize).rev() {
+        let action = if id == 0 { TouchAction::Up } else { TouchAction::PointerUp };
+        events.push(event(action, id as u32, placed[..=id].to_vec(), t));
+        t += Duration::from_millis(5);
+    }
+
+    events
+}